@@ -0,0 +1,321 @@
+use crate::dpkg::{native_architecture, parse_paragraph};
+use crate::error::{Error, Result};
+use std::collections::{HashMap, HashSet};
+use std::process::{Command, Output};
+
+const DPKG_STATUS_SOURCE: &str = "/var/lib/dpkg/status";
+const INSTALLED_MARKER: &str = "***";
+const NO_INSTALLED_VERSION: &str = "(none)";
+const INSTALL_PREFIX: &str = "Inst ";
+const REMOVAL_PREFIX: &str = "Remv ";
+// Separates the component from the section for packages outside of main, e.g. `non-free/libs`.
+const SECTION_SEPARATOR: char = '/';
+const SOURCE_INDENT: &str = "        ";
+
+pub(crate) struct PolicyVersion {
+    pub(crate) installed: bool,
+    // Sources as printed in the version table without the pin priority, e.g.
+    // `http://deb.debian.org/debian bookworm/main amd64 Packages`.
+    pub(crate) sources: Vec<String>,
+    pub(crate) version: String,
+}
+
+impl PolicyVersion {
+    // A version only known from the dpkg status file is not downloadable from any repository.
+    pub(crate) fn is_local_only(&self) -> bool {
+        self.sources.iter().all(|s| s == DPKG_STATUS_SOURCE)
+    }
+
+    // Repositories providing the version, named by their location without the URL scheme and
+    // their suite and component, e.g. `deb.debian.org/debian-security bookworm-security/main`.
+    pub(crate) fn origins(&self) -> Vec<String> {
+        self.sources
+            .iter()
+            .filter(|s| *s != DPKG_STATUS_SOURCE)
+            .map(|s| {
+                let fields: Vec<&str> = s.split_whitespace().collect();
+                let location = fields.first().copied().unwrap_or_default();
+                let location = location
+                    .split_once("://")
+                    .map(|(_, l)| l)
+                    .unwrap_or(location);
+                match fields.get(1) {
+                    Some(suite) => format!("{} {suite}", location.trim_end_matches('/')),
+                    None => location.to_string(),
+                }
+            })
+            .collect()
+    }
+}
+
+pub(crate) struct PackagePolicy {
+    pub(crate) candidate: Option<String>,
+    pub(crate) installed: Option<String>,
+    pub(crate) name: String,
+    pub(crate) versions: Vec<PolicyVersion>,
+}
+
+impl PackagePolicy {
+    fn new(name: &str) -> PackagePolicy {
+        PackagePolicy {
+            candidate: None,
+            installed: None,
+            name: name.to_string(),
+            versions: vec![],
+        }
+    }
+
+    pub(crate) fn installed_version(&self) -> Option<&PolicyVersion> {
+        self.versions.iter().find(|v| v.installed)
+    }
+
+    pub(crate) fn version(&self, version: &str) -> Option<&PolicyVersion> {
+        self.versions.iter().find(|v| v.version == version)
+    }
+}
+
+// Where a package is filed in the archive, as given by its candidate version.
+pub(crate) struct PackageClass {
+    pub(crate) priority: Option<String>,
+    pub(crate) section: Option<String>,
+}
+
+impl PackageClass {
+    // Sections match with or without their component.
+    pub(crate) fn in_section(&self, section: &str) -> bool {
+        self.section.as_ref().is_some_and(|s| {
+            s.eq_ignore_ascii_case(section)
+                || s.rsplit(SECTION_SEPARATOR)
+                    .next()
+                    .is_some_and(|s| s.eq_ignore_ascii_case(section))
+        })
+    }
+
+    pub(crate) fn has_priority(&self, priority: &str) -> bool {
+        self.priority
+            .as_ref()
+            .is_some_and(|p| p.eq_ignore_ascii_case(priority))
+    }
+
+    pub(crate) fn describe(&self) -> String {
+        [&self.section, &self.priority]
+            .iter()
+            .filter_map(|f| f.as_deref())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+fn version_value(value: &str) -> Option<String> {
+    let value = value.trim();
+    if value == NO_INSTALLED_VERSION {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+fn parse_policy(output: &str) -> Vec<PackagePolicy> {
+    let mut policies: Vec<PackagePolicy> = vec![];
+
+    for line in output.lines() {
+        if !line.starts_with(' ') {
+            if let Some(name) = line.strip_suffix(':') {
+                policies.push(PackagePolicy::new(name));
+            }
+            continue;
+        }
+        let Some(policy) = policies.last_mut() else {
+            continue;
+        };
+
+        let trimmed = line.trim();
+        if let Some(installed) = trimmed.strip_prefix("Installed:") {
+            policy.installed = version_value(installed);
+        } else if let Some(candidate) = trimmed.strip_prefix("Candidate:") {
+            policy.candidate = version_value(candidate);
+        } else if trimmed.starts_with("Version table:") {
+            continue;
+        } else if line.starts_with(SOURCE_INDENT) {
+            if let Some(version) = policy.versions.last_mut() {
+                // Strip the pin priority preceding the source.
+                let source = trimmed.split_once(' ').map(|(_, s)| s).unwrap_or(trimmed);
+                version.sources.push(source.to_string());
+            }
+        } else {
+            let installed = trimmed.starts_with(INSTALLED_MARKER);
+            let version = trimmed
+                .trim_start_matches(INSTALLED_MARKER)
+                .split_whitespace()
+                .next()
+                .unwrap_or_default();
+            policy.versions.push(PolicyVersion {
+                installed,
+                sources: vec![],
+                version: version.to_string(),
+            });
+        }
+    }
+
+    policies
+}
+
+// Output of an apt command, which fails if apt is not installed, e.g. in containers or on other
+// distributions a bundle is read on.
+fn apt_output(command: &mut Command, name: &str) -> Result<Output> {
+    command.output().map_err(|source| Error::Spawn {
+        command: name.to_string(),
+        source,
+    })
+}
+
+// Output of a simulation of `apt-get`, which has to succeed.
+fn simulation(command: &str) -> Result<String> {
+    let name = format!("apt-get --simulate {command}");
+    let output = apt_output(Command::new("apt-get").args(["--simulate", command]), &name)?;
+    if !output.status.success() {
+        return Err(Error::program_failed(&name, &output));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+// Policies of the given packages, as reported by `apt-cache policy`. Packages of the native
+// architecture are named without an architecture qualifier in the output.
+fn policies(packages: &[String]) -> Result<Vec<PackagePolicy>> {
+    if packages.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let output = apt_output(
+        Command::new("apt-cache").arg("policy").args(packages),
+        "apt-cache policy",
+    )?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(parse_policy(&stdout))
+}
+
+// Policies of the given `name:arch` packages along with the package each belongs to, resolving
+// the unqualified names apt uses for packages of the native architecture and architecture
+// independent ones.
+pub(crate) fn package_policies(packages: &[String]) -> Result<Vec<(String, PackagePolicy)>> {
+    let native_arch = native_architecture()?;
+    let requested: HashSet<&String> = packages.iter().collect();
+
+    Ok(policies(packages)?
+        .into_iter()
+        .filter_map(|policy| {
+            let package = if policy.name.contains(':') {
+                Some(policy.name.clone())
+            } else {
+                [native_arch.as_str(), "all"]
+                    .iter()
+                    .map(|arch| format!("{}:{arch}", policy.name))
+                    .find(|p| requested.contains(p))
+            };
+            Some((package?, policy))
+        })
+        .collect())
+}
+
+// Sections and priorities of the given `name:arch` packages, as reported by `apt-cache show`.
+// Packages apt does not know anymore are missing. The history log names architecture independent
+// packages by the native architecture, while apt-cache names them by `all`.
+pub(crate) fn package_classes(packages: &[String]) -> Result<HashMap<String, PackageClass>> {
+    if packages.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let native_arch = native_architecture()?;
+    let requested: HashSet<&String> = packages.iter().collect();
+
+    // Unknown packages make apt-cache fail but the known ones are still shown.
+    let output = apt_output(
+        Command::new("apt-cache")
+            .args(["show", "--no-all-versions"])
+            .args(packages),
+        "apt-cache show",
+    )?;
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .split("\n\n")
+        .map(parse_paragraph)
+        .filter_map(|mut fields| {
+            let name = fields.get("Package")?;
+            let package = [fields.get("Architecture")?.as_str(), &native_arch]
+                .iter()
+                .map(|arch| format!("{name}:{arch}"))
+                .find(|p| requested.contains(p))?;
+            let class = PackageClass {
+                priority: fields.remove("Priority"),
+                section: fields.remove("Section"),
+            };
+            Some((package, class))
+        })
+        .collect())
+}
+
+// A change `apt-get --simulate` reports, with versions as apt-cache shows them.
+pub(crate) struct SimulatedChange {
+    pub(crate) action: &'static str,
+    pub(crate) from: Option<String>,
+    // Package as `name:arch`, with architecture independent packages named by the native
+    // architecture like the history log does.
+    pub(crate) package: String,
+    pub(crate) to: Option<String>,
+}
+
+// e.g. `Inst bash [5.2.15-2+b8] (5.2.15-2+b13 Debian:12.14/oldstable [amd64])` or
+// `Remv libfoo1 [1.2-1]`, the installed version is missing for new packages.
+fn parse_simulated_change(line: &str, native_arch: &str) -> Option<SimulatedChange> {
+    let (action, rest) = if let Some(rest) = line.strip_prefix(INSTALL_PREFIX) {
+        ("Install", rest)
+    } else {
+        ("Remove", line.strip_prefix(REMOVAL_PREFIX)?)
+    };
+    let (name, rest) = rest.split_once(' ').unwrap_or((rest, ""));
+    let from = rest
+        .strip_prefix('[')
+        .and_then(|r| r.split_once(']'))
+        .map(|(version, _)| version.to_string());
+    let candidate = rest
+        .split_once('(')
+        .and_then(|(_, r)| r.split_once(')'))
+        .map(|(c, _)| c);
+    let to = candidate.and_then(|c| c.split_whitespace().next().map(|v| v.to_string()));
+    let arch = candidate
+        .and_then(|c| c.rsplit_once('[')?.1.strip_suffix(']'))
+        .filter(|a| *a != "all")
+        .unwrap_or(native_arch);
+    let (name, arch) = name.split_once(':').unwrap_or((name, arch));
+    let action = match (action, &from) {
+        ("Install", Some(_)) => "Upgrade",
+        (action, _) => action,
+    };
+    Some(SimulatedChange {
+        action,
+        from,
+        package: format!("{name}:{arch}"),
+        to,
+    })
+}
+
+// Changes `apt-get upgrade` would make, found by simulating it. Downgrades are reported as
+// upgrades as apt does not tell them apart in simulations.
+pub(crate) fn simulated_upgrade() -> Result<Vec<SimulatedChange>> {
+    let output = simulation("upgrade")?;
+    let native_arch = native_architecture()?;
+    Ok(output
+        .lines()
+        .filter_map(|l| parse_simulated_change(l, &native_arch))
+        .collect())
+}
+
+// Packages `apt-get autoremove` would remove, found by simulating it. Packages are named as apt
+// names them, with an architecture qualifier only for those of foreign architectures.
+pub(crate) fn autoremove_candidates() -> Result<Vec<String>> {
+    // e.g. `Remv libfoo1 [1.2-1]`
+    Ok(simulation("autoremove")?
+        .lines()
+        .filter_map(|l| l.strip_prefix(REMOVAL_PREFIX))
+        .filter_map(|l| l.split_whitespace().next())
+        .map(|p| p.to_string())
+        .collect())
+}
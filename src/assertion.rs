@@ -0,0 +1,52 @@
+use crate::date::parse_date;
+use crate::error::{Error, Result};
+use crate::history::{history_entries, HistoryEntry};
+use std::collections::HashSet;
+
+fn touches(entry: &HistoryEntry, packages: &HashSet<String>) -> bool {
+    entry
+        .affected
+        .values()
+        .flat_map(|pkg_map| pkg_map.values())
+        .any(|pkgs| !pkgs.is_disjoint(packages))
+}
+
+// Exits with a non-zero status if any transaction violates the assertions: no transactions at
+// all since the given date, or none changing the given packages, within the date range if one
+// is given.
+pub fn assert(since: Option<String>, unchanged_packages: Vec<String>) -> Result<()> {
+    if since.is_none() && unchanged_packages.is_empty() {
+        return Err(Error::Usage(
+            "no assertion given, use `--no-changes-since` and/or `--package-unchanged`".to_string(),
+        ));
+    }
+
+    let since_date = since
+        .as_deref()
+        .map(parse_date)
+        .transpose()
+        .map_err(Error::Usage)?;
+    let packages: HashSet<String> = unchanged_packages.iter().cloned().collect();
+
+    let violations: Vec<String> = history_entries()?
+        .iter()
+        .filter(|e| since_date.is_none_or(|d| e.start_date >= d))
+        .filter(|e| packages.is_empty() || touches(e, &packages))
+        .map(|e| e.id.to_string())
+        .collect();
+    if violations.is_empty() {
+        return Ok(());
+    }
+
+    let mut assertion = String::from("changes");
+    if !unchanged_packages.is_empty() {
+        assertion.push_str(&format!(" to {}", unchanged_packages.join(", ")));
+    }
+    if let Some(since) = since {
+        assertion.push_str(&format!(" since {since}"));
+    }
+    Err(Error::AssertionFailed(format!(
+        "{assertion} in transaction(s) {}",
+        violations.join(", ")
+    )))
+}
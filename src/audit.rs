@@ -0,0 +1,75 @@
+use crate::apt::package_policies;
+use crate::dpkg::status_packages;
+use crate::error::Result;
+use crate::history::history_entries;
+use std::collections::{HashMap, HashSet};
+use stybulate::{Cell, Headers, Style, Table};
+
+const HEADERS: [&str; 4] = ["Package", "Installed version", "Candidate", "Installed in"];
+const INSTALLING_ACTIONS: [&str; 4] = ["Install", "Upgrade", "Downgrade", "Reinstall"];
+
+// Last transaction installing each package in `name:arch` form.
+fn last_installs() -> Result<HashMap<String, u32>> {
+    let mut installs: HashMap<String, u32> = HashMap::new();
+    for entry in history_entries()? {
+        for (action, pkg_map) in entry.affected.iter() {
+            if !INSTALLING_ACTIONS.contains(&action.as_str()) {
+                continue;
+            }
+            for (arch, pkgs) in pkg_map.iter() {
+                for pkg in pkgs {
+                    installs.insert(format!("{pkg}:{arch}"), entry.id);
+                }
+            }
+        }
+    }
+    Ok(installs)
+}
+
+pub fn audit() -> Result<()> {
+    let installs = last_installs()?;
+    let installed: HashSet<String> = status_packages()
+        .iter()
+        .filter(|p| p.is_installed())
+        .map(|p| format!("{}:{}", p.name, p.arch))
+        .collect();
+
+    let mut packages: Vec<String> = installs
+        .keys()
+        .filter(|p| installed.contains(*p))
+        .cloned()
+        .collect();
+    packages.sort();
+
+    let mut rows: Vec<Vec<Cell>> = Vec::new();
+    for (package, policy) in package_policies(&packages)? {
+        let Some(version) = policy.installed_version() else {
+            continue;
+        };
+        if !version.is_local_only() {
+            continue;
+        }
+
+        let id = installs
+            .get(&package)
+            .expect("unexpected entry miss in map");
+
+        rows.push(vec![
+            Cell::from(&package),
+            Cell::from(&version.version),
+            Cell::from(policy.candidate.as_deref().unwrap_or("")),
+            Cell::Int(*id as i32),
+        ]);
+    }
+
+    if rows.is_empty() {
+        println!(
+            "All packages installed by transactions are available from configured repositories"
+        );
+        return Ok(());
+    }
+
+    let table = Table::new(Style::Presto, rows, Some(Headers::from(HEADERS.to_vec()))).tabulate();
+    println!("{}", table);
+    Ok(())
+}
@@ -0,0 +1,136 @@
+use crate::apt::autoremove_candidates;
+use crate::dpkg::native_architecture;
+use crate::error::Result;
+use crate::history::{history_entries, HistoryEntry};
+use std::collections::HashMap;
+use stybulate::{Cell, Headers, Style, Table};
+
+const HEADERS: [&str; 3] = ["Package", "Installed in", "Reason"];
+const INSTALL_ACTION: &str = "Install";
+const REMOVING_ACTIONS: [&str; 2] = ["Remove", "Purge"];
+
+// Last installing and removing transaction of each `name:arch` package.
+#[derive(Default)]
+struct PackageHistory {
+    installs: HashMap<String, u32>,
+    removals: HashMap<String, u32>,
+}
+
+impl PackageHistory {
+    fn new(entries: &[HistoryEntry]) -> PackageHistory {
+        let mut history = PackageHistory::default();
+        for entry in entries {
+            for (action, pkg_map) in entry.affected.iter() {
+                let changes = if action == INSTALL_ACTION {
+                    &mut history.installs
+                } else if REMOVING_ACTIONS.contains(&action.as_str()) {
+                    &mut history.removals
+                } else {
+                    continue;
+                };
+                for (arch, pkgs) in pkg_map.iter() {
+                    for pkg in pkgs {
+                        changes.insert(format!("{pkg}:{arch}"), entry.id);
+                    }
+                }
+            }
+        }
+        history
+    }
+
+    fn is_removed(&self, package: &str) -> Option<u32> {
+        let removal = *self.removals.get(package)?;
+        let install = self.installs.get(package).copied().unwrap_or_default();
+        (removal > install).then_some(removal)
+    }
+}
+
+// Packages installed on request in an entry, as opposed to those pulled in as dependencies.
+fn requested_packages(entry: &HistoryEntry) -> Vec<String> {
+    let Some(pkg_map) = entry.affected.get(INSTALL_ACTION) else {
+        return vec![];
+    };
+    let mut requested: Vec<String> = pkg_map
+        .iter()
+        .flat_map(|(arch, pkgs)| pkgs.iter().map(move |pkg| format!("{pkg}:{arch}")))
+        .filter(|p| !entry.is_automatic(INSTALL_ACTION, p))
+        .collect();
+    requested.sort();
+    requested
+}
+
+// Why a package installed in the given entry is no longer needed.
+fn reason(entry: &HistoryEntry, package: &str, history: &PackageHistory) -> String {
+    if !entry.is_automatic(INSTALL_ACTION, package) {
+        return "installed on request, marked as automatically installed since".to_string();
+    }
+    let requested = requested_packages(entry);
+    if requested.is_empty() {
+        return format!(
+            "dependency pulled in by `{}`, no longer required",
+            entry.command_line
+        );
+    }
+    let removed: Vec<String> = requested
+        .iter()
+        .filter_map(|p| Some(format!("{p} (removed in {})", history.is_removed(p)?)))
+        .collect();
+    if removed.is_empty() {
+        format!(
+            "dependency of {}, no longer required by them",
+            requested.join(", ")
+        )
+    } else {
+        format!("dependency of {}", removed.join(", "))
+    }
+}
+
+// Packages apt would autoremove with the change that made each of them removable, as far as the
+// history tells.
+pub fn autoremove_preview() -> Result<()> {
+    let candidates = autoremove_candidates()?;
+    if candidates.is_empty() {
+        println!("No packages would be autoremoved");
+        return Ok(());
+    }
+
+    let entries = history_entries()?;
+    let history = PackageHistory::new(&entries);
+    let by_id: HashMap<u32, &HistoryEntry> = entries.iter().map(|e| (e.id, e)).collect();
+    let native_arch = native_architecture()?;
+
+    let rows: Vec<Vec<Cell>> = candidates
+        .iter()
+        .map(|candidate| {
+            // Packages of the native architecture and architecture independent ones are named
+            // without a qualifier by apt.
+            let package = if candidate.contains(':') {
+                Some(candidate.clone())
+            } else {
+                [native_arch.as_str(), "all"]
+                    .iter()
+                    .map(|arch| format!("{candidate}:{arch}"))
+                    .find(|p| history.installs.contains_key(p))
+            };
+            let install = package.and_then(|p| Some((by_id.get(history.installs.get(&p)?)?, p)));
+            match install {
+                Some((entry, package)) => vec![
+                    Cell::from(&package),
+                    Cell::Int(entry.id as i32),
+                    Cell::from(&reason(entry, &package, &history)),
+                ],
+                None => vec![
+                    Cell::from(candidate),
+                    Cell::from(""),
+                    Cell::from("no recorded installation"),
+                ],
+            }
+        })
+        .collect();
+    let headers = Headers::from(HEADERS.to_vec());
+    println!(
+        "{}",
+        Table::new(Style::Presto, rows, Some(headers)).tabulate()
+    );
+    Ok(())
+}
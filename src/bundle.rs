@@ -0,0 +1,195 @@
+use crate::dpkg::{APT_EXTENDED_STATES_PATH, DPKG_STATUS_PATH};
+use crate::error::{Error, Result};
+use crate::history::{APT_LOG_PATH, HOSTNAME_PATH};
+use crate::privileges;
+use crate::storage;
+use crate::uuids;
+use crate::vulns::OS_RELEASE_PATH;
+use chrono::prelude::*;
+use std::env;
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+use std::sync::OnceLock;
+
+// Log files captured from the apt log directory, including rotated ones.
+const LOG_PREFIXES: [&str; 2] = ["history.log", "term.log"];
+const METADATA_FILE: &str = "apt-history.meta";
+const SYSTEM_FILES: [&str; 5] = [
+    APT_EXTENDED_STATES_PATH,
+    DPKG_STATUS_PATH,
+    HOSTNAME_PATH,
+    OS_RELEASE_PATH,
+    uuids::INDEX_PATH,
+];
+
+// Directory a bundle given with `--bundle` is extracted to, system paths are read from there.
+static ROOT: OnceLock<PathBuf> = OnceLock::new();
+
+fn output_error(path: &Path) -> impl FnOnce(io::Error) -> Error + '_ {
+    move |source| Error::OutputFile {
+        path: path.display().to_string(),
+        source,
+    }
+}
+
+fn work_dir(purpose: &str) -> Result<PathBuf> {
+    let dir = env::temp_dir().join(format!("apt-history-{purpose}-{}", process::id()));
+    fs::create_dir_all(&dir).map_err(output_error(&dir))?;
+    Ok(dir)
+}
+
+// Runs tar, whose failures are reported with its error output rather than its exit status.
+fn run_tar(args: &[&OsStr]) -> Result<()> {
+    let output = Command::new("tar")
+        .arg("--zstd")
+        .args(args)
+        .output()
+        .map_err(|source| Error::Spawn {
+            command: "tar".to_string(),
+            source,
+        })?;
+    if !output.status.success() {
+        let args: Vec<_> = args.iter().map(|a| a.to_string_lossy()).collect();
+        return Err(Error::program_failed(
+            &format!("tar --zstd {}", args.join(" ")),
+            &output,
+        ));
+    }
+    Ok(())
+}
+
+// Copies a system file to the same path below the bundle directory.
+fn capture(dir: &Path, path: &Path) -> Result<()> {
+    let content = match fs::read(path) {
+        Ok(content) => content,
+        // Files missing on this system are left out as well.
+        Err(e) => {
+            privileges::is_unavailable(&path.display().to_string(), &e);
+            return Ok(());
+        }
+    };
+    let target = dir.join(path.strip_prefix("/").unwrap_or(path));
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).map_err(output_error(parent))?;
+    }
+    fs::write(&target, content).map_err(output_error(&target))
+}
+
+fn tool_version(program: &str) -> String {
+    Command::new(program)
+        .arg("--version")
+        .output()
+        .ok()
+        .and_then(|o| String::from_utf8(o.stdout).ok())
+        .and_then(|o| o.lines().next().map(|l| l.to_string()))
+        .unwrap_or_default()
+}
+
+fn metadata() -> String {
+    [
+        format!("apt-history = {}", env!("CARGO_PKG_VERSION")),
+        format!("apt = {}", tool_version("apt-get")),
+        format!("dpkg = {}", tool_version("dpkg")),
+        format!("created = {}", Local::now().format("%FT%T%:z")),
+    ]
+    .join("\n")
+        + "\n"
+}
+
+// Writes the apt logs, dpkg state and tool versions into a zstd compressed tarball, which can be
+// read back with `--bundle` on another machine.
+pub fn bundle(output: Option<Vec<String>>) -> Result<()> {
+    let output = match output.as_deref() {
+        Some([output]) => output.to_string(),
+        _ => {
+            return Err(Error::Usage(
+                "`bundle` requires a single output path, e.g. `bundle history.tar.zst`".to_string(),
+            ))
+        }
+    };
+    let dir_error = |source| Error::LogDir {
+        path: APT_LOG_PATH.to_string(),
+        source,
+    };
+    let log_dir = fs::read_dir(APT_LOG_PATH).map_err(dir_error)?;
+    let dir = work_dir("bundle")?;
+    let result = write_bundle(log_dir, &dir, Path::new(&output));
+    let _ = fs::remove_dir_all(&dir);
+    result
+}
+
+fn write_bundle(log_dir: fs::ReadDir, dir: &Path, output: &Path) -> Result<()> {
+    let dir_error = |source| Error::LogDir {
+        path: APT_LOG_PATH.to_string(),
+        source,
+    };
+    for entry in log_dir {
+        let entry = entry.map_err(dir_error)?;
+        let name = entry.file_name();
+        if LOG_PREFIXES
+            .iter()
+            .any(|p| name.to_string_lossy().starts_with(p))
+        {
+            capture(dir, &entry.path())?;
+        }
+    }
+    for path in SYSTEM_FILES {
+        capture(dir, Path::new(path))?;
+    }
+    let metadata_path = dir.join(METADATA_FILE);
+    fs::write(&metadata_path, metadata()).map_err(output_error(&metadata_path))?;
+
+    storage::replace(output, |temporary| {
+        run_tar(&[
+            OsStr::new("--create"),
+            OsStr::new("--file"),
+            temporary.as_os_str(),
+            OsStr::new("--directory"),
+            dir.as_os_str(),
+            OsStr::new("."),
+        ])
+    })
+}
+
+// Reads system paths from a bundle instead of the running system.
+pub fn open(bundle: &str) -> Result<()> {
+    let dir = work_dir("read")?;
+    // Set before extracting so that the directory is removed by `close` if the bundle is invalid.
+    ROOT.set(dir.clone()).expect("bundle already opened");
+    run_tar(&[
+        OsStr::new("--extract"),
+        OsStr::new("--file"),
+        OsStr::new(bundle),
+        OsStr::new("--directory"),
+        dir.as_os_str(),
+    ])?;
+    if !dir.join(METADATA_FILE).exists() {
+        return Err(Error::InvalidInput {
+            path: bundle.to_string(),
+            message: "not an apt-history bundle".to_string(),
+        });
+    }
+    Ok(())
+}
+
+pub fn close() {
+    if let Some(dir) = ROOT.get() {
+        let _ = fs::remove_dir_all(dir);
+    }
+}
+
+// Whether system paths are read from a bundle, which is never written to.
+pub(crate) fn is_open() -> bool {
+    ROOT.get().is_some()
+}
+
+// Where a system path is read from, which is within the bundle if one is opened.
+pub(crate) fn path(path: &str) -> PathBuf {
+    match ROOT.get() {
+        Some(root) => root.join(path.strip_prefix('/').unwrap_or(path)),
+        None => PathBuf::from(path),
+    }
+}
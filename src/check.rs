@@ -0,0 +1,86 @@
+use crate::bundle;
+use crate::date::parse_duration;
+use crate::error::{Error, Result};
+use crate::history::{history_entries, LIST_DATE_FORMAT};
+use chrono::prelude::*;
+use std::process;
+
+const SERVICE: &str = "APT-HISTORY";
+const UPGRADE_ACTION: &str = "Upgrade";
+
+// Plugin states and their exit codes as defined by the Nagios plugin guidelines.
+enum State {
+    Ok,
+    Warning,
+    Critical,
+    Unknown,
+}
+
+impl State {
+    fn exit_code(&self) -> i32 {
+        match self {
+            State::Ok => 0,
+            State::Warning => 1,
+            State::Critical => 2,
+            State::Unknown => 3,
+        }
+    }
+
+    fn label(&self) -> &str {
+        match self {
+            State::Ok => "OK",
+            State::Warning => "WARNING",
+            State::Critical => "CRITICAL",
+            State::Unknown => "UNKNOWN",
+        }
+    }
+}
+
+fn exit(state: State, message: &str) -> ! {
+    println!("{SERVICE} {} - {message}", state.label());
+    bundle::close();
+    process::exit(state.exit_code());
+}
+
+// Reports an error of `check`, including ones reading the configuration or a bundle before it
+// runs, as the unknown state rather than with the exit status of the error, which monitoring
+// would take for a warning or critical state.
+pub(crate) fn unknown(error: &Error) -> ! {
+    exit(State::Unknown, &error.to_string())
+}
+
+// Exits with the status of the plugin state, errors are returned for `unknown` to report.
+pub fn check(warn_age: &str, crit_age: &str) -> Result<()> {
+    let warn_age = parse_duration(warn_age).map_err(Error::Usage)?;
+    let crit_age = parse_duration(crit_age).map_err(Error::Usage)?;
+
+    let entries = history_entries()?;
+    // Interrupted and failed upgrades do not count, and only finished ones have a logged end date.
+    let Some(last_upgrade) = entries
+        .iter()
+        .rev()
+        .find(|e| e.finished && e.error.is_none() && e.affected.contains_key(UPGRADE_ACTION))
+    else {
+        exit(State::Unknown, "no upgrade transactions found");
+    };
+
+    let age = Local::now().naive_local() - last_upgrade.end_date;
+    let state = if age >= crit_age {
+        State::Critical
+    } else if age >= warn_age {
+        State::Warning
+    } else {
+        State::Ok
+    };
+
+    let message = format!(
+        "last upgrade {} days ago at {} (transaction {}) | age={}s;{};{};0",
+        age.num_days(),
+        last_upgrade.end_date.format(LIST_DATE_FORMAT),
+        last_upgrade.id,
+        age.num_seconds(),
+        warn_age.num_seconds(),
+        crit_age.num_seconds()
+    );
+    exit(state, &message);
+}
@@ -0,0 +1,121 @@
+use crate::terminal;
+use ansi_term::{Colour, Style};
+use clap::ValueEnum;
+use std::env;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+const NO_COLOR_VARIABLE: &str = "NO_COLOR";
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static MARKING: OnceLock<(Palette, MarkerStyle)> = OnceLock::new();
+
+// Colors transactions are marked with, set in the `[display]` section of the configuration.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) enum Palette {
+    #[default]
+    Default,
+    // Colors from the Okabe-Ito palette, which stay distinguishable with color vision
+    // deficiencies.
+    ColorBlind,
+    // Text attributes only, for terminals without colors.
+    Monochrome,
+}
+
+// Characters transactions are marked with, so that marks can be told apart without colors.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) enum MarkerStyle {
+    #[default]
+    Letters,
+    Symbols,
+}
+
+// Why a transaction stands out, from the most to the least important.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub(crate) enum Severity {
+    Failed,
+    Security,
+    Kernel,
+}
+
+impl Severity {
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Severity::Failed => "failed",
+            Severity::Security => "security",
+            Severity::Kernel => "kernel",
+        }
+    }
+
+    pub(crate) fn marker(&self) -> &'static str {
+        let (_, markers) = MARKING.get().copied().unwrap_or_default();
+        match (markers, self) {
+            (MarkerStyle::Letters, Severity::Failed) => "E",
+            (MarkerStyle::Letters, Severity::Security) => "S",
+            (MarkerStyle::Letters, Severity::Kernel) => "K",
+            (MarkerStyle::Symbols, Severity::Failed) => "✗",
+            (MarkerStyle::Symbols, Severity::Security) => "⚑",
+            (MarkerStyle::Symbols, Severity::Kernel) => "⚙",
+        }
+    }
+
+    fn style(&self) -> Style {
+        let (palette, _) = MARKING.get().copied().unwrap_or_default();
+        match (palette, self) {
+            (Palette::Default, Severity::Failed) => Colour::Red.bold(),
+            (Palette::Default, Severity::Security) => Colour::Yellow.bold(),
+            (Palette::Default, Severity::Kernel) => Colour::Cyan.normal(),
+            // Vermillion, orange and sky blue.
+            (Palette::ColorBlind, Severity::Failed) => Colour::Fixed(166).bold(),
+            (Palette::ColorBlind, Severity::Security) => Colour::Fixed(178).bold(),
+            (Palette::ColorBlind, Severity::Kernel) => Colour::Fixed(74).normal(),
+            (Palette::Monochrome, Severity::Failed) => Style::new().bold().reverse(),
+            (Palette::Monochrome, Severity::Security) => Style::new().bold(),
+            (Palette::Monochrome, Severity::Kernel) => Style::new().underline(),
+        }
+    }
+
+    // Marker and name in the colors of the palette, e.g. `E failed`.
+    pub(crate) fn label(&self) -> String {
+        paint(self.style(), &format!("{} {}", self.marker(), self.name()))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum ColorChoice {
+    // Styled output if stdout is a terminal and `NO_COLOR` is not set, see https://no-color.org.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+pub fn init(choice: ColorChoice) {
+    let enabled = match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => {
+            env::var_os(NO_COLOR_VARIABLE).is_none_or(|v| v.is_empty())
+                && terminal::stdout_is_terminal()
+        }
+    };
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub(crate) fn init_marking(palette: Palette, markers: MarkerStyle) {
+    MARKING
+        .set((palette, markers))
+        .expect("marking initialized more than once");
+}
+
+pub(crate) fn paint(style: Style, text: &str) -> String {
+    if ENABLED.load(Ordering::Relaxed) {
+        style.paint(text).to_string()
+    } else {
+        text.to_string()
+    }
+}
+
+pub(crate) fn bold(text: &str) -> String {
+    paint(Style::new().bold(), text)
+}
@@ -0,0 +1,342 @@
+use crate::error::{Error, Result};
+use crate::history::history_entries;
+use crate::shell::SHELLS;
+use clap::Command;
+use std::collections::BTreeSet;
+
+// Argument of `completions` the scripts use to list transaction IDs and package names.
+const CANDIDATES: &str = "candidates";
+// Commands with a summary of what they show or do, as listed in completions and the man page.
+pub(crate) const COMMANDS: [(&str, &str); 38] = [
+    ("annotate", "Set or remove the comment of a transaction"),
+    (
+        "assert",
+        "Fail if transactions happened since a date or changed given packages",
+    ),
+    (
+        "audit",
+        "Installed packages with a newer candidate and the transaction installing them",
+    ),
+    (
+        "autoremove-preview",
+        "Packages apt would autoremove and the transactions making them removable",
+    ),
+    ("blame", "Last transaction changing each given package"),
+    (
+        "bundle",
+        "Write logs and package state to a bundle to read on another machine",
+    ),
+    (
+        "check",
+        "Monitoring plugin reporting the age of the last upgrade",
+    ),
+    (
+        "completions",
+        "Print a completion script for bash, fish or zsh",
+    ),
+    (
+        "doctor",
+        "Check for conditions making the history incomplete or unreliable",
+    ),
+    ("explain", "Describe transactions in a paragraph each"),
+    (
+        "export",
+        "Export transactions as graphs, calendars, manifests, metrics, SQL or archives",
+    ),
+    ("follow", "Show transactions as they finish"),
+    (
+        "import",
+        "Add archives of other hosts to the imported histories",
+    ),
+    ("info", "Show the details of transactions"),
+    (
+        "kernels",
+        "Installations and removals of kernels, flagging the running one",
+    ),
+    ("list", "List transactions"),
+    ("man", "Print this manual page"),
+    (
+        "motd",
+        "Summary of recent changes for the message of the day",
+    ),
+    ("notes", "Show or change the notes of transactions"),
+    (
+        "orphans",
+        "Removals told apart by whether autoremove made them",
+    ),
+    ("package", "Every change of the given packages"),
+    (
+        "preview-diff",
+        "Changes of the next upgrade next to those of the last one",
+    ),
+    (
+        "prune",
+        "Drop transactions older than a retention window from the logs",
+    ),
+    ("redo", "Print or run the commands repeating transactions"),
+    ("repl", "Narrow down transactions interactively"),
+    ("report", "Write an HTML report of transactions"),
+    (
+        "residue",
+        "Removed packages which left configuration behind",
+    ),
+    (
+        "rollback",
+        "Print or run the commands undoing all transactions after one",
+    ),
+    (
+        "search",
+        "Transactions whose command line or packages match a regular expression",
+    ),
+    ("security", "Upgrades from security pockets per transaction"),
+    ("shell-init", "Print shell functions for bash, fish or zsh"),
+    (
+        "snapshot-sources",
+        "Apt sources pointing at snapshots from the time of a transaction",
+    ),
+    ("stats", "Statistics of transactions and changed packages"),
+    (
+        "timeline",
+        "Transactions and changed packages per day, week or month",
+    ),
+    ("tui", "Browse transactions full screen"),
+    ("undo", "Print or run the commands undoing transactions"),
+    (
+        "verify",
+        "Cross-check the state the history leaves packages in with dpkg",
+    ),
+    (
+        "who-installed",
+        "Transactions installing the given packages",
+    ),
+];
+// Commands taking a shell rather than a query.
+const SHELL_COMMANDS: [&str; 2] = ["completions", "shell-init"];
+// Name of the values of flags taking paths, which are completed with files.
+const FILE_VALUE: &str = "FILE";
+
+const BASH: &str = r#"# apt-history completions, enable with `eval "$(apt-history completions bash)"`.
+_apt_history() {
+    local cur=${COMP_WORDS[COMP_CWORD]} prev=${COMP_WORDS[COMP_CWORD-1]}
+    case $prev in
+@VALUES@    esac
+    if [[ $cur == --* ]]; then
+        COMPREPLY=($(compgen -W "@FLAGS@" -- "$cur"))
+        return
+    fi
+    local i command=
+    for ((i = 1; i < COMP_CWORD; i++)); do
+        case ${COMP_WORDS[i]} in
+            @VALUE_FLAGS@) ((i++)) ;;
+            -*) ;;
+            *) command=${COMP_WORDS[i]}; break ;;
+        esac
+    done
+    case $command in
+        '') COMPREPLY=($(compgen -W "@COMMANDS@" -- "$cur")) ;;
+        @SHELL_COMMANDS@) COMPREPLY=($(compgen -W "@SHELLS@" -- "$cur")) ;;
+        *) COMPREPLY=($(compgen -W "$(apt-history --no-pager completions candidates 2>/dev/null)" -- "$cur")) ;;
+    esac
+}
+complete -F _apt_history apt-history
+"#;
+const FISH: &str = r#"# apt-history completions, enable with `apt-history completions fish | source`.
+complete -c apt-history -f
+@SPECS@complete -c apt-history -n '__fish_seen_subcommand_from @SHELL_COMMAND_NAMES@' -a '@SHELLS@'
+complete -c apt-history -n 'not __fish_use_subcommand; and not __fish_seen_subcommand_from @SHELL_COMMAND_NAMES@' \
+    -a '(apt-history --no-pager completions candidates 2>/dev/null)'
+"#;
+const ZSH: &str = r#"#compdef apt-history
+# apt-history completions, enable with `eval "$(apt-history completions zsh)"` after `compinit`.
+_apt_history() {
+    local state line
+    _arguments -C \
+@FLAGS@        '1:command:(@COMMANDS@)' \
+        '*::query:->query' && return
+    case $line[1] in
+        @SHELL_COMMANDS@) _values shell @SHELLS@ ;;
+        *)
+            local -a candidates
+            candidates=(${(f)"$(apt-history --no-pager completions candidates 2>/dev/null)"})
+            compadd -a candidates
+            ;;
+    esac
+}
+compdef _apt_history apt-history
+"#;
+
+// A flag as clap knows it, with the values it takes: none for switches, an empty list for free
+// values.
+struct Flag {
+    file: bool,
+    help: String,
+    long: String,
+    short: Option<char>,
+    values: Option<Vec<String>>,
+}
+
+impl Flag {
+    fn names(&self) -> Vec<String> {
+        let mut names = vec![format!("--{}", self.long)];
+        names.extend(self.short.map(|s| format!("-{s}")));
+        names
+    }
+}
+
+fn flags(command: &Command) -> Vec<Flag> {
+    command
+        .get_arguments()
+        .filter_map(|arg| {
+            let long = arg.get_long()?.to_string();
+            let values = arg.get_action().takes_values().then(|| {
+                arg.get_possible_values()
+                    .iter()
+                    .filter(|v| !v.is_hide_set())
+                    .map(|v| v.get_name().to_string())
+                    .collect()
+            });
+            Some(Flag {
+                file: arg
+                    .get_value_names()
+                    .is_some_and(|names| names.iter().any(|n| n == FILE_VALUE)),
+                help: arg.get_help().map(|h| h.to_string()).unwrap_or_default(),
+                long,
+                short: arg.get_short(),
+                values,
+            })
+        })
+        .collect()
+}
+
+fn bash(flags: &[Flag]) -> String {
+    let mut values = String::new();
+    let mut value_flags = vec![];
+    for flag in flags {
+        let Some(choices) = &flag.values else {
+            continue;
+        };
+        let reply = if flag.file {
+            r#"COMPREPLY=($(compgen -f -- "$cur"))"#.to_string()
+        } else if choices.is_empty() {
+            "COMPREPLY=()".to_string()
+        } else {
+            format!(
+                r#"COMPREPLY=($(compgen -W "{}" -- "$cur"))"#,
+                choices.join(" ")
+            )
+        };
+        let names = flag.names().join("|");
+        values.push_str(&format!("        {names}) {reply}; return ;;\n"));
+        value_flags.push(names);
+    }
+    let long_flags: Vec<String> = flags.iter().map(|f| format!("--{}", f.long)).collect();
+    BASH.replace("@VALUES@", &values)
+        .replace("@FLAGS@", &long_flags.join(" "))
+        .replace("@VALUE_FLAGS@", &value_flags.join("|"))
+}
+
+// Fish shows commands with their summaries.
+fn fish(flags: &[Flag]) -> String {
+    let mut specs = String::new();
+    for flag in flags {
+        let mut spec = "complete -c apt-history".to_string();
+        if let Some(short) = flag.short {
+            spec.push_str(&format!(" -s {short}"));
+        }
+        spec.push_str(&format!(" -l {}", flag.long));
+        match &flag.values {
+            None => (),
+            Some(_) if flag.file => spec.push_str(" -rF"),
+            Some(choices) if choices.is_empty() => spec.push_str(" -r"),
+            Some(choices) => spec.push_str(&format!(" -x -a '{}'", choices.join(" "))),
+        }
+        if !flag.help.is_empty() {
+            spec.push_str(&format!(" -d '{}'", flag.help.replace('\'', "\\'")));
+        }
+        specs.push_str(&spec);
+        specs.push('\n');
+    }
+    for (name, summary) in COMMANDS {
+        specs.push_str(&format!(
+            "complete -c apt-history -n __fish_use_subcommand -a {name} -d '{}'\n",
+            summary.replace('\'', "\\'")
+        ));
+    }
+    FISH.replace("@SPECS@", &specs)
+}
+
+fn zsh(flags: &[Flag]) -> String {
+    let mut specs = String::new();
+    for flag in flags {
+        let help = flag
+            .help
+            .replace('\'', r"'\''")
+            .replace('[', r"\[")
+            .replace(']', r"\]")
+            .replace(':', r"\:");
+        let value = match &flag.values {
+            None => String::new(),
+            Some(_) if flag.file => format!(":{}:_files", flag.long),
+            Some(choices) if choices.is_empty() => format!(":{}: ", flag.long),
+            Some(choices) => format!(":{}:({})", flag.long, choices.join(" ")),
+        };
+        for name in flag.names() {
+            // Long flags also take their value after `=`.
+            let separator = if name.starts_with("--") && !value.is_empty() {
+                "="
+            } else {
+                ""
+            };
+            specs.push_str(&format!("        '{name}{separator}[{help}]{value}' \\\n"));
+        }
+    }
+    ZSH.replace("@FLAGS@", &specs)
+}
+
+// IDs of all transactions, newest first, and the names of all packages they changed.
+fn candidates() -> Result<()> {
+    let entries = history_entries()?;
+    for entry in entries.iter().rev() {
+        println!("{}", entry.id);
+    }
+    let packages: BTreeSet<&String> = entries
+        .iter()
+        .flat_map(|e| e.affected.values())
+        .flat_map(|pkg_map| pkg_map.values())
+        .flatten()
+        .collect();
+    for package in packages {
+        println!("{package}");
+    }
+    Ok(())
+}
+
+// Prints a completion script for the given shell, generated from the flags of the command line
+// parser. Queries are completed with the IDs and package names of the parsed history, which the
+// scripts get from `completions candidates` each time.
+pub fn completions(arguments: Option<Vec<String>>, mut command: Command) -> Result<()> {
+    command.build();
+    let flags = flags(&command);
+    let script = match arguments.as_deref() {
+        Some([shell]) if shell == CANDIDATES => return candidates(),
+        Some([shell]) if shell == "bash" => bash(&flags),
+        Some([shell]) if shell == "fish" => fish(&flags),
+        Some([shell]) if shell == "zsh" => zsh(&flags),
+        _ => {
+            return Err(Error::Usage(format!(
+                "`completions` requires a shell, one of: {}",
+                SHELLS.join(", ")
+            )))
+        }
+    };
+    let names: Vec<&str> = COMMANDS.iter().map(|(name, _)| *name).collect();
+    print!(
+        "{}",
+        script
+            .replace("@COMMANDS@", &names.join(" "))
+            .replace("@SHELL_COMMANDS@", &SHELL_COMMANDS.join("|"))
+            .replace("@SHELL_COMMAND_NAMES@", &SHELL_COMMANDS.join(" "))
+            .replace("@SHELLS@", &SHELLS.join(" "))
+    );
+    Ok(())
+}
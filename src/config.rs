@@ -0,0 +1,503 @@
+use crate::color::{self, MarkerStyle, Palette};
+use crate::date;
+use crate::error::{Error, Result};
+use crate::terminal;
+use chrono::Duration;
+use regex::Regex;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+const COMMAND_LINE_SECTION: &str = "command_line";
+const CONFIG_DIR: &str = "apt-history";
+const CONFIG_FILE: &str = "config";
+const DEFAULT_MAX_COMMAND_LINE_LEN: usize = 100;
+const DEFAULT_SEPARATOR_CHAR: char = '-';
+const DEFAULT_SEPARATOR_WIDTH: usize = 79;
+const DISPLAY_SECTION: &str = "display";
+const HOOKS_SECTION: &str = "hooks";
+const INFO_SECTION: &str = "info";
+const KEY_SEPARATOR: char = '=';
+const DEFAULT_CACHE_TTL: &str = "6h";
+const DEFAULT_MAX_REQUESTS: usize = 2;
+const DEFAULT_MIN_INTERVAL: &str = "1s";
+const DEFAULT_REQUEST_TIMEOUT: &str = "60s";
+const NETWORK_SECTION: &str = "network";
+const DEFAULT_PROFILE_COMMAND: &str = "list";
+// Settings of report profiles which are passed on as command line options of the same name.
+const PROFILE_OPTIONS: [&str; 8] = [
+    "action", "columns", "format", "output", "since", "tool", "until", "user",
+];
+const REPORT_SECTION_PREFIX: &str = "report.";
+const REWRITE_SECTION: &str = "rewrite";
+const REWRITE_SEPARATOR: &str = "=>";
+const TERMINAL_WIDTH: &str = "terminal";
+
+// Replaces matches of a regular expression in command lines before they are displayed.
+pub(crate) struct RewriteRule {
+    pattern: Regex,
+    replacement: String,
+}
+
+impl RewriteRule {
+    pub(crate) fn apply(&self, command_line: &str) -> String {
+        self.pattern
+            .replace_all(command_line, self.replacement.as_str())
+            .to_string()
+    }
+}
+
+// Tags transactions whose command line or one of whose package names matches a regular
+// expression.
+pub(crate) struct TagRule {
+    pattern: Regex,
+    pub(crate) tag: String,
+}
+
+impl TagRule {
+    pub(crate) fn matches(&self, text: &str) -> bool {
+        self.pattern.is_match(text)
+    }
+}
+
+// Processing of entries after they are parsed, by tag rules and a command which changes entries
+// as described in `hooks::run`.
+#[derive(Default)]
+pub(crate) struct Hooks {
+    pub(crate) command: Option<Vec<String>>,
+    pub(crate) tag_rules: Vec<TagRule>,
+}
+
+impl Hooks {
+    fn set(&mut self, line: &str) -> SettingResult {
+        let (key, value) = setting(line)?;
+        match key {
+            "command" => {
+                let command: Vec<String> =
+                    value.split_whitespace().map(|a| a.to_string()).collect();
+                if command.is_empty() {
+                    return Err(format!("invalid value for command: `{value}`"));
+                }
+                self.command = Some(command);
+            }
+            "tag" => {
+                let (pattern, tag) = value.split_once(REWRITE_SEPARATOR).ok_or_else(|| {
+                    format!("invalid tag rule `{value}`, expected `<regex> => <tag>`")
+                })?;
+                self.tag_rules.push(TagRule {
+                    pattern: Regex::new(pattern.trim()).map_err(|e| {
+                        format!("invalid tag rule pattern `{}`: {e}", pattern.trim())
+                    })?,
+                    tag: tag.trim().to_string(),
+                });
+            }
+            key => {
+                return Err(format!(
+                    "unknown setting `{key}` in section `{HOOKS_SECTION}`"
+                ))
+            }
+        }
+        Ok(())
+    }
+}
+
+// Which part of a command line is dropped when it is too long.
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum TruncationStrategy {
+    // Keep the start of the command line.
+    Head,
+    // Keep the start and the end, where the package arguments usually are.
+    Middle,
+    None,
+}
+
+pub(crate) struct Truncation {
+    pub(crate) max_length: usize,
+    pub(crate) strategy: TruncationStrategy,
+}
+
+impl Default for Truncation {
+    fn default() -> Self {
+        Truncation {
+            max_length: DEFAULT_MAX_COMMAND_LINE_LEN,
+            strategy: TruncationStrategy::Head,
+        }
+    }
+}
+
+impl Truncation {
+    fn set(&mut self, line: &str) -> SettingResult {
+        let (key, value) = setting(line)?;
+        match key {
+            "max_length" => {
+                self.max_length = value
+                    .parse()
+                    .map_err(|_| format!("invalid value for max_length: `{value}`"))?
+            }
+            "truncation" => {
+                self.strategy = match value {
+                    "head" => TruncationStrategy::Head,
+                    "middle" => TruncationStrategy::Middle,
+                    "none" => TruncationStrategy::None,
+                    _ => return Err(format!("invalid value for truncation: `{value}`")),
+                }
+            }
+            key => {
+                return Err(format!(
+                    "unknown setting `{key}` in section `{COMMAND_LINE_SECTION}`"
+                ))
+            }
+        }
+        Ok(())
+    }
+}
+
+// Outcome of applying a line of the configuration, with a message for invalid ones.
+type SettingResult = std::result::Result<(), String>;
+
+fn setting(line: &str) -> std::result::Result<(&str, &str), String> {
+    let (key, value) = line
+        .split_once(KEY_SEPARATOR)
+        .ok_or_else(|| format!("invalid setting `{line}`, expected `<key> = <value>`"))?;
+    Ok((key.trim(), value.trim()))
+}
+
+pub(crate) enum HeaderStyle {
+    Bold,
+    Plain,
+    Underline,
+}
+
+pub(crate) enum SeparatorWidth {
+    Fixed(usize),
+    Terminal,
+}
+
+// How transactions shown by `info` are laid out.
+pub(crate) struct InfoLayout {
+    blank_lines: bool,
+    header_style: HeaderStyle,
+    separator_char: Option<char>,
+    separator_width: SeparatorWidth,
+}
+
+impl Default for InfoLayout {
+    fn default() -> Self {
+        InfoLayout {
+            blank_lines: false,
+            header_style: HeaderStyle::Plain,
+            separator_char: Some(DEFAULT_SEPARATOR_CHAR),
+            separator_width: SeparatorWidth::Fixed(DEFAULT_SEPARATOR_WIDTH),
+        }
+    }
+}
+
+impl InfoLayout {
+    // Lines printed between transactions.
+    pub(crate) fn separator(&self) -> Vec<String> {
+        let mut lines = vec![];
+        if self.blank_lines {
+            lines.push(String::new());
+        }
+        if let Some(c) = self.separator_char {
+            let width = match self.separator_width {
+                SeparatorWidth::Fixed(width) => width,
+                SeparatorWidth::Terminal => terminal::width().unwrap_or(DEFAULT_SEPARATOR_WIDTH),
+            };
+            lines.push(c.to_string().repeat(width));
+            if self.blank_lines {
+                lines.push(String::new());
+            }
+        }
+        lines
+    }
+
+    pub(crate) fn header(&self, title: &str) -> String {
+        match self.header_style {
+            HeaderStyle::Bold => color::bold(title),
+            HeaderStyle::Plain => title.to_string(),
+            HeaderStyle::Underline => color::paint(ansi_term::Style::new().underline(), title),
+        }
+    }
+
+    fn set(&mut self, line: &str) -> SettingResult {
+        let (key, value) = setting(line)?;
+        match key {
+            "blank_lines" => {
+                self.blank_lines = value
+                    .parse()
+                    .map_err(|_| format!("invalid value for blank_lines: `{value}`"))?
+            }
+            "header_style" => {
+                self.header_style = match value {
+                    "bold" => HeaderStyle::Bold,
+                    "plain" => HeaderStyle::Plain,
+                    "underline" => HeaderStyle::Underline,
+                    _ => return Err(format!("invalid value for header_style: `{value}`")),
+                }
+            }
+            "separator" => {
+                let mut chars = value.chars();
+                self.separator_char = match (chars.next(), chars.next()) {
+                    (None, _) => None,
+                    (Some(c), None) => Some(c),
+                    _ => {
+                        return Err(format!(
+                            "invalid value for separator: `{value}`, expected a character"
+                        ))
+                    }
+                }
+            }
+            "separator_width" => {
+                self.separator_width =
+                    match value {
+                        TERMINAL_WIDTH => SeparatorWidth::Terminal,
+                        _ => SeparatorWidth::Fixed(value.parse().map_err(|_| {
+                            format!("invalid value for separator_width: `{value}`")
+                        })?),
+                    }
+            }
+            key => {
+                return Err(format!(
+                    "unknown setting `{key}` in section `{INFO_SECTION}`"
+                ))
+            }
+        }
+        Ok(())
+    }
+}
+
+// Limits of requests to remote services, shared by all runs of the same user.
+pub(crate) struct NetworkSettings {
+    // How long responses are reused for, zero to always fetch them again.
+    pub(crate) cache_ttl: Duration,
+    // Requests running at once.
+    pub(crate) max_requests: usize,
+    // Time between the starts of two requests.
+    pub(crate) min_interval: Duration,
+    pub(crate) timeout: Duration,
+}
+
+impl Default for NetworkSettings {
+    fn default() -> Self {
+        let default = |duration| date::parse_duration(duration).expect("invalid default duration");
+        NetworkSettings {
+            cache_ttl: default(DEFAULT_CACHE_TTL),
+            max_requests: DEFAULT_MAX_REQUESTS,
+            min_interval: default(DEFAULT_MIN_INTERVAL),
+            timeout: default(DEFAULT_REQUEST_TIMEOUT),
+        }
+    }
+}
+
+impl NetworkSettings {
+    fn set(&mut self, line: &str) -> SettingResult {
+        let (key, value) = setting(line)?;
+        match key {
+            "cache_ttl" => self.cache_ttl = date::parse_duration(value)?,
+            "max_requests" => {
+                self.max_requests = value
+                    .parse()
+                    .ok()
+                    .filter(|max| *max > 0)
+                    .ok_or_else(|| format!("invalid value for max_requests: `{value}`"))?
+            }
+            "min_interval" => self.min_interval = date::parse_duration(value)?,
+            "timeout" => self.timeout = date::parse_duration(value)?,
+            key => {
+                return Err(format!(
+                    "unknown setting `{key}` in section `{NETWORK_SECTION}`"
+                ))
+            }
+        }
+        Ok(())
+    }
+}
+
+// A named report run with `report <name>`, which runs a command with the configured options and
+// writes its output to a file if a path is set.
+pub(crate) struct ReportProfile {
+    command: String,
+    options: Vec<String>,
+    pub(crate) path: Option<PathBuf>,
+    query: Vec<String>,
+}
+
+impl Default for ReportProfile {
+    fn default() -> Self {
+        ReportProfile {
+            command: DEFAULT_PROFILE_COMMAND.to_string(),
+            options: vec![],
+            path: None,
+            query: vec![],
+        }
+    }
+}
+
+impl ReportProfile {
+    // Command line arguments running the report.
+    pub(crate) fn arguments(&self) -> Vec<String> {
+        let mut arguments = self.options.clone();
+        arguments.push(self.command.clone());
+        arguments.extend(self.query.iter().cloned());
+        arguments
+    }
+
+    fn set(&mut self, name: &str, line: &str) -> SettingResult {
+        let (key, value) = setting(line)?;
+        match key {
+            "command" => self.command = value.to_string(),
+            "path" => self.path = Some(PathBuf::from(value)),
+            "query" => self.query = value.split_whitespace().map(|t| t.to_string()).collect(),
+            key if PROFILE_OPTIONS.contains(&key) => {
+                self.options.push(format!("--{key}"));
+                self.options.push(value.to_string());
+            }
+            key => {
+                return Err(format!(
+                    "unknown setting `{key}` in report profile `{name}`"
+                ))
+            }
+        }
+        Ok(())
+    }
+}
+
+// Settings read from `$XDG_CONFIG_HOME/apt-history/config`, an INI-like file where sections
+// start with `[name]` and `#` starts a comment line. Rewrite rules in the `[rewrite]` section
+// have the form `<regex> => <replacement>`, e.g. `^DEBIAN_FRONTEND=\S+\s+ =>` to drop a prefix.
+// The `[info]` section takes `<key> = <value>` settings for the layout of `info` and the
+// `[command_line]` one for the truncation of long command lines. The `[display]` section sets
+// defaults of display options, the `[network]` one limits requests to remote services and
+// sections named `[report.<name>]` define report profiles. The `[hooks]` section sets up processing
+// of entries before they are shown.
+#[derive(Default)]
+pub(crate) struct Config {
+    // Default of `--date-format`.
+    pub(crate) date_format: Option<String>,
+    pub(crate) hooks: Hooks,
+    pub(crate) info_layout: InfoLayout,
+    pub(crate) markers: MarkerStyle,
+    // Default of `--max-lines`.
+    pub(crate) max_lines: Option<usize>,
+    pub(crate) network: NetworkSettings,
+    pub(crate) palette: Palette,
+    pub(crate) report_profiles: HashMap<String, ReportProfile>,
+    pub(crate) truncation: Truncation,
+    pub(crate) rewrite_rules: Vec<RewriteRule>,
+}
+
+fn config_path() -> Option<PathBuf> {
+    let config_home = match env::var("XDG_CONFIG_HOME") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var("HOME").ok()?).join(".config"),
+    };
+    Some(config_home.join(CONFIG_DIR).join(CONFIG_FILE))
+}
+
+fn parse_rewrite_rule(line: &str) -> std::result::Result<RewriteRule, String> {
+    let (pattern, replacement) = line.split_once(REWRITE_SEPARATOR).ok_or_else(|| {
+        format!("invalid rewrite rule `{line}`, expected `<regex> => <replacement>`")
+    })?;
+    Ok(RewriteRule {
+        pattern: Regex::new(pattern.trim())
+            .map_err(|e| format!("invalid rewrite rule pattern `{}`: {e}", pattern.trim()))?,
+        replacement: replacement.trim().to_string(),
+    })
+}
+
+impl Config {
+    fn set_display(&mut self, line: &str) -> SettingResult {
+        let (key, value) = setting(line)?;
+        match key {
+            "date_format" => self.date_format = Some(value.to_string()),
+            "markers" => {
+                self.markers = match value {
+                    "letters" => MarkerStyle::Letters,
+                    "symbols" => MarkerStyle::Symbols,
+                    _ => return Err(format!("invalid value for markers: `{value}`")),
+                }
+            }
+            "palette" => {
+                self.palette = match value {
+                    "colorblind" => Palette::ColorBlind,
+                    "default" => Palette::Default,
+                    "monochrome" => Palette::Monochrome,
+                    _ => return Err(format!("invalid value for palette: `{value}`")),
+                }
+            }
+            "max_lines" => {
+                self.max_lines = Some(
+                    value
+                        .parse()
+                        .map_err(|_| format!("invalid value for max_lines: `{value}`"))?,
+                )
+            }
+            key => {
+                return Err(format!(
+                    "unknown setting `{key}` in section `{DISPLAY_SECTION}`"
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    fn set(&mut self, section: &str, line: &str) -> SettingResult {
+        match section {
+            COMMAND_LINE_SECTION => self.truncation.set(line),
+            DISPLAY_SECTION => self.set_display(line),
+            HOOKS_SECTION => self.hooks.set(line),
+            INFO_SECTION => self.info_layout.set(line),
+            NETWORK_SECTION => self.network.set(line),
+            REWRITE_SECTION => {
+                self.rewrite_rules.push(parse_rewrite_rule(line)?);
+                Ok(())
+            }
+            _ if section.starts_with(REPORT_SECTION_PREFIX) => {
+                let name = &section[REPORT_SECTION_PREFIX.len()..];
+                self.report_profiles
+                    .entry(name.to_string())
+                    .or_default()
+                    .set(name, line)
+            }
+            _ => Err(format!("unknown configuration section `{section}`")),
+        }
+    }
+
+    // Invalid lines are reported with their line number.
+    pub(crate) fn load() -> Result<Config> {
+        let Some(path) = config_path() else {
+            return Ok(Config::default());
+        };
+        let Ok(content) = fs::read_to_string(&path) else {
+            return Ok(Config::default());
+        };
+
+        let mut config = Config::default();
+        let mut section = String::new();
+        for (number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = name.trim().to_string();
+                continue;
+            }
+            config
+                .set(&section, line)
+                .map_err(|message| Error::InvalidInput {
+                    path: format!("{}:{}", path.display(), number + 1),
+                    message,
+                })?;
+        }
+        Ok(config)
+    }
+
+    // The configuration for code which cannot report errors, such as the display of entries.
+    // Commands load it before anything else, so that errors in it are reported up front.
+    pub(crate) fn load_checked() -> Config {
+        Config::load().unwrap_or_default()
+    }
+}
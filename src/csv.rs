@@ -0,0 +1,43 @@
+// Minimal CSV writing as described in RFC 4180.
+const LINE_ENDING: &str = "\r\n";
+const SPECIAL_CHARS: [char; 4] = [',', '"', '\r', '\n'];
+
+fn field(value: &str) -> String {
+    if value.contains(SPECIAL_CHARS) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// A single record terminated by a line break.
+pub(crate) fn record<S: AsRef<str>>(fields: &[S]) -> String {
+    let fields: Vec<String> = fields.iter().map(|f| field(f.as_ref())).collect();
+    format!("{}{LINE_ENDING}", fields.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leaves_plain_fields_unquoted() {
+        assert_eq!(record(&["1", "apt-get", ""]), "1,apt-get,\r\n");
+    }
+
+    #[test]
+    fn doubles_embedded_quotes() {
+        assert_eq!(
+            record(&["say \"hi\"", "\""]),
+            "\"say \"\"hi\"\"\",\"\"\"\"\r\n"
+        );
+    }
+
+    #[test]
+    fn quotes_fields_with_separators_and_line_breaks() {
+        assert_eq!(
+            record(&["a,b", "line\nbreak", "cr\r"]),
+            "\"a,b\",\"line\nbreak\",\"cr\r\"\r\n"
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use chrono::format::{Item, StrftimeItems};
+use chrono::prelude::*;
+use chrono::{Duration, Months};
+
+const DATE_FORMATS: [&str; 3] = ["%F %T", "%F %H:%M", "%FT%T"];
+const DAY_FORMAT: &str = "%F";
+const DAYS_IN_MONTH: i64 = 30;
+const DAYS_IN_WEEK: i64 = 7;
+const DAYS_IN_YEAR: i64 = 365;
+const RELATIVE_SUFFIX: &str = " ago";
+
+// A date relative to now such as `2 weeks ago`, `1 month ago`, `yesterday` or `today`, the last
+// two standing for their midnight. Months and years are calendar ones.
+fn parse_relative_date(date: &str, now: NaiveDateTime) -> Option<NaiveDateTime> {
+    let midnight = now.date().and_time(NaiveTime::MIN);
+    match date {
+        "now" => return Some(now),
+        "today" => return Some(midnight),
+        "yesterday" => return midnight.checked_sub_signed(Duration::try_days(1)?),
+        _ => (),
+    }
+    let (amount, unit) = date.strip_suffix(RELATIVE_SUFFIX)?.split_once(' ')?;
+    let amount: u32 = amount.parse().ok()?;
+    let unit = unit.strip_suffix('s').unwrap_or(unit);
+    let elapsed = match unit {
+        "second" => Duration::try_seconds(amount.into()),
+        "minute" => Duration::try_minutes(amount.into()),
+        "hour" => Duration::try_hours(amount.into()),
+        "day" => Duration::try_days(amount.into()),
+        "week" => Duration::try_weeks(amount.into()),
+        "month" => return now.checked_sub_months(Months::new(amount)),
+        "year" => return now.checked_sub_months(Months::new(amount.checked_mul(12)?)),
+        _ => None,
+    };
+    now.checked_sub_signed(elapsed?)
+}
+
+// Dates given on the command line, either a day such as `2024-06-01` which stands for its
+// midnight, a date and time such as `2024-06-01 12:30` or a date relative to now such as
+// `2 weeks ago`.
+pub(crate) fn parse_date(date: &str) -> Result<NaiveDateTime, String> {
+    for format in DATE_FORMATS {
+        if let Ok(parsed) = NaiveDateTime::parse_from_str(date, format) {
+            return Ok(parsed);
+        }
+    }
+    let trimmed = date.trim().to_lowercase();
+    if let Some(parsed) = parse_relative_date(&trimmed, Local::now().naive_local()) {
+        return Ok(parsed);
+    }
+    NaiveDate::parse_from_str(date, DAY_FORMAT)
+        .map(|day| day.and_time(NaiveTime::MIN))
+        .map_err(|_| {
+            format!(
+                "unable to parse date `{date}`, expected e.g. `2024-06-01 12:30` or `2 weeks ago`"
+            )
+        })
+}
+
+// Date formats given on the command line or in the configuration, which are checked up front as
+// dates cannot be displayed with invalid ones.
+pub(crate) fn check_format(format: String) -> Result<String, String> {
+    if StrftimeItems::new(&format).any(|item| item == Item::Error) {
+        return Err(format!("invalid date format `{format}`"));
+    }
+    Ok(format)
+}
+
+// Durations such as `90d`, `2w` or `12h`, a number followed by a single unit suffix.
+pub(crate) fn parse_duration(duration: &str) -> Result<Duration, String> {
+    // The unit is split off at a character boundary, so that any input is reported as invalid.
+    let unit_index = duration.char_indices().last().map_or(0, |(index, _)| index);
+    let (amount, unit) = duration.split_at(unit_index);
+    let amount: i64 = amount
+        .parse()
+        .map_err(|_| format!("unable to parse duration `{duration}`"))?;
+    let parsed = match unit {
+        "s" => Duration::try_seconds(amount),
+        "m" => Duration::try_minutes(amount),
+        "h" => Duration::try_hours(amount),
+        "d" => Duration::try_days(amount),
+        "w" => Duration::try_weeks(amount),
+        _ => {
+            return Err(format!(
+                "unknown unit in duration `{duration}`, use one of s, m, h, d, w"
+            ))
+        }
+    };
+    parsed.ok_or_else(|| format!("duration `{duration}` is out of range"))
+}
+
+// Time elapsed since a date in its largest whole unit, e.g. `3 days ago` or `2 hours ago`.
+pub(crate) fn relative_date(date: NaiveDateTime, now: NaiveDateTime) -> String {
+    let elapsed = now - date;
+    let (amount, suffix) = if elapsed < Duration::zero() {
+        (-elapsed, "from now")
+    } else {
+        (elapsed, "ago")
+    };
+    let days = amount.num_days();
+    let (count, unit) = if days >= DAYS_IN_YEAR {
+        (days / DAYS_IN_YEAR, "year")
+    } else if days >= DAYS_IN_MONTH {
+        (days / DAYS_IN_MONTH, "month")
+    } else if days >= DAYS_IN_WEEK {
+        (days / DAYS_IN_WEEK, "week")
+    } else if days > 0 {
+        (days, "day")
+    } else if amount.num_hours() > 0 {
+        (amount.num_hours(), "hour")
+    } else if amount.num_minutes() > 0 {
+        (amount.num_minutes(), "minute")
+    } else {
+        return "just now".to_string();
+    };
+    let plural = if count == 1 { "" } else { "s" };
+    format!("{count} {unit}{plural} {suffix}")
+}
+
+// Durations in their two largest units, e.g. `45s`, `3m 20s` or `1h 05m`.
+pub(crate) fn format_duration(duration: Duration) -> String {
+    let seconds = duration.num_seconds().max(0);
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{hours}h {minutes:02}m")
+    } else if minutes > 0 {
+        format!("{minutes}m {seconds:02}s")
+    } else {
+        format!("{seconds}s")
+    }
+}
@@ -0,0 +1,237 @@
+use crate::error::Result;
+use crate::history::{
+    history_entries, history_files, open_log, overlapping_pairs, rotation_gaps, ACTIONS,
+    CURRENT_HISTORY_FILE, LOG_FILE_DATE_FORMAT,
+};
+use crate::lock;
+use chrono::NaiveDateTime;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+// Fields of transactions besides the actions, as known to the parser.
+const FIELDS: [&str; 5] = [
+    "Commandline",
+    "End-Date",
+    "Error",
+    "Requested-By",
+    "Start-Date",
+];
+const OK_MARKER: &str = "ok";
+const SUGGESTION_INDENT: &str = "    ";
+const WARNING_MARKER: &str = "warning";
+
+fn report(ok: bool, message: &str) {
+    let marker = if ok { OK_MARKER } else { WARNING_MARKER };
+    println!("[{marker}] {message}");
+}
+
+fn suggest(suggestion: &str) {
+    println!("{SUGGESTION_INDENT}{suggestion}");
+}
+
+// A block of lines between empty lines, which should be one transaction.
+#[derive(Default)]
+struct Block {
+    end_date: bool,
+    first_line: usize,
+    start_dates: Vec<usize>,
+}
+
+// Problems in the content of a log, found line by line rather than by the parser so that all of
+// them are reported rather than only the first one.
+fn scan(content: &str, current: bool) -> (usize, Vec<String>, BTreeMap<String, usize>) {
+    let mut problems = vec![];
+    let mut unknown: BTreeMap<String, usize> = BTreeMap::new();
+    let mut blocks: Vec<Block> = vec![];
+    let mut block: Option<Block> = None;
+    for (index, line) in content.lines().enumerate() {
+        let number = index + 1;
+        if line.is_empty() {
+            blocks.extend(block.take());
+            continue;
+        }
+        let block = block.get_or_insert_with(|| Block {
+            first_line: number,
+            ..Block::default()
+        });
+        let Some((field, value)) = line.split_once(": ") else {
+            problems.push(format!("line {number} is not a field: `{line}`"));
+            continue;
+        };
+        match field {
+            "Start-Date" | "End-Date" => {
+                if NaiveDateTime::parse_from_str(value, LOG_FILE_DATE_FORMAT).is_err() {
+                    problems.push(format!("line {number} has an invalid date: `{value}`"));
+                }
+                if field == "Start-Date" {
+                    block.start_dates.push(number);
+                } else {
+                    block.end_date = true;
+                }
+            }
+            field if FIELDS.contains(&field) || ACTIONS.contains(&field) => (),
+            field => {
+                unknown.entry(field.to_string()).or_insert(number);
+            }
+        }
+    }
+    blocks.extend(block);
+
+    let count = blocks.len();
+    for (position, block) in blocks.iter().enumerate() {
+        match block.start_dates.as_slice() {
+            [] => problems.push(format!(
+                "the lines from line {} have no Start-Date",
+                block.first_line
+            )),
+            [start] if !block.end_date => {
+                // The last transaction of the current log is still running while apt is.
+                let running =
+                    current && position + 1 == count && lock::frontend_lock_holder().is_some();
+                if !running {
+                    problems.push(format!(
+                        "the transaction starting at line {start} has no End-Date"
+                    ));
+                }
+            }
+            [_] => (),
+            [start, next, ..] => problems.push(format!(
+                "the transaction starting at line {start} has no End-Date before the next one at \
+                 line {next}"
+            )),
+        }
+    }
+    (count, problems, unknown)
+}
+
+// Checks one log: whether it can be read, decompressed and parsed.
+fn check_log(path: &Path) {
+    let filename = path.display().to_string();
+    let current = path.file_name().is_some_and(|n| n == CURRENT_HISTORY_FILE);
+    if let Err(e) = File::open(path) {
+        report(false, &format!("{filename} cannot be read: {e}"));
+        if e.kind() == io::ErrorKind::PermissionDenied {
+            suggest("run as root or as a member of the `adm` group");
+        }
+        return;
+    }
+    let mut content = vec![];
+    if let Err(e) = open_log(&filename).and_then(|mut log| log.read_to_end(&mut content)) {
+        report(
+            false,
+            &format!("{filename} is corrupt after {} bytes: {e}", content.len()),
+        );
+        suggest("restore it from a backup, or move it out of the log directory to read the others");
+        return;
+    }
+    let content = match String::from_utf8(content) {
+        Ok(content) => content,
+        Err(e) => {
+            report(
+                false,
+                &format!(
+                    "{filename} is not valid UTF-8 after {} bytes",
+                    e.utf8_error().valid_up_to()
+                ),
+            );
+            suggest("the log was probably damaged on disk, restore it from a backup");
+            return;
+        }
+    };
+
+    let (transactions, problems, unknown) = scan(&content, current);
+    if problems.is_empty() && unknown.is_empty() {
+        report(true, &format!("{filename}: {transactions} transactions"));
+        return;
+    }
+    for problem in &problems {
+        report(false, &format!("{filename}: {problem}"));
+    }
+    if !problems.is_empty() {
+        suggest(
+            "apt was probably interrupted or the disk was full, check the packages of these \
+             transactions with `dpkg --audit`",
+        );
+    }
+    for (field, line) in &unknown {
+        report(
+            false,
+            &format!("{filename}: unknown field `{field}` first at line {line}"),
+        );
+    }
+    if !unknown.is_empty() {
+        suggest(
+            "the log was written by an apt version apt-history does not know yet, the \
+             transactions of this log cannot be shown until it does",
+        );
+    }
+}
+
+// Checks for conditions that make the history incomplete or unreliable.
+pub fn doctor() -> Result<()> {
+    let files = history_files()?;
+    let has_current = files
+        .iter()
+        .any(|f| f.file_name().is_some_and(|n| n == CURRENT_HISTORY_FILE));
+    report(
+        has_current,
+        &format!(
+            "{} history log files found{}",
+            files.len(),
+            if has_current {
+                String::new()
+            } else {
+                format!(", {CURRENT_HISTORY_FILE} is missing")
+            }
+        ),
+    );
+
+    for file in &files {
+        check_log(file);
+    }
+
+    let gaps = rotation_gaps()?;
+    if gaps.is_empty() {
+        report(true, "no gaps in rotated history logs");
+    } else {
+        let missing: Vec<String> = gaps
+            .iter()
+            .map(|n| format!("{CURRENT_HISTORY_FILE}.{n}"))
+            .collect();
+        report(
+            false,
+            &format!(
+                "rotated history logs missing, transactions are unknown for: {}",
+                missing.join(", ")
+            ),
+        );
+        suggest("they were probably deleted by hand, restore them from a backup to see their transactions");
+    }
+
+    // Logs which cannot be parsed are a finding rather than a failure here.
+    let entries = match history_entries() {
+        Ok(entries) => entries,
+        Err(e) => {
+            report(false, &e.to_string());
+            return Ok(());
+        }
+    };
+    let overlaps: Vec<String> = overlapping_pairs(&entries)
+        .iter()
+        .map(|(a, b)| format!("{a} and {b}"))
+        .collect();
+    if overlaps.is_empty() {
+        report(true, "no overlapping transactions");
+    } else {
+        report(
+            false,
+            &format!(
+                "transactions overlap in time, the order of their changes is uncertain: {}",
+                overlaps.join(", ")
+            ),
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,159 @@
+use crate::bundle;
+use crate::error::{Error, Result};
+use crate::privileges;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::process::Command;
+
+pub(crate) const APT_EXTENDED_STATES_PATH: &str = "/var/lib/apt/extended_states";
+pub(crate) const DPKG_STATUS_PATH: &str = "/var/lib/dpkg/status";
+const CONFIG_FILES_STATE: &str = "config-files";
+const INSTALLED_STATE: &str = "installed";
+const DEPENDENCY_FIELDS: [&str; 2] = ["Pre-Depends", "Depends"];
+const REPLACES_FIELDS: [&str; 1] = ["Replaces"];
+
+pub(crate) struct DpkgPackage {
+    pub(crate) arch: String,
+    pub(crate) fields: HashMap<String, String>,
+    pub(crate) name: String,
+    pub(crate) status: String,
+}
+
+impl DpkgPackage {
+    fn from_fields(fields: HashMap<String, String>) -> DpkgPackage {
+        let field = |name: &str| fields.get(name).cloned().unwrap_or_default();
+        DpkgPackage {
+            arch: field("Architecture"),
+            name: field("Package"),
+            status: field("Status"),
+            fields,
+        }
+    }
+
+    // Names of all packages this one depends on, alternatives included and version constraints
+    // and architecture qualifiers dropped, e.g. `perl:any (>= 5.36) | perl-base` becomes `perl`
+    // and `perl-base`.
+    pub(crate) fn dependencies(&self) -> Vec<String> {
+        self.relation_names(&DEPENDENCY_FIELDS)
+    }
+
+    // Names of packages this one replaces, in the same form as dependencies.
+    pub(crate) fn replaces(&self) -> Vec<String> {
+        self.relation_names(&REPLACES_FIELDS)
+    }
+
+    fn relation_names(&self, fields: &[&str]) -> Vec<String> {
+        let mut names = vec![];
+        for field in fields {
+            let Some(value) = self.fields.get(*field) else {
+                continue;
+            };
+            for alternative in value.split([',', '|']) {
+                let name = alternative
+                    .split_whitespace()
+                    .next()
+                    .unwrap_or_default()
+                    .split(':')
+                    .next()
+                    .unwrap_or_default();
+                if !name.is_empty() {
+                    names.push(name.to_string());
+                }
+            }
+        }
+        names
+    }
+
+    // Status field is made of want, flag and state, e.g. `deinstall ok config-files`.
+    fn state(&self) -> Option<&str> {
+        self.status.split(' ').nth(2)
+    }
+
+    pub(crate) fn has_config_residue(&self) -> bool {
+        self.state() == Some(CONFIG_FILES_STATE)
+    }
+
+    // Source package name without the version that follows it when it differs from the binary
+    // package version, e.g. `glibc (2.36-9)`.
+    pub(crate) fn source_name(&self) -> Option<String> {
+        let source = self.fields.get("Source")?;
+        source.split_whitespace().next().map(|s| s.to_string())
+    }
+
+    pub(crate) fn is_installed(&self) -> bool {
+        self.state() == Some(INSTALLED_STATE)
+    }
+}
+
+pub(crate) fn parse_paragraph(paragraph: &str) -> HashMap<String, String> {
+    let mut fields: HashMap<String, String> = HashMap::new();
+    let mut last_field: Option<String> = None;
+
+    for line in paragraph.lines() {
+        if line.starts_with(' ') || line.starts_with('\t') {
+            // Continuation of a multi-line field such as `Description` or `Conffiles`.
+            if let Some(name) = &last_field {
+                let value = fields.entry(name.clone()).or_default();
+                value.push('\n');
+                value.push_str(line.trim());
+            }
+            continue;
+        }
+
+        if let Some((name, value)) = line.split_once(':') {
+            fields.insert(name.to_string(), value.trim().to_string());
+            last_field = Some(name.to_string());
+        }
+    }
+    fields
+}
+
+// Paragraphs of a dpkg database file, none if it does not exist, e.g. on hosts without dpkg or
+// in bundles captured without it.
+fn read_paragraphs(path: &str) -> Vec<HashMap<String, String>> {
+    let content = match fs::read_to_string(bundle::path(path)) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return vec![],
+        Err(e) if privileges::is_unavailable(path, &e) => return vec![],
+        Err(e) => panic!("error reading `{path}`: {e}"),
+    };
+    content
+        .split("\n\n")
+        .filter(|p| !p.trim().is_empty())
+        .map(parse_paragraph)
+        .collect()
+}
+
+pub(crate) fn status_packages() -> Vec<DpkgPackage> {
+    read_paragraphs(DPKG_STATUS_PATH)
+        .into_iter()
+        .map(DpkgPackage::from_fields)
+        .collect()
+}
+
+// Packages apt marked as automatically installed, in `name:arch` form.
+pub(crate) fn auto_installed_packages() -> HashSet<String> {
+    read_paragraphs(APT_EXTENDED_STATES_PATH)
+        .iter()
+        .filter(|p| p.get("Auto-Installed").map(|v| v.as_str()) == Some("1"))
+        .filter_map(|p| Some(format!("{}:{}", p.get("Package")?, p.get("Architecture")?)))
+        .collect()
+}
+
+// Architecture packages are named without a qualifier by apt, which requires dpkg, e.g. it is
+// missing when reading a bundle on another distribution.
+pub(crate) fn native_architecture() -> Result<String> {
+    let command = "dpkg --print-architecture";
+    let output = Command::new("dpkg")
+        .arg("--print-architecture")
+        .output()
+        .map_err(|source| Error::Spawn {
+            command: command.to_string(),
+            source,
+        })?;
+    if !output.status.success() {
+        return Err(Error::program_failed(command, &output));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
@@ -0,0 +1,178 @@
+use crate::i18n::tr;
+use std::fmt;
+use std::io;
+use std::process::Output;
+
+// Exit status of violated assertions, as `test` fails with.
+pub(crate) const EX_ASSERTION: i32 = 1;
+// Exit statuses from sysexits(3), so that scripts can tell failures apart.
+pub(crate) const EX_CANTCREAT: i32 = 73;
+pub(crate) const EX_DATAERR: i32 = 65;
+pub(crate) const EX_NOINPUT: i32 = 66;
+pub(crate) const EX_SOFTWARE: i32 = 70;
+pub(crate) const EX_UNAVAILABLE: i32 = 69;
+pub(crate) const EX_USAGE: i32 = 64;
+
+// Failures reported with a message and an exit status rather than a panic.
+#[derive(Debug)]
+pub enum Error {
+    // Transactions violate an assertion of `assert`, which are listed.
+    AssertionFailed(String),
+    // A command exited unsuccessfully, or was killed without a code. The exit status of commands
+    // run on behalf of the user is passed on, programs apt-history runs for itself have the
+    // message they failed with instead.
+    CommandFailed {
+        command: String,
+        code: Option<i32>,
+        message: Option<String>,
+    },
+    // The apt log directory cannot be listed.
+    LogDir {
+        path: String,
+        source: io::Error,
+    },
+    // A history log cannot be read.
+    LogFile {
+        path: String,
+        source: io::Error,
+    },
+    // A history log line is not in the format apt writes.
+    Parse {
+        path: String,
+        line: usize,
+        message: String,
+    },
+    // The configured hook command does not process entries as expected.
+    Hook {
+        command: String,
+        message: String,
+    },
+    // A file given on the command line cannot be read.
+    InputFile {
+        path: String,
+        source: io::Error,
+    },
+    // A file given on the command line or the configuration file is not in the expected format.
+    InvalidInput {
+        path: String,
+        message: String,
+    },
+    // A file cannot be written, either an output file given on the command line or a state file.
+    OutputFile {
+        path: String,
+        source: io::Error,
+    },
+    // A remote service cannot be reached or does not respond successfully.
+    Network {
+        url: String,
+        message: String,
+    },
+    // A program apt-history depends on cannot be started, e.g. as it is not installed.
+    Spawn {
+        command: String,
+        source: io::Error,
+    },
+    UnknownCommand(String),
+    // A command is given arguments it does not take.
+    Usage(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    // Failure of a program apt-history runs for itself, with the error output of the program or
+    // its exit status if it has none.
+    pub(crate) fn program_failed(command: &str, output: &Output) -> Error {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        let message = match output.status.code() {
+            _ if !stderr.is_empty() => stderr,
+            Some(code) => format!("{} {code}", tr("exit status")),
+            None => tr("terminated by a signal").to_string(),
+        };
+        Error::CommandFailed {
+            command: command.to_string(),
+            code: output.status.code(),
+            message: Some(message),
+        }
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::AssertionFailed(_) => EX_ASSERTION,
+            Error::CommandFailed {
+                code: Some(code),
+                message: None,
+                ..
+            } => *code,
+            Error::CommandFailed { .. } => EX_SOFTWARE,
+            Error::InputFile { .. } | Error::LogDir { .. } | Error::LogFile { .. } => EX_NOINPUT,
+            Error::Hook { .. } | Error::InvalidInput { .. } | Error::Parse { .. } => EX_DATAERR,
+            Error::Network { .. } | Error::Spawn { .. } => EX_UNAVAILABLE,
+            Error::OutputFile { .. } => EX_CANTCREAT,
+            Error::UnknownCommand(_) | Error::Usage(_) => EX_USAGE,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::AssertionFailed(violations) => {
+                write!(f, "{}: {violations}", tr("assertion failed"))
+            }
+            Error::CommandFailed {
+                command,
+                message: Some(message),
+                ..
+            } => write!(f, "`{command}` {}: {message}", tr("failed")),
+            Error::CommandFailed { command, code, .. } => match code {
+                Some(code) => write!(f, "`{command}` {} {code}", tr("failed with exit status")),
+                None => write!(f, "`{command}` {}", tr("was terminated by a signal")),
+            },
+            Error::Hook { command, message } => write!(f, "{} `{command}`: {message}", tr("hook")),
+            Error::InputFile { path, source } => {
+                write!(f, "{} `{path}`: {source}", tr("error reading"))
+            }
+            Error::InvalidInput { path, message } => write!(f, "{path}: {message}"),
+            Error::LogDir { path, source } => {
+                write!(
+                    f,
+                    "{} `{path}`: {source}",
+                    tr("error reading log directory")
+                )
+            }
+            Error::LogFile { path, source } => {
+                write!(f, "{} `{path}`: {source}", tr("error reading log file"))
+            }
+            Error::Network { url, message } => {
+                write!(f, "{} `{url}`: {message}", tr("error fetching"))
+            }
+            Error::OutputFile { path, source } => {
+                write!(f, "{} `{path}`: {source}", tr("error writing"))
+            }
+            Error::Parse {
+                path,
+                line,
+                message,
+            } => write!(f, "{path}:{line}: {message}"),
+            Error::Spawn { command, source } => {
+                write!(f, "{} `{command}`: {source}", tr("error running"))
+            }
+            Error::UnknownCommand(command) => write!(f, "{}: `{command}`", tr("unknown command")),
+            Error::Usage(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::InputFile { source, .. }
+            | Error::LogDir { source, .. }
+            | Error::LogFile { source, .. }
+            | Error::OutputFile { source, .. }
+            | Error::Spawn { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use crate::error::{Error, Result};
+use crate::history::{matching_entries, HistoryEntry, LIST_DATE_FORMAT};
+
+const INSTALL_ACTION: &str = "Install";
+const LIBRARY_PREFIX: &str = "lib";
+// Packages worth naming in a summary, by name prefix.
+const NOTABLE_PACKAGES: [(&str, &str); 6] = [
+    ("libc6", "the C library"),
+    ("libssl", "OpenSSL"),
+    ("linux-image-", "the kernel"),
+    ("openssh-server", "the SSH server"),
+    ("openssl", "OpenSSL"),
+    ("systemd", "systemd"),
+];
+// Packages are listed by name up to this many, more are only counted.
+const MAX_NAMED_PACKAGES: usize = 3;
+const ROOT_USER: &str = "root";
+
+fn verb(action: &str) -> String {
+    match action {
+        "Install" => "installed".to_string(),
+        "Reinstall" => "reinstalled".to_string(),
+        "Remove" => "removed".to_string(),
+        "Purge" => "purged".to_string(),
+        "Upgrade" => "upgraded".to_string(),
+        "Downgrade" => "downgraded".to_string(),
+        action => action.to_lowercase(),
+    }
+}
+
+fn count(count: usize, singular: &str, plural: &str) -> String {
+    if count == 1 {
+        format!("1 {singular}")
+    } else {
+        format!("{count} {plural}")
+    }
+}
+
+// `a`, `a and b` or `a, b and c`.
+fn enumerate(items: &[String]) -> String {
+    match items {
+        [] => String::new(),
+        [item] => item.clone(),
+        [init @ .., last] => format!("{} and {last}", init.join(", ")),
+    }
+}
+
+// Packages of an action as `name` without the architecture, sorted by name.
+fn package_names(entry: &HistoryEntry, action: &str) -> Vec<(String, String)> {
+    let mut packages: Vec<(String, String)> = entry.affected[action]
+        .iter()
+        .flat_map(|(arch, pkgs)| pkgs.iter().map(move |p| (p.clone(), format!("{p}:{arch}"))))
+        .collect();
+    packages.sort();
+    packages
+}
+
+fn notable(names: &[String]) -> Vec<String> {
+    let mut notable: Vec<String> = vec![];
+    for (prefix, description) in NOTABLE_PACKAGES {
+        if names.iter().any(|n| n.starts_with(prefix)) && !notable.iter().any(|d| d == description)
+        {
+            notable.push(description.to_string());
+        }
+    }
+    notable
+}
+
+// Installs name the requested packages and count the dependencies pulled in with them, other
+// actions name a few packages or count them and name notable ones.
+fn describe_action(entry: &HistoryEntry, action: &str) -> String {
+    let packages = package_names(entry, action);
+    if action == INSTALL_ACTION {
+        let (dependencies, requested): (Vec<_>, Vec<_>) = packages
+            .iter()
+            .partition(|(_, package)| entry.is_automatic(action, package));
+        let mut parts = if requested.len() <= MAX_NAMED_PACKAGES {
+            requested.iter().map(|(name, _)| name.clone()).collect()
+        } else {
+            vec![count(requested.len(), "package", "packages")]
+        };
+        if !dependencies.is_empty() {
+            parts.push(count(dependencies.len(), "dependency", "dependencies"));
+        }
+        return format!("{} {}", verb(action), enumerate(&parts));
+    }
+
+    let names: Vec<String> = packages.into_iter().map(|(name, _)| name).collect();
+    if names.len() <= MAX_NAMED_PACKAGES {
+        return format!("{} {}", verb(action), enumerate(&names));
+    }
+    let noun = if names.iter().all(|n| n.starts_with(LIBRARY_PREFIX)) {
+        count(names.len(), "library", "libraries")
+    } else {
+        count(names.len(), "package", "packages")
+    };
+    let notable = notable(&names);
+    if notable.is_empty() {
+        format!("{} {noun}", verb(action))
+    } else {
+        format!("{} {noun} including {}", verb(action), enumerate(&notable))
+    }
+}
+
+// User name of a `Requested-By` value such as `alice (1000)`.
+fn initiator(entry: &HistoryEntry) -> String {
+    match &entry.requested_by {
+        Some(user) => user.split(" (").next().unwrap_or(user).to_string(),
+        None => ROOT_USER.to_string(),
+    }
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn summary(entry: &HistoryEntry) -> String {
+    let actions: Vec<String> = entry
+        .sorted_actions()
+        .iter()
+        .map(|a| describe_action(entry, a))
+        .collect();
+    let changes = if actions.is_empty() {
+        "Changed no packages".to_string()
+    } else {
+        capitalize(&actions.join("; "))
+    };
+    let command = format!("{} {}", entry.frontend, entry.full_command_line);
+    let mut summary = format!(
+        "{changes}; initiated by {} via `{}`",
+        initiator(entry),
+        command.trim_end()
+    );
+    if let Some(error) = &entry.error {
+        summary.push_str(&format!("; failed with `{}`", error.replace('\n', " ")));
+    }
+    summary
+}
+
+// One paragraph per transaction, meant to be pasted into change tickets.
+pub fn explain(query: Option<Vec<String>>) -> Result<()> {
+    if query.is_none() {
+        return Err(Error::Usage(
+            "`explain` requires at least one transaction ID".to_string(),
+        ));
+    }
+    for entry in matching_entries(query)? {
+        println!(
+            "Transaction {} on {}: {}.",
+            entry.id,
+            entry.start_date.format(LIST_DATE_FORMAT),
+            summary(&entry)
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,443 @@
+use crate::date::parse_duration;
+use crate::dpkg::{auto_installed_packages, status_packages, DpkgPackage};
+use crate::error::{Error, Result};
+use crate::history::{
+    history_entries, matching_entries, show_transactions, AlteredCount, DisplayOptions, Filters,
+    HistoryEntry, OutputFormat, ACTIONS, JSON_DATE_FORMAT,
+};
+use crate::json::Value;
+use crate::notes::Notes;
+use crate::storage;
+use chrono::prelude::*;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+pub(crate) const ARCHIVE_FORMAT: &str = "apt-history-archive";
+pub(crate) const ARCHIVE_FORMAT_VERSION: f64 = 1.0;
+const ICS_DATE_FORMAT: &str = "%Y%m%dT%H%M%S";
+const ICS_LINE_LIMIT: usize = 75;
+const ICS_PRODUCT_ID: &str = "-//apt-history//apt-history//EN";
+const PROMETHEUS_PERIODS: [&str; 2] = ["1d", "1w"];
+const REMOVING_ACTIONS: [&str; 2] = ["Remove", "Purge"];
+const SQLITE_SCHEMA: &str = "\
+CREATE TABLE transactions (
+  uuid TEXT PRIMARY KEY,
+  id INTEGER NOT NULL,
+  host TEXT NOT NULL,
+  tool TEXT NOT NULL,
+  command_line TEXT NOT NULL,
+  start_date TEXT NOT NULL,
+  end_date TEXT,
+  error TEXT
+);
+CREATE TABLE packages (
+  transaction_uuid TEXT NOT NULL REFERENCES transactions (uuid),
+  action TEXT NOT NULL,
+  name TEXT NOT NULL,
+  arch TEXT NOT NULL,
+  version TEXT
+);";
+const SQLITE_DATE_FORMAT: &str = "%F %T";
+
+fn ics_escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+// Content lines longer than 75 octets have to be folded, continuation lines start with a space.
+fn ics_fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut line_len = 0;
+    for c in line.chars() {
+        if line_len + c.len_utf8() > ICS_LINE_LIMIT {
+            folded.push_str("\r\n ");
+            line_len = 1;
+        }
+        folded.push(c);
+        line_len += c.len_utf8();
+    }
+    folded
+}
+
+fn ics_event(entry: &HistoryEntry, stamp: &str) -> Vec<String> {
+    let actions: Vec<&str> = entry.sorted_actions().iter().map(|a| a.as_str()).collect();
+
+    let summary = format!(
+        "{} ({} packages altered)",
+        entry.command_line,
+        entry.altered(AlteredCount::default())
+    );
+    let description = format!("Transaction {}: {}", entry.id, actions.join(", "));
+
+    let mut event = vec![
+        "BEGIN:VEVENT".to_string(),
+        // IDs change when logs are rotated, which would make calendars show events twice.
+        format!(
+            "UID:{}@apt-history",
+            entry.uuid.clone().unwrap_or_else(|| entry.fingerprint())
+        ),
+        format!("DTSTAMP:{stamp}"),
+        format!("DTSTART:{}", entry.start_date.format(ICS_DATE_FORMAT)),
+    ];
+    // Events of transactions without a logged end are instants at their start.
+    if let Some(end_date) = entry.logged_end_date() {
+        event.push(format!("DTEND:{}", end_date.format(ICS_DATE_FORMAT)));
+    }
+    event.extend([
+        format!("SUMMARY:{}", ics_escape(&summary)),
+        format!("DESCRIPTION:{}", ics_escape(&description)),
+        "END:VEVENT".to_string(),
+    ]);
+    event
+}
+
+fn ics(entries: &[HistoryEntry]) {
+    // Dates in apt history logs are in local time, so events use floating date-times.
+    let stamp = format!("{}Z", Utc::now().format(ICS_DATE_FORMAT));
+
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!("PRODID:{ICS_PRODUCT_ID}"),
+    ];
+    for entry in entries {
+        lines.extend(ics_event(entry, &stamp));
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    for line in lines {
+        print!("{}\r\n", ics_fold(&line));
+    }
+}
+
+fn dot_quote(text: &str) -> String {
+    let escaped = text
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n");
+    format!("\"{escaped}\"")
+}
+
+// Dependencies come from the currently installed versions, which may differ from the ones the
+// transaction installed if the packages have been upgraded since.
+fn dot_graph(
+    entry: &HistoryEntry,
+    installed: &HashMap<String, DpkgPackage>,
+    automatic: &HashSet<String>,
+) {
+    // Package name to `name:arch` nodes, a name can map to multiple nodes for multi-arch installs.
+    let mut nodes: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    let mut node_actions: BTreeMap<String, &String> = BTreeMap::new();
+    for (action, pkg_map) in entry.affected.iter() {
+        for (arch, pkgs) in pkg_map.iter() {
+            for pkg in pkgs {
+                let node = format!("{pkg}:{arch}");
+                nodes.entry(pkg.clone()).or_default().insert(node.clone());
+                node_actions.insert(node, action);
+            }
+        }
+    }
+
+    println!(
+        "digraph {} {{",
+        dot_quote(&format!("transaction {}", entry.id))
+    );
+    println!("    label={};", dot_quote(&entry.command_line));
+    for (node, action) in node_actions.iter() {
+        let style = if automatic.contains(node) {
+            "dashed"
+        } else {
+            "bold"
+        };
+        println!(
+            "    {} [label={}, style={style}];",
+            dot_quote(node),
+            dot_quote(&format!("{node}\n{action}"))
+        );
+    }
+
+    for node in node_actions.keys() {
+        let Some(package) = installed.get(node) else {
+            continue;
+        };
+        let dependencies: BTreeSet<String> = package.dependencies().into_iter().collect();
+        for dependency in dependencies {
+            for target in nodes.get(&dependency).into_iter().flatten() {
+                if target != node {
+                    println!("    {} -> {};", dot_quote(node), dot_quote(target));
+                }
+            }
+        }
+    }
+    println!("}}");
+}
+
+fn dot(entries: &[HistoryEntry]) {
+    let installed: HashMap<String, DpkgPackage> = status_packages()
+        .into_iter()
+        .map(|p| (format!("{}:{}", p.name, p.arch), p))
+        .collect();
+    let automatic = auto_installed_packages();
+
+    for entry in entries {
+        dot_graph(entry, &installed, &automatic);
+    }
+}
+
+fn prometheus_metric(name: &str, help: &str, samples: &[(String, i64)]) {
+    println!("# HELP {name} {help}");
+    println!("# TYPE {name} gauge");
+    for (labels, value) in samples {
+        println!("{name}{labels} {value}");
+    }
+}
+
+fn local_timestamp(date: &NaiveDateTime) -> i64 {
+    Local
+        .from_local_datetime(date)
+        .earliest()
+        .map(|d| d.timestamp())
+        .unwrap_or_else(|| date.and_utc().timestamp())
+}
+
+// Metrics in the textfile collector format of the Prometheus node exporter.
+fn prometheus(entries: &[HistoryEntry]) {
+    let last_end = entries
+        .iter()
+        .rev()
+        .find_map(|e| e.logged_end_date())
+        .map(|d| local_timestamp(&d))
+        .unwrap_or(0);
+    prometheus_metric(
+        "apt_history_last_transaction_timestamp_seconds",
+        "End time of the last apt transaction.",
+        &[(String::new(), last_end)],
+    );
+
+    let now = Local::now().naive_local();
+    let mut action_samples = vec![];
+    for period in PROMETHEUS_PERIODS {
+        let since = now - parse_duration(period).expect("invalid period");
+        let mut counts: BTreeMap<String, i64> =
+            ACTIONS.iter().map(|a| (a.to_string(), 0)).collect();
+        for entry in entries.iter().filter(|e| e.start_date >= since) {
+            for action in entry.affected.keys() {
+                *counts.entry(action.clone()).or_default() += 1;
+            }
+        }
+        for (action, count) in counts {
+            let labels = format!(
+                "{{action=\"{}\",period=\"{period}\"}}",
+                action.to_lowercase()
+            );
+            action_samples.push((labels, count));
+        }
+    }
+    prometheus_metric(
+        "apt_history_recent_transactions",
+        "Number of apt transactions containing an action within the period.",
+        &action_samples,
+    );
+
+    let failed = entries.iter().filter(|e| e.error.is_some()).count() as i64;
+    prometheus_metric(
+        "apt_history_failed_transactions",
+        "Number of apt transactions that reported an error.",
+        &[(String::new(), failed)],
+    );
+}
+
+fn sql_string(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "''"))
+}
+
+// SQL statements creating and populating tables of transactions and their packages, to be fed
+// to `sqlite3`. Transactions are keyed by UUID as IDs of imported hosts overlap, the fingerprint
+// stands in for transactions which have not been assigned one yet.
+fn sqlite(entries: &[HistoryEntry]) {
+    println!("BEGIN TRANSACTION;");
+    println!("{SQLITE_SCHEMA}");
+    for entry in entries {
+        let key = sql_string(&entry.uuid.clone().unwrap_or_else(|| entry.fingerprint()));
+        println!(
+            "INSERT INTO transactions VALUES ({key}, {}, {}, {}, {}, {}, {}, {});",
+            entry.id,
+            sql_string(&entry.host),
+            sql_string(&entry.frontend),
+            sql_string(&entry.command_line),
+            sql_string(&entry.start_date.format(SQLITE_DATE_FORMAT).to_string()),
+            entry
+                .logged_end_date()
+                .map_or("NULL".to_string(), |d| sql_string(
+                    &d.format(SQLITE_DATE_FORMAT).to_string()
+                )),
+            entry
+                .error
+                .as_deref()
+                .map_or("NULL".to_string(), sql_string)
+        );
+        for action in entry.sorted_actions() {
+            let mut packages: Vec<(&String, &String)> = entry.affected[action]
+                .iter()
+                .flat_map(|(arch, pkgs)| pkgs.iter().map(move |pkg| (pkg, arch)))
+                .collect();
+            packages.sort();
+            for (pkg, arch) in packages {
+                let version = entry
+                    .package_version(action, &format!("{pkg}:{arch}"))
+                    .map_or("NULL".to_string(), sql_string);
+                println!(
+                    "INSERT INTO packages VALUES ({key}, {}, {}, {}, {version});",
+                    sql_string(action),
+                    sql_string(pkg),
+                    sql_string(arch)
+                );
+            }
+        }
+    }
+    println!("COMMIT;");
+}
+
+// Versions packages were left at by the entries as `name:arch version` lines, packages removed by
+// their last change are left out and ones changed by overlapping entries are reported as uncertain.
+fn manifest(entries: &[HistoryEntry]) {
+    let mut packages: BTreeMap<String, Option<String>> = BTreeMap::new();
+    let mut changed_by: HashMap<String, u32> = HashMap::new();
+    let mut uncertain: BTreeSet<String> = BTreeSet::new();
+    for entry in entries {
+        for action in entry.sorted_actions() {
+            for (arch, pkgs) in &entry.affected[action] {
+                for pkg in pkgs {
+                    let package = format!("{pkg}:{arch}");
+                    if let Some(previous) = changed_by.insert(package.clone(), entry.id) {
+                        if entry.overlapping.contains(&previous) {
+                            uncertain.insert(package.clone());
+                        }
+                    }
+                    let version = if REMOVING_ACTIONS.contains(&action.as_str()) {
+                        None
+                    } else {
+                        entry
+                            .package_version(action, &package)
+                            .map(|v| v.to_string())
+                    };
+                    packages.insert(package, version);
+                }
+            }
+        }
+    }
+    for (package, version) in packages {
+        if let Some(version) = version {
+            println!("{package} {version}");
+        }
+    }
+    if !uncertain.is_empty() {
+        let uncertain: Vec<&str> = uncertain.iter().map(|p| p.as_str()).collect();
+        eprintln!(
+            "warning: packages changed by overlapping transactions may be at other versions: {}",
+            uncertain.join(", ")
+        );
+    }
+}
+
+// All details of transactions with their full command lines and notes, one transaction per line.
+// Archives do not depend on the logs or any other file of the host they were written on.
+pub(crate) fn archive_document(entries: &[HistoryEntry], notes: &Notes) -> String {
+    let transactions: Vec<String> = entries
+        .iter()
+        .map(|entry| {
+            let mut full = entry.clone();
+            full.command_line = entry.full_command_line.clone();
+            let Value::Object(mut fields) = full.to_json(AlteredCount::Packages) else {
+                unreachable!("transaction is not a JSON object");
+            };
+            let fingerprint = entry.fingerprint();
+            let note = notes.0.get(&fingerprint);
+            fields.push((
+                "note".to_string(),
+                note.map_or(Value::Null, |n| n.to_json(&fingerprint)),
+            ));
+            // Kept so that imported transactions have the same fingerprint.
+            fields.push((
+                "logged_command_line".to_string(),
+                Value::String(entry.logged_command_line.clone()),
+            ));
+            format!("\n{}", Value::Object(fields))
+        })
+        .collect();
+    let end = if transactions.is_empty() { "" } else { "\n" };
+    format!(
+        "{{\"format\":{},\"version\":{ARCHIVE_FORMAT_VERSION},\"exported\":{},\
+        \"transactions\":[{}{end}]}}\n",
+        Value::String(ARCHIVE_FORMAT.to_string()),
+        Value::String(Local::now().format(JSON_DATE_FORMAT).to_string()),
+        transactions.join(",")
+    )
+}
+
+fn archive(entries: &[HistoryEntry], path: &str) -> Result<()> {
+    let document = archive_document(entries, &Notes::load()?);
+    storage::replace(Path::new(path), |temporary| {
+        fs::write(temporary, &document).map_err(|source| Error::OutputFile {
+            path: path.to_string(),
+            source,
+        })
+    })?;
+    println!("Exported {} transactions to {path}", entries.len());
+    Ok(())
+}
+
+// Formats entries can be exported in, besides the output formats of `info`.
+pub struct Formats {
+    pub dot: bool,
+    pub ics: bool,
+    pub manifest: bool,
+    pub prometheus: bool,
+    pub sqlite: bool,
+    // Path of an archive of the whole history.
+    pub to: Option<String>,
+}
+
+// Exports all entries matching the filters, or only the ones matching the query if given. Graphs
+// are drawn for the last entry only unless there is a query, as with `info`.
+pub fn export(
+    query: Option<Vec<String>>,
+    filters: &Filters,
+    options: &DisplayOptions,
+    formats: &Formats,
+) -> Result<()> {
+    if formats.dot {
+        dot(&filters.apply(matching_entries(query)?)?);
+        return Ok(());
+    }
+
+    let entries = if query.is_some() {
+        matching_entries(query)?
+    } else {
+        history_entries()?
+    };
+    let entries = filters.apply(entries)?;
+    if let Some(path) = &formats.to {
+        archive(&entries, path)?;
+    } else if formats.ics {
+        ics(&entries);
+    } else if formats.manifest {
+        manifest(&entries);
+    } else if formats.prometheus {
+        prometheus(&entries);
+    } else if formats.sqlite {
+        sqlite(&entries);
+    } else if options.output != OutputFormat::Table {
+        show_transactions(&entries, options, None)?;
+    } else {
+        return Err(Error::Usage(
+            "no export format given, use one of: `--dot`, `--ics`, `--manifest`, `--prometheus`, \
+            `--sqlite`, `--to` or `--output`"
+                .to_string(),
+        ));
+    }
+    Ok(())
+}
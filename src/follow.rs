@@ -0,0 +1,174 @@
+use crate::date::{format_duration, parse_duration};
+use crate::error::{Error, Result};
+use crate::history::{
+    entries_and_running, show_transactions, DisplayOptions, Filters, HistoryEntry, APT_LOG_PATH,
+    CURRENT_HISTORY_FILE,
+};
+use chrono::{Duration, Local};
+use std::collections::HashSet;
+use std::ffi::{c_char, c_int, c_short, c_ulong, CString};
+use std::fs::File;
+use std::io::{self, Read};
+use std::os::fd::{AsRawFd, FromRawFd};
+
+// Flags and events of inotify(7).
+const IN_CLOEXEC: c_int = 0o2000000;
+const IN_CREATE: u32 = 0x100;
+const IN_MODIFY: u32 = 0x2;
+const IN_MOVED_TO: u32 = 0x80;
+// Event of poll(2) for data to read.
+const POLLIN: c_short = 0x1;
+// Size of the fixed part of `struct inotify_event`, which is followed by the name.
+const EVENT_HEADER_LEN: usize = 16;
+// Room for at least one event with a name of the maximum length.
+const EVENT_BUFFER_LEN: usize = 4096;
+
+// `struct pollfd` of poll(2).
+#[repr(C)]
+struct PollFd {
+    fd: c_int,
+    events: c_short,
+    revents: c_short,
+}
+
+extern "C" {
+    fn inotify_init1(flags: c_int) -> c_int;
+    fn inotify_add_watch(fd: c_int, pathname: *const c_char, mask: u32) -> c_int;
+    fn poll(fds: *mut PollFd, nfds: c_ulong, timeout: c_int) -> c_int;
+}
+
+// Watches the apt log directory rather than the log itself so that the current log is still
+// followed after logrotate replaces it.
+fn watch_log_dir() -> Result<File> {
+    let dir_error = |source| Error::LogDir {
+        path: APT_LOG_PATH.to_string(),
+        source,
+    };
+    let path = CString::new(APT_LOG_PATH).expect("error converting log directory path");
+    // SAFETY: both calls only take plain values and a NUL terminated path which outlives them,
+    // and the descriptor is owned by the returned file from here on.
+    unsafe {
+        let fd = inotify_init1(IN_CLOEXEC);
+        if fd < 0 {
+            return Err(dir_error(io::Error::last_os_error()));
+        }
+        let watches = File::from_raw_fd(fd);
+        if inotify_add_watch(fd, path.as_ptr(), IN_CREATE | IN_MODIFY | IN_MOVED_TO) < 0 {
+            return Err(dir_error(io::Error::last_os_error()));
+        }
+        Ok(watches)
+    }
+}
+
+// Blocks until files of the log directory change or the timeout passes, forever without one,
+// and tells whether the current log is one of the changed files.
+fn wait_for_change(watches: &mut File, timeout: Option<Duration>) -> Result<bool> {
+    let dir_error = |source| Error::LogDir {
+        path: APT_LOG_PATH.to_string(),
+        source,
+    };
+    let timeout = timeout.map_or(-1, |t| {
+        c_int::try_from(t.num_milliseconds().max(0)).unwrap_or(c_int::MAX)
+    });
+    let mut watched = PollFd {
+        fd: watches.as_raw_fd(),
+        events: POLLIN,
+        revents: 0,
+    };
+    // SAFETY: the descriptor stays open while polling and the single entry outlives the call.
+    let ready = unsafe { poll(&mut watched, 1, timeout) };
+    if ready < 0 {
+        let error = io::Error::last_os_error();
+        if error.kind() == io::ErrorKind::Interrupted {
+            return Ok(false);
+        }
+        return Err(dir_error(error));
+    }
+    if ready == 0 {
+        return Ok(false);
+    }
+    let mut buffer = [0; EVENT_BUFFER_LEN];
+    let read = watches.read(&mut buffer).map_err(dir_error)?;
+    let mut offset = 0;
+    let mut current = false;
+    while offset + EVENT_HEADER_LEN <= read {
+        let name_len = u32::from_ne_bytes(
+            buffer[offset + 12..offset + EVENT_HEADER_LEN]
+                .try_into()
+                .expect("error reading event name length"),
+        ) as usize;
+        let name_start = offset + EVENT_HEADER_LEN;
+        let name = &buffer[name_start..(name_start + name_len).min(read)];
+        // Names are padded with NUL bytes.
+        let name = name.split(|b| *b == 0).next().unwrap_or_default();
+        current |= name == CURRENT_HISTORY_FILE.as_bytes();
+        offset = name_start + name_len;
+    }
+    Ok(current)
+}
+
+// Finished transactions not seen before, which are added to the seen ones, and the transaction
+// apt is running, if any.
+fn new_entries(seen: &mut HashSet<String>) -> Result<(Vec<HistoryEntry>, Option<HistoryEntry>)> {
+    let (entries, running) = entries_and_running()?;
+    let entries = entries
+        .into_iter()
+        .filter(|e| e.finished && seen.insert(e.fingerprint()))
+        .collect();
+    Ok((entries, running))
+}
+
+// Time left until a running transaction counts as stuck, none if no transaction is running or
+// it has been warned about already.
+fn stuck_timeout(
+    running: &Option<HistoryEntry>,
+    warned: &HashSet<String>,
+    stuck_after: Duration,
+) -> Option<Duration> {
+    let running = running.as_ref()?;
+    if warned.contains(&running.fingerprint()) {
+        return None;
+    }
+    Some(running.start_date + stuck_after - Local::now().naive_local())
+}
+
+// Shows each transaction as it finishes, as `info` does, until interrupted. Transactions which
+// had finished before are not shown. A warning is shown once a transaction has been running for
+// longer than `stuck_after`, as apt may be waiting at a prompt, e.g. an unattended upgrade asking
+// about a changed configuration file.
+pub fn follow(filters: &Filters, options: &DisplayOptions, stuck_after: &str) -> Result<()> {
+    let stuck_after = parse_duration(stuck_after).map_err(Error::Usage)?;
+    let mut watches = watch_log_dir()?;
+    let mut seen = HashSet::new();
+    let mut warned = HashSet::new();
+    let (_, mut running) = new_entries(&mut seen)?;
+    eprintln!("Following {APT_LOG_PATH}/{CURRENT_HISTORY_FILE}, press Ctrl-C to stop");
+    let mut shown = false;
+    loop {
+        let timeout = stuck_timeout(&running, &warned, stuck_after);
+        let changed = wait_for_change(&mut watches, timeout)?;
+        if !changed && timeout.is_none() {
+            continue;
+        }
+        // Looked up again on timeouts too, in case apt exited without logging the end.
+        let (entries, current) = new_entries(&mut seen)?;
+        running = current;
+        if stuck_timeout(&running, &warned, stuck_after).is_some_and(|t| t <= Duration::zero()) {
+            let entry = running.as_ref().expect("error getting running transaction");
+            eprintln!(
+                "Warning: transaction started {} ({} {}) has been running for longer than {}, apt may be stuck at a prompt",
+                entry.start_date, entry.frontend, entry.command_line, format_duration(stuck_after)
+            );
+            warned.insert(entry.fingerprint());
+        }
+        let entries = filters.apply(entries)?;
+        if entries.is_empty() {
+            continue;
+        }
+        if shown {
+            println!();
+        }
+        show_transactions(&entries, options, None)?;
+        shown = true;
+    }
+}
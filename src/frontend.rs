@@ -0,0 +1,111 @@
+// Program names as they appear in recorded command lines, mapped to the front-end they belong to.
+const FRONTENDS: [(&str, &str); 10] = [
+    ("apt", "apt"),
+    ("apt-get", "apt-get"),
+    ("aptdaemon", "aptdaemon"),
+    ("aptitude", "aptitude"),
+    ("aptitude-curses", "aptitude"),
+    ("packagekit", "packagekit"),
+    ("packagekitd", "packagekit"),
+    ("synaptic", "synaptic"),
+    ("unattended-upgrade", "unattended-upgrades"),
+    ("unattended-upgrades", "unattended-upgrades"),
+];
+const CHROOT_PROGRAM: &str = "chroot";
+// Tools that run apt against a root directory they set up themselves.
+const CHROOT_TOOLS: [&str; 7] = [
+    "cowbuilder",
+    "debootstrap",
+    "mk-sbuild",
+    "mmdebstrap",
+    "pbuilder",
+    "sbuild",
+    "schroot",
+];
+const INTERPRETER_PREFIX: &str = "python";
+const OPTION_FLAGS: [&str; 2] = ["-o", "--option"];
+const ROOT_FLAG: &str = "--root";
+const ROOT_OPTIONS: [&str; 2] = ["Dir", "RootDir"];
+const UNKNOWN_FRONTEND: &str = "unknown";
+
+pub(crate) struct Invocation {
+    pub(crate) arguments: String,
+    pub(crate) frontend: String,
+    // Root directory the changes were made in when that is not the host system, or the tool that
+    // set it up if the directory is not known.
+    pub(crate) foreign_root: Option<String>,
+}
+
+fn program_name(token: &str) -> &str {
+    token.rsplit('/').next().unwrap_or(token)
+}
+
+fn root_option(option: &str) -> Option<&str> {
+    let (name, value) = option.split_once('=')?;
+    ROOT_OPTIONS.contains(&name).then_some(value)
+}
+
+// Root directory set with options like `-o Dir=/srv/chroot` or `--root=/srv/chroot`.
+fn root_from_arguments(arguments: &[&str]) -> Option<String> {
+    let mut args = arguments.iter();
+    while let Some(arg) = args.next() {
+        let root = if OPTION_FLAGS.contains(arg) {
+            args.next().and_then(|o| root_option(o))
+        } else if let Some(option) = arg.strip_prefix("-o") {
+            root_option(option.trim_start_matches('='))
+        } else if *arg == ROOT_FLAG {
+            args.next().copied()
+        } else {
+            arg.strip_prefix(ROOT_FLAG)
+                .and_then(|r| r.strip_prefix('='))
+        };
+        if let Some(root) = root.filter(|r| *r != "/") {
+            return Some(root.to_string());
+        }
+    }
+    None
+}
+
+// Splits a recorded command line into the front-end that ran it and its arguments. Scripts run
+// through an interpreter such as `/usr/bin/python3 /usr/bin/unattended-upgrade` are named after
+// the script, unknown programs after themselves.
+pub(crate) fn classify(command_line: &str) -> Invocation {
+    let mut tokens: Vec<&str> = command_line.split_whitespace().collect();
+    if tokens
+        .first()
+        .is_some_and(|t| program_name(t).starts_with(INTERPRETER_PREFIX) && tokens.len() > 1)
+    {
+        tokens.remove(0);
+    }
+
+    let Some(program) = tokens.first() else {
+        return Invocation {
+            arguments: String::new(),
+            frontend: UNKNOWN_FRONTEND.to_string(),
+            foreign_root: None,
+        };
+    };
+
+    let name = program_name(program);
+    // Commands run through `chroot <dir>` are classified as if they were run directly.
+    if name == CHROOT_PROGRAM && tokens.len() > 2 {
+        let mut invocation = classify(&tokens[2..].join(" "));
+        invocation.foreign_root.get_or_insert(tokens[1].to_string());
+        return invocation;
+    }
+    let frontend = FRONTENDS
+        .iter()
+        .find(|(p, _)| *p == name)
+        .map(|(_, f)| *f)
+        .unwrap_or(name);
+    let foreign_root = if CHROOT_TOOLS.contains(&name) {
+        Some(name.to_string())
+    } else {
+        root_from_arguments(&tokens[1..])
+    };
+    Invocation {
+        arguments: tokens[1..].join(" "),
+        frontend: frontend.to_string(),
+        foreign_root,
+    }
+}
@@ -1,42 +1,188 @@
-use ansi_term;
+use crate::apt::{package_classes, PackageClass};
+use crate::bundle;
+use crate::color::{self, Severity};
+use crate::config::{Config, InfoLayout, Truncation, TruncationStrategy};
+use crate::csv;
+use crate::date;
+use crate::error::{Error, Result};
+use crate::frontend;
+use crate::hooks;
+use crate::i18n::tr;
+use crate::imports;
+use crate::incomplete;
+use crate::json::Value;
+use crate::lock;
+use crate::markdown;
+use crate::notes::{Note, Notes};
+use crate::privileges;
+use crate::rename::{self, Rename};
+use crate::render;
+use crate::sources::{self, HistorySource};
+use crate::template::Template;
+use crate::termlog::{self, Download};
+use crate::terminal;
+use crate::users;
+use crate::uuids;
+use crate::vulns::VulnerabilityData;
 use chrono::prelude::*;
+use clap::ValueEnum;
 use flate2::read::GzDecoder;
 use regex::Regex;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::fs::File;
-use std::io::BufRead;
+use std::io::{BufRead, Read};
 use std::ops::Add;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::{fs, io};
 use std::cmp::Ordering;
 use stybulate::{Cell, Headers, Style, Table};
 
-const APT_LOG_PATH: &str = "/var/log/apt";
-const APT_HISTORY_LOG_PATTERN: &str = r"history\.log(\.[0-9]+\.gz)?";
+// Actions in the order they are shown in.
+pub(crate) const ACTIONS: [&str; 6] = [
+    "Install",
+    "Upgrade",
+    "Downgrade",
+    "Reinstall",
+    "Remove",
+    "Purge",
+];
+pub(crate) const APT_LOG_PATH: &str = "/var/log/apt";
+// Rotated logs are numbered and usually compressed, except for the most recent one when
+// logrotate is configured with `delaycompress`.
+const APT_HISTORY_LOG_PATTERN: &str = r"^history\.log(?:\.([0-9]+)(?:\.gz)?)?$";
+const AUTOMATIC_MARKER: &str = "automatic";
 const COMMAND_LINE_ELLIPSIS: &str = " <...>";
-const CURRENT_HISTORY_FILE: &str = "history.log";
-const HEADERS: [&str; 5] = [
+const COMMAND_LINE_MIDDLE_ELLIPSIS: &str = " <...> ";
+const MIN_COMMAND_LINE_WIDTH: usize = 20;
+// Characters of a comment shown in the `comment` column of `list`, `info` shows all of it.
+const MAX_COMMENT_LENGTH: usize = 40;
+pub(crate) const CURRENT_HISTORY_FILE: &str = "history.log";
+const INFO_TABLE_HEADERS: [&str; 5] = [
     "ID",
-    "Command line",
     "Date and time",
-    "Action(s)",
-    "Altered",
+    "Command line",
+    "Action",
+    "Package",
+];
+const TEMPLATE_VARIABLES: [&str; 17] = [
+    "actions",
+    "altered",
+    "command_line",
+    "duration",
+    "end_date",
+    "error",
+    "foreign_root",
+    "host",
+    "id",
+    "packages",
+    "source",
+    "start_date",
+    "tags",
+    "tool",
+    "user",
+    "uuid",
+    "versions",
 ];
-const SEPARATOR_CHAR: char = '-';
-const SEPARATOR_LENGTH: usize = 79;
+// Transactions installing packages with this prefix change the kernel.
+pub(crate) const KERNEL_PACKAGE_PREFIX: &str = "linux-image-";
+// Front-end which only installs security updates unless configured otherwise.
+const SECURITY_FRONTEND: &str = "unattended-upgrades";
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+pub(crate) const HOSTNAME_PATH: &str = "/proc/sys/kernel/hostname";
 const INFO_DATE_FORMAT: &str = "%a %b %e %T %Y";
-const LIST_DATE_FORMAT: &str = "%F %H:%M";
-const LOG_FILE_DATE_FORMAT: &str = "%F  %T";
-const MAX_COMMAND_LINE_LEN: usize = 100;
+pub(crate) const JSON_DATE_FORMAT: &str = "%FT%T";
+pub(crate) const LIST_DATE_FORMAT: &str = "%F %H:%M";
+pub(crate) const LOG_FILE_DATE_FORMAT: &str = "%F  %T";
+
+// FNV-1a hash of a key as 16 hex digits, which is stable across runs and versions unlike the
+// standard library's hashers.
+pub(crate) fn fnv_hash(key: &str) -> String {
+    let hash = key.bytes().fold(FNV_OFFSET_BASIS, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+    });
+    format!("{hash:016x}")
+}
 
+// Where an entry was parsed from, as a half-open byte range.
+#[derive(Clone, Default)]
+pub(crate) struct Provenance {
+    pub(crate) end: u64,
+    pub(crate) file: String,
+    pub(crate) start: u64,
+}
+
+impl Provenance {
+    fn describe(&self) -> String {
+        format!("{} bytes {}-{}", self.file, self.start, self.end)
+    }
+
+    // The lines an entry was parsed from, exactly as they appear in the log. The log may have
+    // been rotated or pruned since it was parsed, e.g. while following it, in which case the
+    // range no longer holds a transaction, which starts with its start date in logs and is an
+    // object in imported archives.
+    fn raw_block(&self) -> Result<String> {
+        let log_error = |source| Error::LogFile {
+            path: self.file.clone(),
+            source,
+        };
+        let mut content = vec![];
+        open_log(&self.file)
+            .and_then(|mut log| log.read_to_end(&mut content))
+            .map_err(log_error)?;
+        content
+            .get(self.start as usize..self.end as usize)
+            .map(|block| String::from_utf8_lossy(block).to_string())
+            .filter(|block| block.starts_with("Start-Date:") || block.starts_with('{'))
+            .ok_or_else(|| {
+                log_error(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "the log changed since it was read",
+                ))
+            })
+    }
+}
+
+/// A transaction as parsed from the history logs.
 #[derive(Clone)]
-struct HistoryEntry {
-    affected: HashMap<String, HashMap<String, HashSet<String>>>,
-    altered: usize,
-    command_line: String,
-    end_date: NaiveDateTime,
-    id: u32,
-    start_date: NaiveDateTime,
+pub struct HistoryEntry {
+    pub affected: HashMap<String, HashMap<String, HashSet<String>>>,
+    pub command_line: String,
+    // Comment of the note of the transaction, only loaded and summarized for the `comment` column
+    // of `list`.
+    pub comment: Option<String>,
+    pub end_date: NaiveDateTime,
+    pub error: Option<String>,
+    // Whether the end date was logged, it is not until the transaction is over.
+    pub finished: bool,
+    // Command line arguments as they are before truncation.
+    pub full_command_line: String,
+    // Host the entry was recorded on, entries are referred to as `<host>:<id>` to tell apart
+    // entries of different hosts.
+    pub host: String,
+    // Set for changes made to a root directory other than the host system, see `frontend`.
+    pub foreign_root: Option<String>,
+    pub frontend: String,
+    pub id: u32,
+    // The `Commandline` value as it was logged, before rewrite rules and with the front-end.
+    pub logged_command_line: String,
+    // IDs of entries running at the same time as this one, which makes the order of their changes
+    // uncertain.
+    pub overlapping: Vec<u32>,
+    pub(crate) provenance: Provenance,
+    // User running the transaction through `sudo` with the name resolved from its UID, e.g.
+    // `alice (1000)`.
+    pub requested_by: Option<String>,
+    // Lower values take precedence when ordering entries with identical start dates.
+    pub(crate) source_priority: usize,
+    pub start_date: NaiveDateTime,
+    // Labels added by the `[hooks]` configuration, see `hooks`.
+    pub tags: Vec<String>,
+    // Persistent identifier of the transaction, see `uuids::assign`.
+    pub uuid: Option<String>,
+    // Version details per action and `name:arch` package, exactly as they appear in parentheses
+    // in action lines, e.g. `1.2-1, automatic` for installs or `1.1-1, 1.2-1` for upgrades.
+    pub versions: HashMap<String, HashMap<String, String>>,
 }
 
 impl HistoryEntry {
@@ -45,117 +191,448 @@ impl HistoryEntry {
             ..Default::default()
         }
     }
+
+    // Version a package ended up at after the transaction for installs and upgrades, or the
+    // version that was removed for removals.
+    pub(crate) fn package_version(&self, action: &str, package: &str) -> Option<&str> {
+        self.package_versions(action, package).pop()
+    }
+
+    // Versions of a package logged for an action, the old and the new one for upgrades and
+    // downgrades, or a single one otherwise.
+    pub(crate) fn package_versions(&self, action: &str, package: &str) -> Vec<&str> {
+        self.versions
+            .get(action)
+            .and_then(|v| v.get(package))
+            .map(|v| v.split(", ").filter(|v| *v != AUTOMATIC_MARKER).collect())
+            .unwrap_or_default()
+    }
+
+    // When the transaction ended, unknown unless its end date was logged.
+    pub(crate) fn logged_end_date(&self) -> Option<NaiveDateTime> {
+        self.finished.then_some(self.end_date)
+    }
+
+    // How long the transaction took, unknown unless its end date was logged.
+    pub(crate) fn duration(&self) -> Option<chrono::Duration> {
+        self.finished.then(|| self.end_date - self.start_date)
+    }
+
+    // Key of the transaction which stays the same when logs are rotated, read on another machine
+    // or rewrite rules change, unlike its ID: an FNV-1a hash of the start date and the logged
+    // command line.
+    pub(crate) fn fingerprint(&self) -> String {
+        let key = format!(
+            "{}\0{}",
+            self.start_date.format(JSON_DATE_FORMAT),
+            self.logged_command_line
+        );
+        fnv_hash(&key)
+    }
+
+    // Whether a package was installed as a dependency rather than on request.
+    pub(crate) fn is_automatic(&self, action: &str, package: &str) -> bool {
+        self.versions
+            .get(action)
+            .and_then(|v| v.get(package))
+            .is_some_and(|v| v.split(", ").any(|v| v == AUTOMATIC_MARKER))
+    }
+
+    pub(crate) fn altered(&self, counting: AlteredCount) -> usize {
+        match counting {
+            AlteredCount::Operations => self
+                .affected
+                .values()
+                .flat_map(|pkg_map| pkg_map.values())
+                .map(|pkgs| pkgs.len())
+                .sum(),
+            AlteredCount::Packages => {
+                let packages: HashSet<(&String, &String)> = self
+                    .affected
+                    .values()
+                    .flat_map(|pkg_map| pkg_map.iter())
+                    .flat_map(|(arch, pkgs)| pkgs.iter().map(move |pkg| (pkg, arch)))
+                    .collect();
+                packages.len()
+            }
+        }
+    }
+
+    // Value of one of `TEMPLATE_VARIABLES`, lists are separated by spaces.
+    fn template_value(&self, variable: &str, counting: AlteredCount) -> String {
+        match variable {
+            "actions" => self
+                .sorted_actions()
+                .iter()
+                .map(|a| a.as_str())
+                .collect::<Vec<_>>()
+                .join(" "),
+            "altered" => self.altered(counting).to_string(),
+            "command_line" => self.command_line.clone(),
+            "duration" => self
+                .duration()
+                .map(|d| d.num_seconds().to_string())
+                .unwrap_or_default(),
+            "end_date" => self
+                .logged_end_date()
+                .map(|d| d.format(LIST_DATE_FORMAT).to_string())
+                .unwrap_or_default(),
+            "error" => self.error.clone().unwrap_or_default(),
+            "foreign_root" => self.foreign_root.clone().unwrap_or_default(),
+            "host" => self.host.clone(),
+            "id" => self.id.to_string(),
+            "packages" => altered_packages(self)
+                .into_iter()
+                .map(|(_, pkg)| pkg)
+                .collect::<Vec<_>>()
+                .join(" "),
+            "source" => self.provenance.describe(),
+            "start_date" => self.start_date.format(LIST_DATE_FORMAT).to_string(),
+            "tags" => self.tags.join(" "),
+            "tool" => self.frontend.clone(),
+            "user" => self.requested_by.clone().unwrap_or_default(),
+            "uuid" => self.uuid.clone().unwrap_or_default(),
+            "versions" => altered_packages(self)
+                .into_iter()
+                .filter_map(|(action, pkg)| {
+                    let version = self.package_version(action, &pkg)?;
+                    Some(format!("{pkg}={version}"))
+                })
+                .collect::<Vec<_>>()
+                .join(" "),
+            _ => unreachable!("unknown template variable `{variable}`"),
+        }
+    }
+
+    // Reasons the transaction stands out, the most important first.
+    pub(crate) fn severities(&self) -> Vec<Severity> {
+        let mut severities = vec![];
+        if self.error.is_some() {
+            severities.push(Severity::Failed);
+        }
+        if self.frontend == SECURITY_FRONTEND {
+            severities.push(Severity::Security);
+        }
+        let kernel = self
+            .affected
+            .values()
+            .flat_map(|pkg_map| pkg_map.values())
+            .any(|pkgs| pkgs.iter().any(|p| p.starts_with(KERNEL_PACKAGE_PREFIX)));
+        if kernel {
+            severities.push(Severity::Kernel);
+        }
+        severities
+    }
+
+    fn severity_labels(&self) -> String {
+        let labels: Vec<String> = self.severities().iter().map(|s| s.label()).collect();
+        labels.join(", ")
+    }
+
+    // Front-end name, marked when changes were not made to the host system.
+    fn tool_label(&self) -> String {
+        match self.foreign_root {
+            Some(_) => format!("{} (chroot)", self.frontend),
+            None => self.frontend.clone(),
+        }
+    }
+
+    pub(crate) fn sorted_actions(&self) -> Vec<&String> {
+        let mut actions: Vec<&String> = self.affected.keys().collect();
+        actions.sort_by_key(|a| action_rank(a));
+        actions
+    }
+
+    // Fields shown by `list`.
+    fn summary_fields(&self, counting: AlteredCount) -> Vec<(String, Value)> {
+        let actions = self
+            .sorted_actions()
+            .into_iter()
+            .map(|a| Value::String(a.clone()))
+            .collect();
+        vec![
+            ("id".to_string(), Value::Number(self.id as f64)),
+            (
+                "uuid".to_string(),
+                self.uuid.clone().map_or(Value::Null, Value::String),
+            ),
+            ("host".to_string(), Value::String(self.host.clone())),
+            ("tool".to_string(), Value::String(self.frontend.clone())),
+            (
+                "user".to_string(),
+                self.requested_by.clone().map_or(Value::Null, Value::String),
+            ),
+            (
+                "foreign_root".to_string(),
+                self.foreign_root.clone().map_or(Value::Null, Value::String),
+            ),
+            ("command_line".to_string(), Value::String(self.command_line.clone())),
+            (
+                "start_date".to_string(),
+                Value::String(self.start_date.format(JSON_DATE_FORMAT).to_string()),
+            ),
+            (
+                "end_date".to_string(),
+                self.logged_end_date().map_or(Value::Null, |d| {
+                    Value::String(d.format(JSON_DATE_FORMAT).to_string())
+                }),
+            ),
+            ("actions".to_string(), Value::Array(actions)),
+            (
+                "altered".to_string(),
+                Value::Number(self.altered(counting) as f64),
+            ),
+            (
+                "tags".to_string(),
+                Value::Array(self.tags.iter().map(|t| Value::String(t.clone())).collect()),
+            ),
+        ]
+    }
+
+    pub(crate) fn to_summary_json(&self, counting: AlteredCount) -> Value {
+        Value::Object(self.summary_fields(counting))
+    }
+
+    // Full transaction detail, packages are keyed by action and then by architecture and
+    // versions by action and then by `name:arch` package.
+    pub(crate) fn to_json(&self, counting: AlteredCount) -> Value {
+        let mut fields = self.summary_fields(counting);
+        fields.push((
+            "error".to_string(),
+            self.error.clone().map_or(Value::Null, Value::String),
+        ));
+
+        let mut packages = vec![];
+        let mut versions = vec![];
+        for action in self.sorted_actions() {
+            let pkg_map = &self.affected[action];
+            let mut archs: Vec<&String> = pkg_map.keys().collect();
+            archs.sort();
+            let by_arch = archs
+                .into_iter()
+                .map(|arch| {
+                    let mut pkgs: Vec<&String> = pkg_map[arch].iter().collect();
+                    pkgs.sort();
+                    let pkgs = pkgs.into_iter().map(|p| Value::String(p.clone())).collect();
+                    (arch.clone(), Value::Array(pkgs))
+                })
+                .collect();
+            packages.push((action.clone(), Value::Object(by_arch)));
+
+            let mut action_versions: Vec<(&String, &String)> = self
+                .versions
+                .get(action)
+                .map(|v| v.iter().collect())
+                .unwrap_or_default();
+            action_versions.sort();
+            let action_versions = action_versions
+                .into_iter()
+                .map(|(pkg, version)| (pkg.clone(), Value::String(version.clone())))
+                .collect();
+            versions.push((action.clone(), Value::Object(action_versions)));
+        }
+        fields.push(("packages".to_string(), Value::Object(packages)));
+        fields.push(("versions".to_string(), Value::Object(versions)));
+        Value::Object(fields)
+    }
 }
 
 impl Default for HistoryEntry {
     fn default() -> Self {
         HistoryEntry {
             affected: HashMap::new(),
-            altered: 0,
             command_line: "".to_string(),
+            comment: None,
             end_date: Local::now().naive_local(),
+            error: None,
+            finished: false,
+            foreign_root: None,
+            full_command_line: "".to_string(),
+            host: "".to_string(),
+            frontend: "".to_string(),
             id: 0,
+            logged_command_line: "".to_string(),
+            overlapping: vec![],
+            provenance: Provenance::default(),
+            requested_by: None,
+            source_priority: 0,
             start_date: Local::now().naive_local(),
+            tags: vec![],
+            uuid: None,
+            versions: HashMap::new(),
         }
     }
 }
 
+fn head(text: &str, length: usize) -> &str {
+    text.char_indices()
+        .nth(length)
+        .map_or(text, |(index, _)| &text[..index])
+}
+
+fn tail(text: &str, length: usize) -> &str {
+    let skip = text.chars().count().saturating_sub(length);
+    text.char_indices()
+        .nth(skip)
+        .map_or("", |(index, _)| &text[index..])
+}
+
+// First line of a comment, shortened like command lines if it is longer than fits in a column.
+fn comment_summary(comment: &str) -> String {
+    let first_line = comment.lines().next().unwrap_or_default();
+    let elided = first_line.chars().count() > MAX_COMMENT_LENGTH || comment.contains('\n');
+    if !elided {
+        return first_line.to_string();
+    }
+    let length = MAX_COMMENT_LENGTH.saturating_sub(COMMAND_LINE_ELLIPSIS.len());
+    head(first_line, length).to_string().add(COMMAND_LINE_ELLIPSIS)
+}
+
+// Shortens command lines longer than the maximum length, lengths are counted in characters.
+pub(crate) fn truncate_command_line(command_line: String, truncation: &Truncation) -> String {
+    let max_length = truncation.max_length;
+    if command_line.chars().count() <= max_length {
+        return command_line;
+    }
+
+    match truncation.strategy {
+        TruncationStrategy::Head => {
+            let length = max_length.saturating_sub(COMMAND_LINE_ELLIPSIS.len());
+            head(&command_line, length).to_string().add(COMMAND_LINE_ELLIPSIS)
+        }
+        TruncationStrategy::Middle => {
+            // Most of the space goes to the end, which cuts at word boundaries where possible.
+            let budget = max_length.saturating_sub(COMMAND_LINE_MIDDLE_ELLIPSIS.len());
+            let start = head(&command_line, budget / 3);
+            let start = start.rsplit_once(' ').map_or(start, |(start, _)| start);
+            let end = tail(&command_line, budget - budget / 3);
+            let end = end.split_once(' ').map_or(end, |(_, rest)| rest);
+            format!("{start}{COMMAND_LINE_MIDDLE_ELLIPSIS}{end}")
+        }
+        TruncationStrategy::None => command_line,
+    }
+}
+
 fn finalize_entry(
     entry: &mut HistoryEntry,
     index: u32,
     package_map: &HashMap<String, HashMap<String, HashSet<String>>>,
+    config: &Config,
 ) {
     entry.id = index;
+    entry.logged_command_line = entry.command_line.clone();
 
-    let mut command_line = entry.command_line.clone();
-    if command_line.len() > MAX_COMMAND_LINE_LEN {
-        command_line = command_line[0..MAX_COMMAND_LINE_LEN - COMMAND_LINE_ELLIPSIS.len()]
-            .to_string()
-            .add(COMMAND_LINE_ELLIPSIS);
-    }
-    if command_line.starts_with("apt ") {
-        command_line = command_line[4..].to_string();
-    }
-    entry.command_line = command_line;
+    let command_line = config
+        .rewrite_rules
+        .iter()
+        .fold(entry.command_line.clone(), |c, rule| rule.apply(&c));
+    // The front-end is shown separately, so only its arguments are kept in the command line.
+    let invocation = frontend::classify(&command_line);
+    entry.full_command_line = invocation.arguments.clone();
+    entry.command_line = truncate_command_line(invocation.arguments, &config.truncation);
+    entry.frontend = invocation.frontend;
+    entry.foreign_root = invocation.foreign_root;
 
-    let mut altered = 0;
-    for packages in package_map.values() {
-        for pkgs in packages.values() {
-            altered += pkgs.len();
-        }
-    }
-    entry.altered = altered;
     entry.affected = package_map.clone();
+    hooks::tag(entry, &config.hooks.tag_rules);
 }
 
 fn add_parsed_package(
     packages: &HashMap<String, HashSet<String>>,
     package: String,
-) -> HashMap<String, HashSet<String>> {
-    let fields: Vec<&str> = package.split(":").collect();
-    let name = fields.get(0).expect("Unable to parse package name");
-    let arch = fields.get(1).expect("Unable to parse package architecture");
+) -> std::result::Result<HashMap<String, HashSet<String>>, String> {
+    let Some((name, arch)) = package.split_once(':') else {
+        return Err(format!("package `{package}` has no architecture"));
+    };
 
     let mut packages = packages.clone();
-    if packages.contains_key(&arch.to_string()) {
-        packages
-            .get_mut(&arch.to_string())
-            .expect("Unable to update package map")
-            .insert(name.to_string());
-    } else {
-        let mut package_set = HashSet::new();
-        package_set.insert(name.to_string());
-        packages.insert(arch.to_string(), package_set);
-    }
-    return packages;
+    packages
+        .entry(arch.to_string())
+        .or_default()
+        .insert(name.to_string());
+    Ok(packages)
 }
 
-fn packages_from_action_line(line: String) -> HashMap<String, HashSet<String>> {
+// Packages of an action line by architecture and their versions, or a description of what is
+// wrong with the line.
+type ActionPackages = (HashMap<String, HashSet<String>>, HashMap<String, String>);
+
+fn packages_from_action_line(line: String) -> std::result::Result<ActionPackages, String> {
     let mut packages: HashMap<String, HashSet<String>> = HashMap::new();
+    let mut versions: HashMap<String, String> = HashMap::new();
     let mut package = String::new();
+    let mut version = String::new();
     let mut inside_parens = false;
 
     for c in line.chars() {
         match c {
-            ' ' => (),
             '(' => inside_parens = true,
             ')' => inside_parens = false,
+            _ if inside_parens => version.push(c),
+            ' ' => (),
             ',' => {
-                if !inside_parens {
-                    packages = add_parsed_package(&packages, package);
-                    package = String::new();
-                }
-            }
-            _ => {
-                if !inside_parens {
-                    package.push(c)
-                }
+                versions.insert(package.clone(), version);
+                packages = add_parsed_package(&packages, package)?;
+                package = String::new();
+                version = String::new();
             }
+            _ => package.push(c),
         }
     }
 
     // Line does not end with a comma.
-    packages = add_parsed_package(&packages, package);
-    return packages;
+    versions.insert(package.clone(), version);
+    packages = add_parsed_package(&packages, package)?;
+    Ok((packages, versions))
 }
 
-fn entries_from_file(filename: &str, index_start: u32) -> Vec<HistoryEntry> {
-    let log = File::open(filename).unwrap();
-    let reader: Box<dyn BufRead> = if filename.ends_with(".gz") {
+pub(crate) fn open_log(filename: &str) -> io::Result<Box<dyn BufRead>> {
+    let log = File::open(filename)?;
+    if filename.ends_with(".gz") {
         let gz = GzDecoder::new(log);
-        Box::new(io::BufReader::new(gz))
+        Ok(Box::new(io::BufReader::new(gz)))
     } else {
-        Box::new(io::BufReader::new(log))
+        Ok(Box::new(io::BufReader::new(log)))
+    }
+}
+
+fn entries_from_file(
+    filename: &str,
+    index_start: u32,
+    config: &Config,
+    sink: &mut dyn FnMut(HistoryEntry),
+) -> Result<u32> {
+    let read_error = |source| Error::LogFile {
+        path: filename.to_string(),
+        source,
     };
+    let mut reader = open_log(filename).map_err(read_error)?;
 
-    let mut entries = vec![];
     let mut entry = HistoryEntry::new();
     let mut index = index_start;
     let mut seen_entry = false;
     let mut package_map: HashMap<String, HashMap<String, HashSet<String>>> = HashMap::new();
+    let mut buffer = String::new();
+    // Offsets are counted in the decompressed content for compressed logs.
+    let mut offset: u64 = 0;
+    let mut entry_start: Option<u64> = None;
+    let mut line_number = 0;
 
-    for line in reader.lines() {
-        let line = line.unwrap();
+    loop {
+        buffer.clear();
+        let read = reader.read_line(&mut buffer).map_err(read_error)? as u64;
+        if read == 0 {
+            break;
+        }
+        line_number += 1;
+        let parse_error = |message: String| Error::Parse {
+            path: filename.to_string(),
+            line: line_number,
+            message,
+        };
+        let line_start = offset;
+        offset += read;
+        let line = buffer.trim_end_matches('\n');
 
         if line.is_empty() {
             if !seen_entry {
@@ -163,181 +640,585 @@ fn entries_from_file(filename: &str, index_start: u32) -> Vec<HistoryEntry> {
                 continue;
             }
 
-            finalize_entry(&mut entry, index, &package_map);
+            entry.provenance = Provenance {
+                end: line_start,
+                file: filename.to_string(),
+                start: entry_start.take().unwrap_or(line_start),
+            };
+            finalize_entry(&mut entry, index, &package_map, config);
             package_map.clear();
-            entries.push(entry);
+            sink(entry);
             index += 1;
             entry = HistoryEntry::new();
             continue;
         }
+        entry_start.get_or_insert(line_start);
 
-        let mut fields = line.split(": ");
-        let descriptor = fields.nth(0).unwrap();
-        let value = fields
-            .last()
-            .expect(format!("error processing line `{}`", line).as_str());
+        // Values such as error messages can contain the separator themselves.
+        let (descriptor, value) = line
+            .split_once(": ")
+            .ok_or_else(|| parse_error(format!("unexpected line `{line}`")))?;
 
         match descriptor {
             "Commandline" => entry.command_line = value.to_string(),
             "End-Date" => {
                 entry.end_date = NaiveDateTime::parse_from_str(value, LOG_FILE_DATE_FORMAT)
-                    .expect("error parsing end date")
+                    .map_err(|e| parse_error(format!("invalid end date `{value}`: {e}")))?;
+                entry.finished = true;
             }
             "Start-Date" => {
                 entry.start_date = NaiveDateTime::parse_from_str(value, LOG_FILE_DATE_FORMAT)
-                    .expect("error parsing start date");
+                    .map_err(|e| parse_error(format!("invalid start date `{value}`: {e}")))?;
             }
-            "Install" | "Purge" | "Reinstall" | "Remove" | "Upgrade" => {
-                package_map.insert(
-                    descriptor.to_string(),
-                    packages_from_action_line(value.to_string()),
-                );
+            action if ACTIONS.contains(&action) => {
+                let (packages, versions) =
+                    packages_from_action_line(value.to_string()).map_err(parse_error)?;
+                package_map.insert(descriptor.to_string(), packages);
+                entry.versions.insert(descriptor.to_string(), versions);
             }
-            "Error" | "Requested-By" => {}
-            _ => panic!("unknown field {}", descriptor),
+            "Error" => match &mut entry.error {
+                Some(error) => {
+                    error.push('\n');
+                    error.push_str(value);
+                }
+                None => entry.error = Some(value.to_string()),
+            },
+            "Requested-By" => entry.requested_by = Some(value.to_string()),
+            _ => return Err(parse_error(format!("unknown field `{descriptor}`"))),
         }
     }
 
     // Check if this was an empty log file
     if !entry.command_line.is_empty() {
         // Last line is not empty.
-        finalize_entry(&mut entry, index, &package_map);
-        entries.push(entry);
+        entry.provenance = Provenance {
+            end: offset,
+            file: filename.to_string(),
+            start: entry_start.unwrap_or(offset),
+        };
+        finalize_entry(&mut entry, index, &package_map, config);
+        sink(entry);
+        index += 1;
     }
-    entries
+    Ok(index - index_start)
 }
 
-fn path_buf_name(p: &PathBuf) -> &str {
+// The history log apt writes to `/var/log/apt/history.log`, along with its rotated logs.
+pub(crate) struct AptHistoryLog;
+
+impl HistorySource for AptHistoryLog {
+    fn files(&self) -> Result<Vec<PathBuf>> {
+        history_files()
+    }
+
+    fn parse(
+        &self,
+        filename: &str,
+        index_start: u32,
+        config: &Config,
+        sink: &mut dyn FnMut(HistoryEntry),
+    ) -> Result<u32> {
+        entries_from_file(filename, index_start, config, sink)
+    }
+}
+
+fn path_buf_name(p: &Path) -> &str {
     p.file_name().expect("error getting file name").to_str() .expect("error converting file name")
 }
 
-fn log_file_num(f: &str) -> u32 {
-    let fields: Vec<&str> = f.split(".").collect();
-    let num_field = fields.get(2).expect("Unable to find number field in log file name");
-    let number: u32 = num_field.parse().expect("Unable to parse log file number");
-    number
+// Rotation number of a log file, the current log file has none.
+fn log_file_num(log_file_regex: &Regex, f: &str) -> Option<u32> {
+    let captures = log_file_regex.captures(f)?;
+    captures.get(1)?.as_str().parse().ok()
 }
 
-fn sort_log_files(a: &PathBuf, b: &PathBuf) -> Ordering {
-    let a_name = path_buf_name(a);
-    let b_name = path_buf_name(b);
+// Whether a file matching a log file pattern is a log, rotated logs need a rotation number
+// which fits the type of IDs.
+fn is_log_file(log_file_regex: &Regex, f: &str) -> bool {
+    log_file_regex
+        .captures(f)
+        .is_some_and(|c| c.get(1).is_none() || log_file_num(log_file_regex, f).is_some())
+}
 
-    if a_name == CURRENT_HISTORY_FILE {
-        return Ordering::Greater
-    }
-    if b_name == CURRENT_HISTORY_FILE {
-        return Ordering::Less
+// Logs are ordered from the oldest to the current one, older rotated logs have larger numbers.
+fn sort_log_files(log_file_regex: &Regex, a: &Path, b: &Path) -> Ordering {
+    let a_num = log_file_num(log_file_regex, path_buf_name(a));
+    let b_num = log_file_num(log_file_regex, path_buf_name(b));
+    match (a_num, b_num) {
+        (Some(a_num), Some(b_num)) => b_num.cmp(&a_num).then(a.cmp(b)),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
     }
+}
 
-    let a_num = log_file_num(a_name);
-    let b_num = log_file_num(b_name);
-    // Older log files have smaller number suffixes.
-    a_num.cmp(&b_num).reverse()
+// History log files from the oldest to the current one.
+pub(crate) fn history_files() -> Result<Vec<PathBuf>> {
+    log_files(APT_HISTORY_LOG_PATTERN)
 }
 
-fn history_entries() -> Vec<HistoryEntry> {
-    let log_file_regex = Regex::new(APT_HISTORY_LOG_PATTERN).expect("error parsing file regex");
-    let mut history_files: Vec<PathBuf> = vec![];
+// Log files in the apt log directory matching a pattern which captures the rotation number, from
+// the oldest to the current one.
+pub(crate) fn log_files(pattern: &str) -> Result<Vec<PathBuf>> {
+    let log_file_regex = Regex::new(pattern).expect("error parsing file regex");
+    let mut log_files: Vec<PathBuf> = vec![];
 
-    for entry in fs::read_dir(APT_LOG_PATH).expect("error reading apt log path") {
-        let entry = entry.expect("error reading dir entry");
+    let log_dir = bundle::path(APT_LOG_PATH);
+    let dir_error = |source| Error::LogDir {
+        path: log_dir.display().to_string(),
+        source,
+    };
+    for entry in fs::read_dir(&log_dir).map_err(dir_error)? {
+        let entry = entry.map_err(dir_error)?;
         let filename = entry.file_name();
-        let filename = filename.to_str().expect("error reading file name");
-        if log_file_regex.is_match(filename) {
-            history_files.push(entry.path());
+        let Some(filename) = filename.to_str() else {
+            continue;
+        };
+        if is_log_file(&log_file_regex, filename) {
+            log_files.push(entry.path());
         }
     }
-    history_files.sort_by(sort_log_files);
+    log_files.sort_by(|a, b| sort_log_files(&log_file_regex, a, b));
+    Ok(log_files)
+}
 
-    let mut combined: Vec<HistoryEntry> = vec![];
+// Rotation numbers missing from the sequence of rotated logs, which is counted from one up to
+// the oldest log, for example because old logs were deleted by hand.
+pub(crate) fn rotation_gaps() -> Result<Vec<u32>> {
+    let log_file_regex = Regex::new(APT_HISTORY_LOG_PATTERN).expect("error parsing file regex");
+    let numbers: HashSet<u32> = history_files()?
+        .iter()
+        .filter_map(|f| log_file_num(&log_file_regex, path_buf_name(f)))
+        .collect();
+    let oldest = numbers.iter().max().copied().unwrap_or_default();
+    Ok((1..oldest).filter(|n| !numbers.contains(n)).collect())
+}
+
+fn hostname() -> String {
+    fs::read_to_string(bundle::path(HOSTNAME_PATH))
+        .map(|h| h.trim().to_string())
+        .unwrap_or_default()
+}
+
+// Calls `visit` with entries as they are parsed, from the oldest log file to the current one of
+// each registered source. IDs follow the parse order, which only differs from the final order for
+// entries with start dates out of order.
+fn visit_entries(visit: &mut dyn FnMut(HistoryEntry)) -> Result<()> {
+    let config = Config::load()?;
+    let host = hostname();
+    let usernames = users::usernames();
     let mut id: u32 = 1;
-    for file in history_files {
-        let entries = entries_from_file(file.to_str().expect("error getting file path"), id);
-        if entries.len() == 0 {
-            continue;
+    let mut priority = 0;
+    for source in sources::registered() {
+        for file in source.files()? {
+            let filename = file.to_str().expect("error getting file path");
+            // Rotated logs can be restricted to the `adm` group.
+            if let Err(e) = File::open(&file) {
+                if privileges::is_unavailable(filename, &e) {
+                    continue;
+                }
+            }
+            let mut parsed = 0;
+            let result = source.parse(filename, id, &config, &mut |mut entry| {
+                entry.source_priority = priority;
+                // Imported entries keep the host they were recorded on and its user names.
+                if entry.host.is_empty() {
+                    entry.host = host.clone();
+                    entry.requested_by = entry
+                        .requested_by
+                        .map(|r| users::resolve(&r, &usernames));
+                }
+                parsed += 1;
+                visit(entry);
+            });
+            // IDs go on after the entries of a broken file read before the error.
+            match result {
+                Ok(count) => id += count,
+                Err(e) => {
+                    incomplete::tolerate(filename, parsed, e)?;
+                    id += parsed;
+                }
+            }
+            priority += 1;
+        }
+    }
+    Ok(())
+}
+
+fn parse_entries() -> Result<Vec<HistoryEntry>> {
+    let mut combined: Vec<HistoryEntry> = vec![];
+    visit_entries(&mut |entry| combined.push(entry))?;
+    if imports::enabled() {
+        // Histories of this host imported from its own archives are only shown once.
+        let mut seen = HashSet::new();
+        combined.sort_by_key(|e| e.source_priority);
+        combined.retain(|e| seen.insert((e.host.clone(), e.fingerprint())));
+    }
+    order_entries(&mut combined);
+    uuids::assign(&mut combined)?;
+    hooks::run(&mut combined, &Config::load()?)?;
+    Ok(combined)
+}
+
+// A transaction apt is still running, if any.
+enum CurrentTransaction {
+    // The transaction has been partially written to the history log.
+    Logged(Box<HistoryEntry>),
+    // The front-end lock is held but nothing has been logged yet, e.g. while downloading.
+    Locked(u32),
+}
+
+// Splits off the last entry if it has not finished while the front-end lock is held, entries of
+// interrupted transactions are never finished either.
+fn split_current(
+    mut entries: Vec<HistoryEntry>,
+) -> (Vec<HistoryEntry>, Option<CurrentTransaction>) {
+    let Some(holder) = lock::frontend_lock_holder() else {
+        return (entries, None);
+    };
+    if entries.last().is_some_and(|e| !e.finished) {
+        let current = entries.pop().expect("error getting last entry");
+        return (entries, Some(CurrentTransaction::Logged(Box::new(current))));
+    }
+    (entries, Some(CurrentTransaction::Locked(holder)))
+}
+
+pub fn history_entries() -> Result<Vec<HistoryEntry>> {
+    Ok(split_current(parse_entries()?).0)
+}
+
+// Entries as `history_entries` finds them, along with the transaction apt is running if it has
+// been partially logged.
+pub(crate) fn entries_and_running() -> Result<(Vec<HistoryEntry>, Option<HistoryEntry>)> {
+    let (entries, current) = split_current(parse_entries()?);
+    let running = match current {
+        Some(CurrentTransaction::Logged(entry)) => Some(*entry),
+        _ => None,
+    };
+    Ok((entries, running))
+}
+
+fn show_current(current: &CurrentTransaction) {
+    match current {
+        CurrentTransaction::Logged(entry) => {
+            let elapsed = (Local::now().naive_local() - entry.start_date).num_seconds();
+            let elapsed = match elapsed {
+                s if s < 60 => format!("{s}s"),
+                s if s < 3600 => format!("{}m", s / 60),
+                s => format!("{}h", s / 3600),
+            };
+            println!(
+                "In progress (started {elapsed} ago, {} {})",
+                entry.frontend, entry.command_line
+            );
+        }
+        CurrentTransaction::Locked(pid) => {
+            println!("In progress (apt lock held by process {pid})")
         }
-        let num_entries = entries.len() as u32;
-        combined.extend(entries);
-        id += num_entries;
     }
+}
 
-    combined
+// Entries sharing a start date are ordered by source priority and then by command line, so that
+// IDs and output order do not depend on the order sources happened to be read in.
+pub(crate) fn order_entries(entries: &mut [HistoryEntry]) {
+    entries.sort_by(|a, b| {
+        a.start_date
+            .cmp(&b.start_date)
+            .then(a.source_priority.cmp(&b.source_priority))
+            .then_with(|| a.command_line.cmp(&b.command_line))
+    });
+    // IDs are counted per host, so that imported histories do not change the IDs of this host.
+    let mut ids: HashMap<String, u32> = HashMap::new();
+    for entry in entries.iter_mut() {
+        let id = ids.entry(entry.host.clone()).or_default();
+        *id += 1;
+        entry.id = *id;
+    }
+    mark_overlaps(entries);
+}
+
+// Entries are ordered by start date, so an entry overlaps the earlier ones that end after it
+// starts. Unfinished entries have no logged end, so later entries are not taken to overlap them.
+fn mark_overlaps(entries: &mut [HistoryEntry]) {
+    let mut running: Vec<usize> = vec![];
+    for index in 0..entries.len() {
+        let start = entries[index].start_date;
+        running.retain(|r| entries[*r].end_date > start);
+        for &other in &running {
+            if entries[other].host != entries[index].host {
+                continue;
+            }
+            let (id, other_id) = (entries[index].id, entries[other].id);
+            entries[index].overlapping.push(other_id);
+            entries[other].overlapping.push(id);
+        }
+        if entries[index].finished {
+            running.push(index);
+        }
+    }
+}
+
+// Pairs of IDs of overlapping entries, each pair is listed once with the smaller ID first.
+pub(crate) fn overlapping_pairs(entries: &[HistoryEntry]) -> Vec<(u32, u32)> {
+    entries
+        .iter()
+        .flat_map(|e| e.overlapping.iter().filter(|o| **o > e.id).map(|o| (e.id, *o)))
+        .collect()
 }
 
-fn show_transaction(entry: &HistoryEntry) {
-    let duration = entry.end_date - entry.start_date;
-    let end_time = format!(
-        "{} ({} seconds)",
-        entry.end_date.format(INFO_DATE_FORMAT),
-        duration.num_seconds()
+fn action_rank(action: &str) -> usize {
+    ACTIONS
+        .iter()
+        .position(|a| *a == action)
+        .unwrap_or(ACTIONS.len())
+}
+
+// Actions and `name:arch` packages of an entry, sorted by action and then by package.
+fn altered_packages(entry: &HistoryEntry) -> Vec<(&String, String)> {
+    let mut altered = vec![];
+    for action in entry.sorted_actions() {
+        let pkg_map: &HashMap<String, HashSet<String>> = entry
+            .affected
+            .get(action.as_str())
+            .expect("unexpected entry miss in map");
+
+        let mut pkgs: Vec<String> = Vec::new();
+        for (arch, pkg_list) in pkg_map.iter() {
+            for pkg in pkg_list {
+                pkgs.push(format!("{pkg}:{arch}"))
+            }
+        }
+        pkgs.sort();
+
+        altered.extend(pkgs.into_iter().map(|pkg| (action, pkg)));
+    }
+    altered
+}
+
+// Transaction details on one line followed by its packages, for narrow terminals.
+fn show_transaction_compact(entry: &HistoryEntry, options: &DisplayOptions) {
+    let duration = entry.duration().map_or(tr("not finished").to_string(), |d| {
+        format!("{}s", d.num_seconds())
+    });
+    let mut line = format!(
+        "{} {} ({duration}) {} {}",
+        entry.id,
+        entry.start_date.format(options.list_date_format()),
+        entry.tool_label(),
+        entry.command_line
     );
+    if !entry.severities().is_empty() {
+        line.push_str(&format!(" [{}]", entry.severity_labels()));
+    }
+    println!("{line}");
+    if let Some(error) = &entry.error {
+        println!("  {} {error}", color::bold("Error"));
+    }
+    let lines: Vec<String> = altered_packages(entry)
+        .into_iter()
+        .map(|(action, pkg)| format!("  {} {pkg}", color::bold(&action_abbreviation(action))))
+        .collect();
+    for line in elide(lines, options.max_lines, "  ", "packages") {
+        println!("{line}");
+    }
+}
+
+// Keeps the first and last lines of output longer than the maximum number of lines, with a line
+// telling how many of the given things were left out in between.
+fn elide(lines: Vec<String>, max_lines: Option<usize>, indent: &str, noun: &str) -> Vec<String> {
+    let Some(max_lines) = max_lines.filter(|max| lines.len() > *max) else {
+        return lines;
+    };
+    // The marker takes one of the lines.
+    let kept = max_lines.saturating_sub(1);
+    let head = kept.div_ceil(2);
+    let tail = kept - head;
+    let elided = lines.len() - kept;
+    let mut shown = lines[..head].to_vec();
+    shown.push(format!("{indent}… {elided} more {noun} (use --no-limit)"));
+    shown.extend_from_slice(&lines[lines.len() - tail..]);
+    shown
+}
+
+// Packages altered by any of the entries as `name:arch`.
+fn distinct_packages(entries: &[HistoryEntry]) -> Vec<String> {
+    let packages: BTreeSet<String> = entries
+        .iter()
+        .flat_map(|e| altered_packages(e).into_iter().map(|(_, pkg)| pkg))
+        .collect();
+    packages.into_iter().collect()
+}
+
+fn show_transaction(
+    entry: &HistoryEntry,
+    options: &DisplayOptions,
+    layout: &InfoLayout,
+    renames: &[Rename],
+    download: Option<&Download>,
+    classes: &HashMap<String, PackageClass>,
+    note: Option<&Note>,
+) {
+    let end_time = match entry.duration() {
+        Some(duration) => format!(
+            "{} ({} {})",
+            entry.end_date.format(options.info_date_format()),
+            duration.num_seconds(),
+            tr("seconds")
+        ),
+        None => tr("not finished").to_string(),
+    };
 
     let mut header_table = tabular::Table::new("{:<} : {:<}");
     header_table.add_row(
         tabular::Row::new()
-            .with_cell("Transaction ID")
+            .with_cell(tr("Transaction ID"))
             .with_cell(entry.id),
     );
+    if let Some(uuid) = &entry.uuid {
+        header_table.add_row(tabular::Row::new().with_cell("UUID").with_cell(uuid));
+    }
     header_table.add_row(
         tabular::Row::new()
-            .with_cell("Begin time")
-            .with_cell(&entry.start_date.format(INFO_DATE_FORMAT)),
+            .with_cell(tr("Begin time"))
+            .with_cell(entry.start_date.format(options.info_date_format())),
     );
     header_table.add_row(
         tabular::Row::new()
-            .with_cell("End time")
+            .with_cell(tr("End time"))
             .with_cell(end_time),
     );
+    if imports::enabled() {
+        header_table.add_row(
+            tabular::Row::new()
+                .with_cell(tr("Host"))
+                .with_cell(&entry.host),
+        );
+    }
+    header_table.add_row(
+        tabular::Row::new()
+            .with_cell(tr("Tool"))
+            .with_cell(&entry.frontend),
+    );
+    if let Some(user) = &entry.requested_by {
+        header_table.add_row(tabular::Row::new().with_cell(tr("User")).with_cell(user));
+    }
+    if let Some(root) = &entry.foreign_root {
+        header_table.add_row(tabular::Row::new().with_cell(tr("Root")).with_cell(root));
+    }
+    if !entry.overlapping.is_empty() {
+        let ids: Vec<String> = entry.overlapping.iter().map(|id| id.to_string()).collect();
+        header_table.add_row(
+            tabular::Row::new()
+                .with_cell(tr("Overlaps with"))
+                .with_cell(ids.join(", ")),
+        );
+    }
     header_table.add_row(
         tabular::Row::new()
-            .with_cell("Command Line")
+            .with_cell(tr("Command Line"))
             .with_cell(&entry.command_line),
     );
-    header_table.add_row(tabular::Row::new().with_cell("Comment").with_cell(""));
+    if !entry.severities().is_empty() {
+        header_table.add_row(
+            tabular::Row::new()
+                .with_cell(tr("Marks"))
+                .with_cell(entry.severity_labels()),
+        );
+    }
+    if let Some(error) = &entry.error {
+        for (index, line) in error.lines().enumerate() {
+            let label = if index == 0 { tr("Error") } else { "" };
+            header_table.add_row(tabular::Row::new().with_cell(label).with_cell(line));
+        }
+    }
+    if let Some(download) = download {
+        header_table.add_row(
+            tabular::Row::new()
+                .with_cell(tr("Downloaded"))
+                .with_cell(download.describe()),
+        );
+    }
+    // Tags of hooks and of the note, as both are shown the same.
+    let mut tags = entry.tags.clone();
+    for tag in note.map(|n| n.tags.as_slice()).unwrap_or_default() {
+        if !tags.contains(tag) {
+            tags.push(tag.clone());
+        }
+    }
+    if !tags.is_empty() {
+        header_table.add_row(
+            tabular::Row::new()
+                .with_cell(tr("Tags"))
+                .with_cell(tags.join(", ")),
+        );
+    }
+    let comment = note.and_then(|n| n.comment.as_deref()).unwrap_or_default();
+    header_table.add_row(
+        tabular::Row::new()
+            .with_cell(tr("Comment"))
+            .with_cell(comment),
+    );
+    if options.verbose {
+        header_table.add_row(
+            tabular::Row::new()
+                .with_cell(tr("Source"))
+                .with_cell(entry.provenance.describe()),
+        );
+    }
 
     print!("{header_table}");
-    println!("Packages Altered:");
+    println!("{}", layout.header(tr("Packages Altered:")));
 
     let mut pkgs_table = tabular::Table::new("    {:>} {:<}");
-    let mut actions: Vec<&String> = entry.affected.keys().clone().collect();
-    actions.sort();
-
-    let style = ansi_term::Style::new().bold();
-    for action in actions {
-        let pkg_map: &HashMap<String, HashSet<String>> = entry
-            .affected
-            .get(action.as_str())
-            .expect("unexpected entry miss in map");
-
-        let mut pkgs: Vec<String> = Vec::new();
-        for (arch, pkg_list) in pkg_map.iter() {
-            for pkg in pkg_list {
-                pkgs.push(format!("{pkg}:{arch}"))
-            }
+    // Packages making up a rename are shown together instead of as separate actions.
+    for (action, pkg) in altered_packages(entry) {
+        if action == rename::INSTALL_ACTION && renames.iter().any(|r| r.to == pkg) {
+            continue;
         }
-        pkgs.sort();
-
-        for pkg in pkgs {
+        if let Some(rename) = renames.iter().find(|r| r.action == *action && r.from == pkg) {
             pkgs_table.add_row(
                 tabular::Row::new()
-                    .with_cell(style.paint(action))
-                    .with_cell(pkg),
+                    .with_cell(color::bold("Rename"))
+                    .with_cell(format!("{} -> {}", rename.from, rename.to)),
             );
+            continue;
         }
+        let versions = entry.package_versions(action, &pkg);
+        let mut package = if versions.is_empty() {
+            pkg.clone()
+        } else {
+            format!("{pkg} {}", versions.join(" -> "))
+        };
+        if let Some(class) = classes.get(&pkg) {
+            package.push_str(&format!(" ({})", class.describe()));
+        }
+        pkgs_table.add_row(
+            tabular::Row::new()
+                .with_cell(color::bold(action))
+                .with_cell(package),
+        );
     }
 
-    print!("{pkgs_table}");
+    let lines: Vec<String> = pkgs_table.to_string().lines().map(str::to_string).collect();
+    for line in elide(lines, options.max_lines, "    ", "packages") {
+        println!("{line}");
+    }
 }
 
-fn matches(entry: &HistoryEntry, ids: &HashSet<u32>, packages: &HashSet<String>) -> bool {
-    if ids.contains(&entry.id) {
+fn matches(
+    entry: &HistoryEntry,
+    ids: &HashSet<u32>,
+    qualified_ids: &HashSet<(String, u32)>,
+    packages: &HashSet<String>,
+) -> bool {
+    if ids.contains(&entry.id) || qualified_ids.contains(&(entry.host.clone(), entry.id)) {
         return true;
     }
 
     for affected in entry.affected.values() {
         for pkgs in affected.values() {
             let union: HashSet<&String> = packages.intersection(pkgs).collect();
-            if union.len() > 0 {
+            if !union.is_empty() {
                 return true;
             }
         }
@@ -346,22 +1227,287 @@ fn matches(entry: &HistoryEntry, ids: &HashSet<u32>, packages: &HashSet<String>)
     false
 }
 
-fn matching_entries(query: Option<Vec<String>>) -> Vec<HistoryEntry> {
-    let entries = history_entries();
-    let max_id = entries.len() as u32;
-    let fallback_transaction: String = max_id.to_string();
+// Formats entries can be rendered in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Table,
+    Csv,
+    Json,
+    // One JSON object per line, `list` prints entries as they are parsed in log order.
+    Jsonl,
+    Markdown,
+    // Browse entries full screen as `tui` does.
+    Tui,
+    Yaml,
+}
+
+// Columns of the list table.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum Column {
+    Id,
+    Tool,
+    CommandLine,
+    Date,
+    Actions,
+    Altered,
+    Duration,
+    Source,
+    User,
+    Uuid,
+    Host,
+    Comment,
+}
+
+const DEFAULT_COLUMNS: [Column; 6] = [
+    Column::Id,
+    Column::Tool,
+    Column::CommandLine,
+    Column::Date,
+    Column::Actions,
+    Column::Altered,
+];
+
+impl Column {
+    fn header(&self) -> &'static str {
+        match self {
+            Column::Id => "ID",
+            Column::Tool => "Tool",
+            Column::CommandLine => "Command line",
+            Column::Date => "Date and time",
+            Column::Actions => "Action(s)",
+            Column::Altered => "Altered",
+            Column::Duration => "Duration",
+            Column::Source => "Source",
+            Column::User => "User",
+            Column::Uuid => "UUID",
+            Column::Host => "Host",
+            Column::Comment => "Comment",
+        }
+    }
+
+    fn value(&self, entry: &HistoryEntry, options: &DisplayOptions) -> String {
+        match self {
+            Column::Id => entry.id.to_string(),
+            Column::Tool => entry.tool_label(),
+            Column::CommandLine => entry.command_line.clone(),
+            Column::Date if options.relative_dates => {
+                date::relative_date(entry.start_date, Local::now().naive_local())
+            }
+            Column::Date => entry
+                .start_date
+                .format(options.list_date_format())
+                .to_string(),
+            Column::Actions => list_actions(entry),
+            Column::Altered => entry.altered(options.counting).to_string(),
+            // Left empty for transactions without a logged end.
+            Column::Duration => entry
+                .duration()
+                .map(date::format_duration)
+                .unwrap_or_default(),
+            Column::Source => entry.provenance.describe(),
+            Column::User => entry.requested_by.clone().unwrap_or_default(),
+            Column::Uuid => entry.uuid.clone().unwrap_or_default(),
+            Column::Host => entry.host.clone(),
+            Column::Comment => entry.comment.clone().unwrap_or_default(),
+        }
+    }
+
+    // Header in the language of the locale, for output read by people.
+    fn label(&self) -> &'static str {
+        tr(self.header())
+    }
+
+    fn is_numeric(&self) -> bool {
+        matches!(self, Column::Id | Column::Altered | Column::Duration)
+    }
+
+    fn cell(&self, entry: &HistoryEntry, options: &DisplayOptions) -> Cell<'_> {
+        match self {
+            Column::Id => Cell::Int(entry.id as i32),
+            // Text cells are aligned left, these are padded to line up on the right like counts.
+            Column::Duration => Cell::from(&format!(
+                "{:>width$}",
+                self.value(entry, options),
+                width = self.label().chars().count()
+            )),
+            // Counts are text as well to make room for the marker of the most important reason
+            // the transaction stands out for. Markers are not colored as escape sequences would
+            // throw off the alignment of the table.
+            Column::Altered => {
+                let marker = entry.severities().first().map_or(" ", |s| s.marker());
+                Cell::from(&format!(
+                    "{:>width$}",
+                    format!("{} {marker}", entry.altered(options.counting)),
+                    width = self.label().chars().count()
+                ))
+            }
+            _ => Cell::from(&self.value(entry, options)),
+        }
+    }
+}
+
+// How the altered count of a transaction is determined. A package replaced in a transaction can
+// appear under several actions, e.g. both removed and installed, which is a single package but
+// two operations.
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum AlteredCount {
+    #[default]
+    Packages,
+    Operations,
+}
+
+// How selected entries are displayed.
+#[derive(Default)]
+pub struct DisplayOptions {
+    pub full_command_line: bool,
+    // Columns shown by `list`, the default columns are shown if empty.
+    pub columns: Vec<Column>,
+    pub compact: bool,
+    pub counting: AlteredCount,
+    // Format of dates in place of the default ones of `list` and `info`, a strftime string.
+    pub date_format: Option<String>,
+    pub format: Option<String>,
+    pub ids_only: bool,
+    // Lines of package tables in `info` and of the table of `list` after which the middle of
+    // them is left out, none to show all.
+    pub max_lines: Option<usize>,
+    pub output: OutputFormat,
+    // Terminate fields with NUL bytes instead of formatting them, for scripts.
+    pub print0: bool,
+    pub raw: bool,
+    // Show how long ago transactions were started instead of their dates in `list`.
+    pub relative_dates: bool,
+    pub reverse: bool,
+    // Show the section and priority of packages in `info`.
+    pub sections: bool,
+    pub verbose: bool,
+}
+
+impl DisplayOptions {
+    pub(crate) fn list_date_format(&self) -> &str {
+        self.date_format.as_deref().unwrap_or(LIST_DATE_FORMAT)
+    }
+
+    pub(crate) fn info_date_format(&self) -> &str {
+        self.date_format.as_deref().unwrap_or(INFO_DATE_FORMAT)
+    }
+}
+
+// Checks actions to filter by, which are matched regardless of case.
+pub(crate) fn check_actions(actions: &[String]) -> std::result::Result<(), String> {
+    match actions
+        .iter()
+        .find(|a| !ACTIONS.iter().any(|known| known.eq_ignore_ascii_case(a)))
+    {
+        Some(unknown) => Err(format!(
+            "unknown action `{unknown}`, use one of: {}",
+            ACTIONS.join(", ").to_lowercase()
+        )),
+        None => Ok(()),
+    }
+}
+
+// Criteria entries have to satisfy in addition to matching the query.
+#[derive(Default)]
+pub struct Filters {
+    // Entries containing any of the given actions, all entries if there are none.
+    pub action: Vec<String>,
+    // Entries changing a package of the given priority and section, as filed in the apt cache.
+    pub priority: Option<String>,
+    pub section: Option<String>,
+    // Start dates of entries are at or after `since` and before `until`.
+    pub since: Option<NaiveDateTime>,
+    pub tool: Option<String>,
+    pub until: Option<NaiveDateTime>,
+    // Name or UID of the user who requested the transaction.
+    pub user: Option<String>,
+}
+
+impl Filters {
+    // Whether the packages of entries have to be looked up in the apt cache to filter them.
+    fn needs_classes(&self) -> bool {
+        self.priority.is_some() || self.section.is_some()
+    }
+
+    fn class_matches(&self, entry: &HistoryEntry, classes: &HashMap<String, PackageClass>) -> bool {
+        if !self.needs_classes() {
+            return true;
+        }
+        altered_packages(entry).iter().any(|(_, pkg)| {
+            classes.get(pkg).is_some_and(|class| {
+                self.priority.as_ref().is_none_or(|p| class.has_priority(p))
+                    && self.section.as_ref().is_none_or(|s| class.in_section(s))
+            })
+        })
+    }
+
+    fn matches(&self, entry: &HistoryEntry, classes: &HashMap<String, PackageClass>) -> bool {
+        let action_matches = self.action.is_empty()
+            || entry
+                .affected
+                .keys()
+                .any(|a| self.action.iter().any(|action| a.eq_ignore_ascii_case(action)));
+        action_matches
+            && self.since.is_none_or(|d| entry.start_date >= d)
+            && self.until.is_none_or(|d| entry.start_date < d)
+            && self.tool.as_ref().is_none_or(|t| *t == entry.frontend)
+            && self
+                .user
+                .as_ref()
+                .is_none_or(|u| users::requested_by_matches(entry.requested_by.as_deref(), u))
+            && self.class_matches(entry, classes)
+    }
+
+    pub(crate) fn apply(&self, entries: Vec<HistoryEntry>) -> Result<Vec<HistoryEntry>> {
+        let classes = if self.needs_classes() {
+            package_classes(&distinct_packages(&entries))?
+        } else {
+            HashMap::new()
+        };
+        Ok(entries
+            .into_iter()
+            .filter(|e| self.matches(e, &classes))
+            .collect())
+    }
+}
+
+// Entries matching any of the given IDs or package names, relative IDs are counted back from
+// the last of the given entries.
+pub(crate) fn select_entries(
+    entries: &[HistoryEntry],
+    query: Option<Vec<String>>,
+) -> Vec<HistoryEntry> {
+    let max_id = entries.last().map(|e| e.id).unwrap_or(0);
+    // The last entry of its host only, as other hosts can have entries with the same ID.
+    let fallback_transaction: String = entries
+        .last()
+        .map_or(max_id.to_string(), |e| format!("{}:{}", e.host, e.id));
 
     let transactions = query.clone()
         .or(Some(vec![fallback_transaction]))
         .expect("error getting ID of history entry");
 
     let mut ids: HashSet<u32> = HashSet::new();
+    let mut qualified_ids: HashSet<(String, u32)> = HashSet::new();
     let mut packages: HashSet<String> = HashSet::new();
     for transaction in transactions {
+        if let Some(entry) = entries.iter().find(|e| e.uuid.as_ref() == Some(&transaction)) {
+            qualified_ids.insert((entry.host.clone(), entry.id));
+            continue;
+        }
+        // IDs qualified by a host, e.g. `web01:42`.
+        if let Some((host, id)) = transaction
+            .split_once(':')
+            .and_then(|(host, id)| Some((host, id.parse::<u32>().ok()?)))
+        {
+            qualified_ids.insert((host.to_string(), id));
+            continue;
+        }
         match transaction.parse::<i32>() {
             Ok(mut tid) => {
                 if tid <= 0 {
-                    tid = (max_id as i32) + tid;
+                    tid += max_id as i32;
                 }
                 ids.insert(tid as u32)
             },
@@ -369,71 +1515,557 @@ fn matching_entries(query: Option<Vec<String>>) -> Vec<HistoryEntry> {
         };
     }
 
-    return entries
+    entries
         .iter()
-        .filter(|e| matches(e, &ids, &packages))
+        .filter(|e| matches(e, &ids, &qualified_ids, &packages))
         .cloned()
-        .collect();
+        .collect()
 }
 
-pub fn info(query: Option<Vec<String>>) {
-    let selected = matching_entries(query);
+pub(crate) fn matching_entries(query: Option<Vec<String>>) -> Result<Vec<HistoryEntry>> {
+    Ok(select_entries(&history_entries()?, query))
+}
+
+// Entries with command lines expanded to their full length if requested.
+fn displayed_entries(selected: &[HistoryEntry], options: &DisplayOptions) -> Vec<HistoryEntry> {
+    let mut entries = selected.to_vec();
+    if options.full_command_line {
+        entries
+            .iter_mut()
+            .for_each(|e| e.command_line = e.full_command_line.clone());
+    }
+    entries
+}
 
-    let separator = SEPARATOR_CHAR.to_string().repeat(SEPARATOR_LENGTH);
+pub(crate) fn show_transactions(
+    selected: &[HistoryEntry],
+    options: &DisplayOptions,
+    vulnerabilities: Option<&VulnerabilityData>,
+) -> Result<()> {
+    let selected = &displayed_entries(selected, options);
+    render::renderer(options).info(selected, options, vulnerabilities)
+}
+
+// Entries exactly as they were logged.
+pub(crate) fn show_raw(selected: &[HistoryEntry]) -> Result<()> {
     for (index, entry) in selected.iter().enumerate() {
         if index > 0 {
-            println!("{separator}")
+            println!();
         }
-        show_transaction(entry)
+        print!("{}", entry.provenance.raw_block()?);
     }
+    Ok(())
 }
 
-pub fn list(query: Option<Vec<String>>, reverse: bool) {
-    let mut selected = if query.is_some() {
-        matching_entries(query)
+// Details of each entry laid out as configured in the `[info]` section.
+pub(crate) fn show_transaction_details(
+    selected: &[HistoryEntry],
+    options: &DisplayOptions,
+    vulnerabilities: Option<&VulnerabilityData>,
+) -> Result<()> {
+    let layout = Config::load_checked().info_layout;
+    let (replacements, downloads) = if options.compact {
+        (HashMap::new(), HashMap::new())
+    } else {
+        (rename::replacements(), termlog::downloads()?)
+    };
+    let classes = if options.sections && !options.compact {
+        package_classes(&distinct_packages(selected))?
     } else {
-        history_entries()
+        HashMap::new()
     };
+    // Notes are only shown, so a broken notes file does not keep transactions from being shown.
+    let notes = Notes::load().unwrap_or_else(|e| {
+        eprintln!("Warning: {e}");
+        Notes::default()
+    });
+    let separator = layout.separator();
+    for (index, entry) in selected.iter().enumerate() {
+        if index > 0 {
+            separator.iter().for_each(|line| println!("{line}"));
+        }
+        if options.compact {
+            show_transaction_compact(entry, options);
+        } else {
+            let renames = rename::renames(entry, &replacements);
+            let download = downloads.get(&entry.start_date);
+            let note = notes.0.get(&entry.fingerprint());
+            show_transaction(entry, options, &layout, &renames, download, &classes, note);
+        }
+        if let Some(vulnerabilities) = vulnerabilities {
+            vulnerabilities.show(entry, &layout);
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn transactions_json(
+    selected: &[HistoryEntry],
+    options: &DisplayOptions,
+    vulnerabilities: Option<&VulnerabilityData>,
+) -> Vec<Value> {
+    selected
+        .iter()
+        .map(|entry| {
+            let mut value = entry.to_json(options.counting);
+            if let (Some(vulnerabilities), Value::Object(fields)) = (vulnerabilities, &mut value) {
+                fields.push(("vulnerabilities".to_string(), vulnerabilities.to_json(entry)));
+            }
+            value
+        })
+        .collect()
+}
+
+// Templates given with `--format`, which are checked up front as entries cannot be shown with
+// invalid ones.
+pub(crate) fn check_template(format: String) -> Result<String> {
+    Template::parse(&format, &TEMPLATE_VARIABLES).map_err(Error::Usage)?;
+    Ok(format)
+}
+
+// One line per entry rendered from a user supplied template.
+pub(crate) fn show_formatted(selected: &[HistoryEntry], format: &str, options: &DisplayOptions) {
+    let template =
+        Template::parse(format, &TEMPLATE_VARIABLES).expect("error parsing checked template");
+    for entry in selected {
+        println!(
+            "{}",
+            template.render(|v| entry.template_value(v, options.counting))
+        );
+    }
+}
+
+// One record per altered package, with transaction details repeated on each.
+pub(crate) fn show_transactions_csv(selected: &[HistoryEntry], options: &DisplayOptions) {
+    print!("{}", csv::record(&INFO_TABLE_HEADERS));
+    for entry in selected {
+        let id = entry.id.to_string();
+        let date = entry
+            .start_date
+            .format(options.list_date_format())
+            .to_string();
+        for (action, pkg) in altered_packages(entry) {
+            print!(
+                "{}",
+                csv::record(&[&id, &date, &entry.command_line, action, &pkg])
+            );
+        }
+    }
+}
 
+// Fields of one record per altered package like the CSV output, each terminated by a NUL byte.
+pub(crate) fn show_transactions_nul(selected: &[HistoryEntry], options: &DisplayOptions) {
+    for entry in selected {
+        let id = entry.id.to_string();
+        let date = entry
+            .start_date
+            .format(options.list_date_format())
+            .to_string();
+        for (action, pkg) in altered_packages(entry) {
+            print!("{}", nul_record(&[&id, &date, &entry.command_line, action, &pkg]));
+        }
+    }
+}
+
+pub(crate) fn show_transactions_markdown(selected: &[HistoryEntry], options: &DisplayOptions) {
+    let mut rows = vec![];
+    for entry in selected {
+        for (action, pkg) in altered_packages(entry) {
+            rows.push(vec![
+                entry.id.to_string(),
+                entry
+                    .start_date
+                    .format(options.list_date_format())
+                    .to_string(),
+                entry.command_line.clone(),
+                action.clone(),
+                pkg,
+            ]);
+        }
+    }
+    let numeric = [true, false, false, false, false];
+    let headers: Vec<&str> = INFO_TABLE_HEADERS.iter().map(|h| tr(h)).collect();
+    println!("{}", markdown::table(&headers, &numeric, &rows));
+}
+
+// All transactions in one table, transaction details are only shown on the first row of its
+// packages.
+fn show_transactions_table(selected: &[HistoryEntry], options: &DisplayOptions) {
+    let mut rows: Vec<Vec<Cell>> = Vec::new();
+    for entry in selected {
+        for (index, (action, pkg)) in altered_packages(entry).into_iter().enumerate() {
+            let mut row = if index == 0 {
+                vec![
+                    Cell::Int(entry.id as i32),
+                    Cell::from(
+                        &entry
+                            .start_date
+                            .format(options.list_date_format())
+                            .to_string(),
+                    ),
+                    Cell::from(&entry.command_line),
+                ]
+            } else {
+                vec![Cell::from(""), Cell::from(""), Cell::from("")]
+            };
+            row.push(Cell::from(action));
+            row.push(Cell::from(&pkg));
+            rows.push(row);
+        }
+    }
+
+    let headers = Headers::from(INFO_TABLE_HEADERS.iter().map(|h| tr(h)).collect());
+    let table = Table::new(Style::Presto, rows, Some(headers)).tabulate();
+    println!("{}", table);
+}
+
+pub fn info(
+    query: Option<Vec<String>>,
+    filters: &Filters,
+    options: &DisplayOptions,
+    vulns_data: Option<String>,
+    release: Option<String>,
+    table: bool,
+) -> Result<()> {
+    let selected = filters.apply(matching_entries(query)?)?;
+    if table && options.output == OutputFormat::Table {
+        show_transactions_table(&selected, options);
+        return Ok(());
+    }
+    let vulnerabilities = vulns_data
+        .map(|source| VulnerabilityData::load(&source, release))
+        .transpose()?;
+    show_transactions(&selected, options, vulnerabilities.as_ref())
+}
+
+// Reinstall and Remove share an initial, so removals are abbreviated as erasures like dnf does.
+fn action_abbreviation(action: &str) -> String {
+    match action {
+        "Remove" => "E".to_string(),
+        _ => action
+            .chars()
+            .next()
+            .expect("error getting first char of action")
+            .to_string(),
+    }
+}
+
+// Single actions are shown in full, several actions by their initials.
+fn list_actions(entry: &HistoryEntry) -> String {
+    let actions = entry.sorted_actions();
+    if actions.len() == 1 {
+        actions
+            .first()
+            .expect("error getting action of history entry")
+            .to_string()
+    } else {
+        let initials: Vec<_> = actions.iter().map(|a| action_abbreviation(a)).collect();
+        initials.join(", ")
+    }
+}
+
+// Columns requested by the user, or the default ones with the host of imported histories and the
+// source in verbose mode.
+fn list_columns(options: &DisplayOptions) -> Vec<Column> {
+    if !options.columns.is_empty() {
+        return options.columns.clone();
+    }
+    let mut columns = DEFAULT_COLUMNS.to_vec();
+    if imports::enabled() {
+        columns.insert(1, Column::Host);
+    }
+    if options.verbose {
+        columns.push(Column::Source);
+    }
+    columns
+}
+
+pub(crate) fn show_list_csv(selected: &[HistoryEntry], options: &DisplayOptions) {
+    let columns = list_columns(options);
+    let headers: Vec<&str> = columns.iter().map(|c| c.header()).collect();
+    print!("{}", csv::record(&headers));
+    for entry in selected {
+        let fields: Vec<String> = columns.iter().map(|c| c.value(entry, options)).collect();
+        print!("{}", csv::record(&fields));
+    }
+}
+
+// Records have as many fields as there are columns, so they are told apart by counting fields.
+fn nul_record<S: AsRef<str>>(fields: &[S]) -> String {
+    fields.iter().map(|f| format!("{}\0", f.as_ref())).collect()
+}
+
+pub(crate) fn show_list_nul(selected: &[HistoryEntry], options: &DisplayOptions) {
+    let columns = list_columns(options);
+    for entry in selected {
+        let fields: Vec<String> = columns.iter().map(|c| c.value(entry, options)).collect();
+        print!("{}", nul_record(&fields));
+    }
+}
+
+pub(crate) fn show_list_markdown(selected: &[HistoryEntry], options: &DisplayOptions) {
+    let columns = list_columns(options);
+    let headers: Vec<&str> = columns.iter().map(|c| c.label()).collect();
+    let numeric: Vec<bool> = columns.iter().map(|c| c.is_numeric()).collect();
+    let rows: Vec<Vec<String>> = selected
+        .iter()
+        .map(|entry| columns.iter().map(|c| c.value(entry, options)).collect())
+        .collect();
+    println!("{}", markdown::table(&headers, &numeric, &rows));
+}
+
+pub(crate) fn show_list_table(selected: &[HistoryEntry], options: &DisplayOptions) {
+    let columns = list_columns(options);
+    let headers: Vec<&str> = columns.iter().map(|c| c.label()).collect();
+    let mut selected = selected.to_vec();
+    if !options.full_command_line && columns.contains(&Column::CommandLine) {
+        fit_command_lines(&mut selected, &columns, options);
+    }
+    let rows: Vec<Vec<Cell>> = selected
+        .iter()
+        .map(|entry| columns.iter().map(|c| c.cell(entry, options)).collect())
+        .collect();
+    let table = Table::new(Style::Presto, rows, Some(Headers::from(headers))).tabulate();
+    // Rows after the header and the line below it.
+    let mut lines = table.lines().map(str::to_string);
+    let header: Vec<String> = lines.by_ref().take(2).collect();
+    let rows = elide(lines.collect(), options.max_lines, " ", "transactions");
+    for line in header.iter().chain(rows.iter()) {
+        println!("{line}");
+    }
+
+    let overlaps: Vec<String> = overlapping_pairs(&selected)
+        .iter()
+        .map(|(a, b)| format!("{a} and {b}"))
+        .collect();
+    if !overlaps.is_empty() {
+        println!("{}: {}", tr("Overlapping transactions"), overlaps.join(", "));
+    }
+}
+
+pub(crate) fn show_list(selected: Vec<HistoryEntry>, options: &DisplayOptions) -> Result<()> {
+    let mut selected = displayed_entries(&selected, options);
+    // As with `info`, a broken notes file does not keep transactions from being shown.
+    if list_columns(options).contains(&Column::Comment) {
+        let notes = Notes::load().unwrap_or_else(|e| {
+            eprintln!("Warning: {e}");
+            Notes::default()
+        });
+        for entry in selected.iter_mut() {
+            entry.comment = notes
+                .0
+                .get(&entry.fingerprint())
+                .and_then(|n| n.comment.as_deref())
+                .map(comment_summary);
+        }
+    }
     // Default behavior of dnf is to list entries in descending order by ID, the entries we get by
     // parsing history logs is in ascending order by default.
-    if !reverse {
+    if !options.reverse {
         selected.reverse();
     }
+    render::renderer(options).list(&selected, options)
+}
 
-    let mut rows: Vec<Vec<Cell>> = Vec::new();
-    selected.iter().for_each(|entry| {
-        let actions: Vec<&String> = entry.affected.keys().collect();
-        let actions = if actions.len() == 1 {
-            actions
-                .get(0)
-                .expect("error getting action of history entry")
-                .to_string()
-        } else {
-            let mut initials: Vec<_> = actions
+// Truncates command lines so that list rows fit the width of the terminal, the width of the other
+// columns is measured by rendering the table with empty command lines. Command lines are left as
+// they are when not writing to a terminal.
+fn fit_command_lines(selected: &mut [HistoryEntry], columns: &[Column], options: &DisplayOptions) {
+    if !terminal::stdout_is_terminal() {
+        return;
+    }
+    let Some(width) = terminal::width() else {
+        return;
+    };
+    let mut truncation = Config::load_checked().truncation;
+    if truncation.strategy == TruncationStrategy::None {
+        return;
+    }
+
+    let rows: Vec<Vec<Cell>> = selected
+        .iter()
+        .map(|entry| {
+            columns
                 .iter()
-                .map(|a| {
-                    a.chars()
-                        .nth(0)
-                        .expect("error getting first char of action")
-                        .to_string()
+                .map(|c| match c {
+                    Column::CommandLine => Cell::from(""),
+                    _ => c.cell(entry, options),
                 })
-                .collect();
-            initials.sort();
-            let joined = initials.join(", ");
-            joined
-        };
+                .collect()
+        })
+        .collect();
+    let headers: Vec<&str> = columns.iter().map(|c| c.label()).collect();
+    let table = Table::new(Style::Presto, rows, Some(Headers::from(headers))).tabulate();
+    let base_width = table.lines().next().map_or(0, |l| l.chars().count());
+    let header_width = Column::CommandLine.label().chars().count();
+    truncation.max_length = (width + header_width)
+        .saturating_sub(base_width)
+        .max(MIN_COMMAND_LINE_WIDTH);
 
-        let row = vec![
-            Cell::Int(entry.id as i32),
-            Cell::from(&entry.command_line),
-            Cell::from(&entry.start_date.format(LIST_DATE_FORMAT).to_string()),
-            Cell::from(&actions),
-            Cell::Int(entry.altered as i32),
-        ];
-        rows.push(row);
-    });
+    for entry in selected.iter_mut() {
+        entry.command_line = truncate_command_line(entry.full_command_line.clone(), &truncation);
+    }
+}
 
-    let table = Table::new(Style::Presto, rows, Some(Headers::from(HEADERS.to_vec()))).tabulate();
-    println!("{}", table);
+// Prints entries as JSON lines without waiting for all logs to be parsed. Unfinished entries are
+// skipped while apt is running as the last one of them is still being written. Only UUIDs which
+// have been assigned before are known here.
+fn stream_list(filters: &Filters, options: &DisplayOptions) -> Result<()> {
+    let locked = lock::frontend_lock_holder().is_some();
+    let uuids = uuids::known();
+    visit_entries(&mut |mut entry| {
+        if (locked && !entry.finished) || !filters.matches(&entry, &HashMap::new()) {
+            return;
+        }
+        entry.uuid = uuids.get(&entry.fingerprint()).cloned();
+        let entry = displayed_entries(&[entry], options).remove(0);
+        println!("{}", entry.to_summary_json(options.counting));
+    })
+}
+
+pub fn list(query: Option<Vec<String>>, filters: &Filters, options: &DisplayOptions) -> Result<()> {
+    if query.is_some() {
+        return show_list(filters.apply(matching_entries(query)?)?, options);
+    }
+    // Filtering by package classes needs all packages to look them up at once, as does a hook
+    // command to run once for all entries and numbering the entries of imported hosts.
+    if options.output == OutputFormat::Jsonl
+        && options.format.is_none()
+        && !options.ids_only
+        && !filters.needs_classes()
+        && Config::load()?.hooks.command.is_none()
+        && !imports::enabled()
+    {
+        return stream_list(filters, options);
+    }
+
+    let (entries, current) = split_current(parse_entries()?);
+    if let Some(current) = current.filter(|_| options.output == OutputFormat::Table && !options.ids_only) {
+        show_current(&current);
+    }
+    show_list(filters.apply(entries)?, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: u32, start_date: &str, command_line: &str) -> HistoryEntry {
+        HistoryEntry {
+            command_line: command_line.to_string(),
+            full_command_line: command_line.to_string(),
+            id,
+            logged_command_line: command_line.to_string(),
+            start_date: NaiveDateTime::parse_from_str(start_date, JSON_DATE_FORMAT)
+                .expect("error parsing start date"),
+            ..HistoryEntry::default()
+        }
+    }
+
+    #[test]
+    fn hashes_with_fnv_1a() {
+        assert_eq!(fnv_hash(""), "cbf29ce484222325");
+        assert_eq!(fnv_hash("a"), "af63dc4c8601ec8c");
+    }
+
+    #[test]
+    fn fingerprints_start_date_and_logged_command_line() {
+        let entry = entry(3, "2024-06-01T10:00:00", "install -y curl");
+        // Archives and notes are keyed by fingerprints, which must not change between versions.
+        assert_eq!(entry.fingerprint(), "bb9da9844c5b67d8");
+    }
+
+    #[test]
+    fn keeps_fingerprints_when_ids_and_displayed_command_lines_change() {
+        let original = entry(3, "2024-06-01T10:00:00", "install -y curl");
+        let mut moved = original.clone();
+        moved.id = 7;
+        moved.host = "web01".to_string();
+        moved.command_line = "install <...>".to_string();
+        moved.full_command_line = "install -y curl".to_string();
+        assert_eq!(original.fingerprint(), moved.fingerprint());
+    }
+
+    #[test]
+    fn tells_transactions_apart_by_fingerprint() {
+        let original = entry(3, "2024-06-01T10:00:00", "install -y curl");
+        let later = entry(3, "2024-06-01T10:00:01", "install -y curl");
+        let other = entry(3, "2024-06-01T10:00:00", "install -y wget");
+        assert_ne!(original.fingerprint(), later.fingerprint());
+        assert_ne!(original.fingerprint(), other.fingerprint());
+    }
+
+    fn entries_on(hosts: &[(&str, u32)]) -> Vec<HistoryEntry> {
+        hosts
+            .iter()
+            .map(|(host, id)| HistoryEntry {
+                host: host.to_string(),
+                ..entry(*id, "2024-06-01T10:00:00", "upgrade")
+            })
+            .collect()
+    }
+
+    fn selected(entries: &[HistoryEntry], query: &[&str]) -> Vec<String> {
+        let query = (!query.is_empty()).then(|| query.iter().map(|t| t.to_string()).collect());
+        select_entries(entries, query)
+            .iter()
+            .map(|e| format!("{}:{}", e.host, e.id))
+            .collect()
+    }
+
+    #[test]
+    fn selects_last_entry_of_its_host_by_default() {
+        let entries = entries_on(&[("vm", 1), ("vm", 2), ("web01", 2)]);
+        assert_eq!(selected(&entries, &[]), ["web01:2"]);
+    }
+
+    #[test]
+    fn selects_ids_of_all_hosts_unless_qualified() {
+        let entries = entries_on(&[("vm", 1), ("vm", 2), ("web01", 1), ("web01", 2)]);
+        assert_eq!(selected(&entries, &["2"]), ["vm:2", "web01:2"]);
+        assert_eq!(selected(&entries, &["web01:1"]), ["web01:1"]);
+        assert_eq!(selected(&entries, &["db01:1"]), Vec::<String>::new());
+    }
+
+    #[test]
+    fn selects_ids_relative_to_the_last_entry() {
+        let entries = entries_on(&[("vm", 1), ("vm", 2), ("vm", 3)]);
+        assert_eq!(selected(&entries, &["0"]), ["vm:3"]);
+        assert_eq!(selected(&entries, &["-1", "-2"]), ["vm:1", "vm:2"]);
+    }
+
+    #[test]
+    fn selects_entries_changing_packages() {
+        let mut entries = entries_on(&[("vm", 1), ("vm", 2)]);
+        entries[0].affected.insert(
+            "Install".to_string(),
+            HashMap::from([("amd64".to_string(), HashSet::from(["curl".to_string()]))]),
+        );
+        assert_eq!(selected(&entries, &["curl"]), ["vm:1"]);
+        assert_eq!(selected(&entries, &["wget"]), Vec::<String>::new());
+    }
+
+    #[test]
+    fn selects_entries_by_uuid() {
+        let mut entries = entries_on(&[("vm", 1), ("web01", 1), ("web01", 2)]);
+        entries[1].uuid = Some("5fbf9347-ded9-4f38-a09e-24aaef638089".to_string());
+        assert_eq!(
+            selected(&entries, &["5fbf9347-ded9-4f38-a09e-24aaef638089"]),
+            ["web01:1"]
+        );
+        // Unknown UUIDs are looked up as package names.
+        assert_eq!(
+            selected(&entries, &["b4a88146-086b-485a-9e5b-22747dcb9b02"]),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn summarizes_long_and_multi_line_comments() {
+        assert_eq!(comment_summary("CHG-1234"), "CHG-1234");
+        assert_eq!(comment_summary("CHG-1234\nrollback plan"), "CHG-1234 <...>");
+        let summary = comment_summary(&"x".repeat(60));
+        assert_eq!(summary, format!("{} <...>", "x".repeat(34)));
+        assert_eq!(summary.chars().count(), MAX_COMMENT_LENGTH);
+    }
 }
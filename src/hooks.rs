@@ -0,0 +1,120 @@
+use crate::config::{Config, TagRule};
+use crate::error::{Error, Result};
+use crate::history::{truncate_command_line, AlteredCount, HistoryEntry};
+use crate::json::{self, Value};
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::thread;
+
+// Adds the tags of the rules matching the command line or a package name of an entry.
+pub(crate) fn tag(entry: &mut HistoryEntry, rules: &[TagRule]) {
+    for rule in rules {
+        let matches = rule.matches(&entry.full_command_line)
+            || entry
+                .affected
+                .values()
+                .flat_map(|pkg_map| pkg_map.values())
+                .any(|pkgs| pkgs.iter().any(|p| rule.matches(p)));
+        if matches && !entry.tags.contains(&rule.tag) {
+            entry.tags.push(rule.tag.clone());
+        }
+    }
+}
+
+// Changes one entry as told by a line of hook output: an object whose `command_line` replaces
+// the command line and whose `tags` are added to the entry. Other fields are ignored.
+fn apply(
+    entry: &mut HistoryEntry,
+    change: &Value,
+    config: &Config,
+) -> std::result::Result<(), String> {
+    if change.as_object().is_none() {
+        return Err(format!(
+            "output for transaction {} is not an object",
+            entry.id
+        ));
+    }
+    if let Some(command_line) = change.get("command_line") {
+        let command_line = command_line.as_str().ok_or(format!(
+            "command line for transaction {} is not text",
+            entry.id
+        ))?;
+        entry.full_command_line = command_line.to_string();
+        entry.command_line = truncate_command_line(command_line.to_string(), &config.truncation);
+    }
+    if let Some(tags) = change.get("tags") {
+        let not_list = || format!("tags for transaction {} are not a list of text", entry.id);
+        for tag in tags.as_array().ok_or_else(not_list)? {
+            let tag = tag.as_str().ok_or_else(not_list)?.to_string();
+            if !entry.tags.contains(&tag) {
+                entry.tags.push(tag);
+            }
+        }
+    }
+    Ok(())
+}
+
+// Passes entries through the configured hook command, if any: it reads one JSON summary line per
+// entry with the full command line on its standard input and writes one line per entry in the
+// same order, either empty to leave the entry as it is or an object as described in `apply`.
+pub(crate) fn run(entries: &mut [HistoryEntry], config: &Config) -> Result<()> {
+    let Some(command) = &config.hooks.command else {
+        return Ok(());
+    };
+    if entries.is_empty() {
+        return Ok(());
+    }
+    let hook_error = |message: String| Error::Hook {
+        command: command.join(" "),
+        message,
+    };
+    let mut child = Command::new(&command[0])
+        .args(&command[1..])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| hook_error(format!("error running hook: {e}")))?;
+
+    let input: String = entries
+        .iter()
+        .map(|e| {
+            let mut full = e.clone();
+            full.command_line = e.full_command_line.clone();
+            format!("{}\n", full.to_summary_json(AlteredCount::Packages))
+        })
+        .collect();
+    let mut stdin = child.stdin.take().expect("error getting hook input");
+    // Written from another thread so that a hook writing its output as it goes does not block.
+    let writer = thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let output = child
+        .wait_with_output()
+        .map_err(|e| hook_error(format!("error waiting for hook: {e}")))?;
+    // A hook exiting without reading all its input is only an error if it fails.
+    let _ = writer.join();
+    if !output.status.success() {
+        return Err(Error::CommandFailed {
+            command: command.join(" "),
+            code: output.status.code(),
+            message: None,
+        });
+    }
+
+    let output = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = output.lines().collect();
+    if lines.len() != entries.len() {
+        return Err(hook_error(format!(
+            "expected {} lines of output, got {}",
+            entries.len(),
+            lines.len()
+        )));
+    }
+    for (entry, line) in entries.iter_mut().zip(lines) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let change = json::parse(line)
+            .map_err(|e| hook_error(format!("invalid output for transaction {}: {e}", entry.id)))?;
+        apply(entry, &change, config).map_err(hook_error)?;
+    }
+    Ok(())
+}
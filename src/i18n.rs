@@ -0,0 +1,147 @@
+use std::env;
+use std::sync::OnceLock;
+
+// Locale variables in the order gettext consults them for messages.
+const LOCALE_VARIABLES: [&str; 3] = ["LC_ALL", "LC_MESSAGES", "LANG"];
+
+// Translations by language, keyed by the English text. Only text read by people is translated,
+// CSV headers and machine readable formats stay the same in every locale.
+const CATALOGS: [(&str, &[(&str, &str)]); 2] = [
+    (
+        "de",
+        &[
+            ("Action", "Aktion"),
+            ("Action(s)", "Aktion(en)"),
+            ("Altered", "Geändert"),
+            ("Begin time", "Beginn"),
+            ("Command Line", "Befehlszeile"),
+            ("Command line", "Befehlszeile"),
+            ("Comment", "Kommentar"),
+            ("Date and time", "Datum und Uhrzeit"),
+            ("Downloaded", "Heruntergeladen"),
+            ("Duration", "Dauer"),
+            ("End time", "Ende"),
+            ("Error", "Fehler"),
+            ("Host", "Rechner"),
+            ("Known Vulnerabilities:", "Bekannte Schwachstellen:"),
+            ("Marks", "Markierungen"),
+            ("Overlapping transactions", "Überlappende Transaktionen"),
+            ("Overlaps with", "Überlappt mit"),
+            ("Package", "Paket"),
+            ("Packages Altered:", "Geänderte Pakete:"),
+            ("Root", "Wurzelverzeichnis"),
+            ("Source", "Quelle"),
+            ("Tags", "Schlagwörter"),
+            ("Tool", "Werkzeug"),
+            ("Transaction ID", "Transaktions-ID"),
+            ("User", "Benutzer"),
+            ("assertion failed", "Zusicherung fehlgeschlagen"),
+            ("error fetching", "Fehler beim Abrufen von"),
+            ("error reading", "Fehler beim Lesen von"),
+            (
+                "error reading log directory",
+                "Fehler beim Lesen des Log-Verzeichnisses",
+            ),
+            ("error reading log file", "Fehler beim Lesen der Logdatei"),
+            ("error running", "Fehler beim Ausführen von"),
+            ("error writing", "Fehler beim Schreiben von"),
+            ("exit status", "Exit-Status"),
+            ("failed", "fehlgeschlagen"),
+            ("failed with exit status", "fehlgeschlagen mit Exit-Status"),
+            ("hook", "Hook"),
+            ("none", "keine"),
+            ("not finished", "nicht beendet"),
+            ("seconds", "Sekunden"),
+            ("terminated by a signal", "durch ein Signal beendet"),
+            ("unknown command", "unbekannter Befehl"),
+            (
+                "was terminated by a signal",
+                "wurde durch ein Signal beendet",
+            ),
+        ],
+    ),
+    (
+        "es",
+        &[
+            ("Action", "Acción"),
+            ("Action(s)", "Acción(es)"),
+            ("Altered", "Modificados"),
+            ("Begin time", "Inicio"),
+            ("Command Line", "Línea de órdenes"),
+            ("Command line", "Línea de órdenes"),
+            ("Comment", "Comentario"),
+            ("Date and time", "Fecha y hora"),
+            ("Downloaded", "Descargado"),
+            ("Duration", "Duración"),
+            ("End time", "Fin"),
+            ("Error", "Error"),
+            ("Host", "Equipo"),
+            ("Known Vulnerabilities:", "Vulnerabilidades conocidas:"),
+            ("Marks", "Marcas"),
+            ("Overlapping transactions", "Transacciones solapadas"),
+            ("Overlaps with", "Se solapa con"),
+            ("Package", "Paquete"),
+            ("Packages Altered:", "Paquetes modificados:"),
+            ("Root", "Raíz"),
+            ("Source", "Origen"),
+            ("Tags", "Etiquetas"),
+            ("Tool", "Herramienta"),
+            ("Transaction ID", "ID de transacción"),
+            ("User", "Usuario"),
+            ("assertion failed", "aserción fallida"),
+            ("error fetching", "error al obtener"),
+            ("error reading", "error al leer"),
+            (
+                "error reading log directory",
+                "error al leer el directorio de registros",
+            ),
+            (
+                "error reading log file",
+                "error al leer el archivo de registro",
+            ),
+            ("error running", "error al ejecutar"),
+            ("error writing", "error al escribir"),
+            ("exit status", "código de salida"),
+            ("failed", "falló"),
+            ("failed with exit status", "falló con el código de salida"),
+            ("hook", "gancho"),
+            ("none", "ninguna"),
+            ("not finished", "no terminada"),
+            ("seconds", "segundos"),
+            ("terminated by a signal", "terminado por una señal"),
+            ("unknown command", "orden desconocida"),
+            ("was terminated by a signal", "fue terminado por una señal"),
+        ],
+    ),
+];
+
+static CATALOG: OnceLock<&[(&str, &str)]> = OnceLock::new();
+
+// Language of a locale such as `de_DE.UTF-8` or `es_ES@euro`.
+fn language(locale: &str) -> &str {
+    locale.split(['_', '.', '@']).next().unwrap_or(locale)
+}
+
+// Catalog of the first locale variable set, as an empty variable is skipped. `C` and `POSIX` or
+// languages without a catalog get the English text.
+fn catalog() -> &'static [(&'static str, &'static str)] {
+    CATALOG.get_or_init(|| {
+        let locale = LOCALE_VARIABLES
+            .iter()
+            .filter_map(|v| env::var(v).ok())
+            .find(|l| !l.is_empty())
+            .unwrap_or_default();
+        CATALOGS
+            .iter()
+            .find(|(l, _)| *l == language(&locale))
+            .map_or(&[], |(_, catalog)| *catalog)
+    })
+}
+
+// Text in the language of the locale, or as it is without a translation.
+pub(crate) fn tr(text: &'static str) -> &'static str {
+    catalog()
+        .iter()
+        .find(|(english, _)| *english == text)
+        .map_or(text, |(_, translated)| translated)
+}
@@ -0,0 +1,306 @@
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::export::{archive_document, ARCHIVE_FORMAT, ARCHIVE_FORMAT_VERSION};
+use crate::history::{
+    order_entries, truncate_command_line, HistoryEntry, Provenance, JSON_DATE_FORMAT,
+};
+use crate::json::{self, Value};
+use crate::notes::{self, Note, Notes};
+use crate::sources::HistorySource;
+use crate::storage;
+use chrono::NaiveDateTime;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+const IMPORTS_DIR: &str = "apt-history/imports";
+const IMPORT_EXTENSION: &str = "json";
+// Name of the file of transactions whose archive does not name a host.
+const UNKNOWN_HOST: &str = "unknown";
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+pub fn init(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+// Whether imported histories are shown along with the one of this host.
+pub(crate) fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+fn imports_dir() -> Option<PathBuf> {
+    let data_home = match env::var("XDG_DATA_HOME") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var("HOME").ok()?).join(".local/share"),
+    };
+    Some(data_home.join(IMPORTS_DIR))
+}
+
+// Whether an entry was read from an imported history rather than a log of this host.
+pub(crate) fn is_imported(entry: &HistoryEntry) -> bool {
+    enabled() && imports_dir().is_some_and(|dir| Path::new(&entry.provenance.file).starts_with(dir))
+}
+
+fn texts(value: Option<&Value>) -> Option<Vec<String>> {
+    value?
+        .as_array()?
+        .iter()
+        .map(|v| v.as_str().map(str::to_string))
+        .collect()
+}
+
+fn date(value: &Value, key: &str) -> std::result::Result<NaiveDateTime, String> {
+    let text = value
+        .get(key)
+        .and_then(|d| d.as_str())
+        .ok_or(format!("transaction without `{key}`"))?;
+    NaiveDateTime::parse_from_str(text, JSON_DATE_FORMAT)
+        .map_err(|e| format!("invalid `{key}` `{text}`: {e}"))
+}
+
+// A transaction of an archive written by `export --to`, along with its note.
+fn entry_from_json(
+    value: &Value,
+    config: &Config,
+) -> std::result::Result<(HistoryEntry, Option<(String, Note)>), String> {
+    let text = |key: &str| value.get(key).and_then(|v| v.as_str()).map(str::to_string);
+    let command_line = text("command_line").unwrap_or_default();
+    // Without a name the transactions could not be told apart from the ones of this host.
+    let host = match text("host") {
+        Some(host) if host.trim().is_empty() => {
+            return Err("transaction with an empty `host`".to_string())
+        }
+        Some(host) => host,
+        None => UNKNOWN_HOST.to_string(),
+    };
+    let mut entry = HistoryEntry {
+        command_line: truncate_command_line(command_line.clone(), &config.truncation),
+        error: text("error"),
+        foreign_root: text("foreign_root"),
+        frontend: text("tool").unwrap_or_default(),
+        full_command_line: command_line.clone(),
+        host,
+        // Archives written before the logged command line was kept only have the full one.
+        logged_command_line: text("logged_command_line").unwrap_or(command_line),
+        requested_by: text("user"),
+        start_date: date(value, "start_date")?,
+        tags: texts(value.get("tags")).unwrap_or_default(),
+        uuid: text("uuid"),
+        ..HistoryEntry::default()
+    };
+    // Transactions which had not finished when the archive was written have no end date.
+    if value
+        .get("end_date")
+        .is_some_and(|d| !matches!(d, Value::Null))
+    {
+        entry.end_date = date(value, "end_date")?;
+        entry.finished = true;
+    }
+
+    let fingerprint = entry.fingerprint();
+    let invalid_packages = || format!("invalid packages of transaction `{fingerprint}`");
+    for (action, by_arch) in value
+        .get("packages")
+        .and_then(|p| p.as_object())
+        .unwrap_or_default()
+    {
+        let mut pkg_map = HashMap::new();
+        for (arch, pkgs) in by_arch.as_object().ok_or_else(invalid_packages)? {
+            let pkgs: HashSet<String> = texts(Some(pkgs))
+                .ok_or_else(invalid_packages)?
+                .into_iter()
+                .collect();
+            pkg_map.insert(arch.clone(), pkgs);
+        }
+        entry.affected.insert(action.clone(), pkg_map);
+    }
+    for (action, versions) in value
+        .get("versions")
+        .and_then(|v| v.as_object())
+        .unwrap_or_default()
+    {
+        let versions = versions
+            .as_object()
+            .ok_or_else(invalid_packages)?
+            .iter()
+            .map(|(pkg, version)| Some((pkg.clone(), version.as_str()?.to_string())))
+            .collect::<Option<HashMap<String, String>>>()
+            .ok_or_else(invalid_packages)?;
+        entry.versions.insert(action.clone(), versions);
+    }
+
+    let note = match value.get("note") {
+        Some(Value::Null) | None => None,
+        Some(note) => Some(Note::from_json(note)?),
+    };
+    Ok((entry, note))
+}
+
+// Transactions and notes of an archive, which can have been formatted again since it was
+// written.
+fn parse_archive(content: &str, path: &str, config: &Config) -> Result<(Vec<HistoryEntry>, Notes)> {
+    let invalid = |message: String| Error::InvalidInput {
+        path: path.to_string(),
+        message,
+    };
+    let document = json::parse(content).map_err(invalid)?;
+    if document.get("format").and_then(|f| f.as_str()) != Some(ARCHIVE_FORMAT) {
+        return Err(invalid(format!("not an `{ARCHIVE_FORMAT}` document")));
+    }
+    match document.get("version") {
+        Some(Value::Number(version)) if *version == ARCHIVE_FORMAT_VERSION => (),
+        _ => return Err(invalid("unsupported format version".to_string())),
+    }
+    let transactions = document
+        .get("transactions")
+        .and_then(|t| t.as_array())
+        .ok_or_else(|| invalid("no list of transactions".to_string()))?;
+
+    let mut entries = vec![];
+    let mut notes = Notes::default();
+    for transaction in transactions {
+        let (entry, note) = entry_from_json(transaction, config).map_err(invalid)?;
+        if let Some((fingerprint, note)) = note {
+            notes.0.insert(fingerprint, note);
+        }
+        entries.push(entry);
+    }
+    Ok((entries, notes))
+}
+
+// Histories imported with `import`, a file per host in `$XDG_DATA_HOME/apt-history/imports`.
+// They are only read with `--imported`, so that commands changing this host never see them by
+// accident.
+pub(crate) struct ImportedHistories;
+
+impl HistorySource for ImportedHistories {
+    fn files(&self) -> Result<Vec<PathBuf>> {
+        let Some(dir) = imports_dir().filter(|_| enabled()) else {
+            return Ok(vec![]);
+        };
+        let mut files: Vec<PathBuf> = fs::read_dir(dir)
+            .map(|d| d.flatten().map(|e| e.path()).collect())
+            .unwrap_or_default();
+        files.retain(|f| f.extension().is_some_and(|e| e == IMPORT_EXTENSION));
+        files.sort();
+        Ok(files)
+    }
+
+    // Imported files are written by `import` with one transaction per line, which is the range
+    // entries are said to come from.
+    fn parse(
+        &self,
+        filename: &str,
+        index_start: u32,
+        config: &Config,
+        sink: &mut dyn FnMut(HistoryEntry),
+    ) -> Result<u32> {
+        let content = fs::read_to_string(filename).map_err(|source| Error::LogFile {
+            path: filename.to_string(),
+            source,
+        })?;
+        let (entries, _) = parse_archive(&content, filename, config)?;
+        let mut offset = 0;
+        let mut ranges = vec![];
+        for line in content.split_inclusive('\n') {
+            if line.starts_with('{') && offset > 0 {
+                ranges.push((offset, offset + line.trim_end().len() as u64));
+            }
+            offset += line.len() as u64;
+        }
+
+        let mut index = index_start;
+        for (position, mut entry) in entries.into_iter().enumerate() {
+            let (start, end) = ranges.get(position).copied().unwrap_or_default();
+            entry.id = index;
+            entry.provenance = Provenance {
+                end,
+                file: filename.to_string(),
+                start,
+            };
+            sink(entry);
+            index += 1;
+        }
+        Ok(index - index_start)
+    }
+}
+
+// Host names are used as file names.
+fn import_path(dir: &Path, host: &str) -> PathBuf {
+    let name: String = host
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    dir.join(format!("{name}.{IMPORT_EXTENSION}"))
+}
+
+// Adds the transactions of archives written by `export --to` on other hosts to the imported
+// histories, merged with the ones imported before. Their notes are added to the notes of this
+// host.
+pub fn import(paths: Option<Vec<String>>) -> Result<()> {
+    let Some(paths) = paths else {
+        return Err(Error::Usage(
+            "`import` requires at least one archive written by `export --to`".to_string(),
+        ));
+    };
+    let config = Config::load()?;
+    let mut by_host: HashMap<String, Vec<HistoryEntry>> = HashMap::new();
+    let mut imported_notes = Notes::default();
+    for path in &paths {
+        let content = fs::read_to_string(path).map_err(|source| Error::InputFile {
+            path: path.clone(),
+            source,
+        })?;
+        let (entries, notes) = parse_archive(&content, path, &config)?;
+        for entry in entries {
+            by_host.entry(entry.host.clone()).or_default().push(entry);
+        }
+        imported_notes.0.extend(notes.0);
+    }
+
+    let dir = imports_dir().ok_or_else(|| {
+        Error::Usage(
+            "unable to determine the imports directory, set `XDG_DATA_HOME` or `HOME`".to_string(),
+        )
+    })?;
+    let mut hosts: Vec<String> = by_host.keys().cloned().collect();
+    hosts.sort();
+    for host in hosts {
+        let path = import_path(&dir, &host);
+        let mut added = 0;
+        let entries = by_host.remove(&host).unwrap_or_default();
+        storage::update(&path, |content| {
+            let mut merged = if content.trim().is_empty() {
+                vec![]
+            } else {
+                parse_archive(content, &path.display().to_string(), &config)?.0
+            };
+            let mut known: HashSet<String> = merged.iter().map(|e| e.fingerprint()).collect();
+            for entry in entries {
+                if known.insert(entry.fingerprint()) {
+                    merged.push(entry);
+                    added += 1;
+                }
+            }
+            order_entries(&mut merged);
+            Ok::<String, Error>(archive_document(&merged, &Notes::default()))
+        })?;
+        println!(
+            "Imported {added} new transactions of {host} into `{}`",
+            path.display()
+        );
+    }
+    if !imported_notes.0.is_empty() {
+        notes::store(imported_notes)?;
+    }
+    Ok(())
+}
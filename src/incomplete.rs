@@ -0,0 +1,79 @@
+use crate::error::{Error, Result};
+use crate::json::Value;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+static FAIL_FAST: AtomicBool = AtomicBool::new(false);
+// Sources which could not be read completely, in the order they were read.
+static FAILURES: Mutex<Vec<Failure>> = Mutex::new(vec![]);
+
+struct Failure {
+    error: String,
+    source: String,
+    // Transactions read from the source before the error.
+    transactions: u32,
+}
+
+pub fn init(fail_fast: bool) {
+    FAIL_FAST.store(fail_fast, Ordering::Relaxed);
+}
+
+// Passes on an error reading a source with `--fail-fast`. Otherwise the error is recorded for the
+// summary at the end and the transactions of the other sources are still shown, along with the
+// ones read from this source before the error.
+pub(crate) fn tolerate(source: &str, transactions: u32, error: Error) -> Result<()> {
+    if FAIL_FAST.load(Ordering::Relaxed) {
+        return Err(error);
+    }
+    let mut failures = FAILURES.lock().expect("error locking failed sources");
+    // Logs are parsed again by commands looking at entries more than once.
+    if !failures.iter().any(|f| f.source == source) {
+        failures.push(Failure {
+            error: error.to_string(),
+            source: source.to_string(),
+            transactions,
+        });
+    }
+    Ok(())
+}
+
+// A summary of the sources which could not be read, on stderr to keep it out of parsed output.
+// Machine readable output gets a JSON summary so that automation can tell partial results apart.
+pub fn notify(json: bool) {
+    let failures = FAILURES.lock().expect("error locking failed sources");
+    if failures.is_empty() {
+        return;
+    }
+    if json {
+        let sources = failures
+            .iter()
+            .map(|f| {
+                Value::Object(vec![
+                    ("source".to_string(), Value::String(f.source.clone())),
+                    ("error".to_string(), Value::String(f.error.clone())),
+                    (
+                        "transactions".to_string(),
+                        Value::Number(f.transactions as f64),
+                    ),
+                ])
+            })
+            .collect();
+        let summary = Value::Object(vec![("incomplete".to_string(), Value::Array(sources))]);
+        eprintln!("{summary}");
+        return;
+    }
+    eprintln!(
+        "Warning: results are incomplete as {} could not be read completely, use --fail-fast to \
+         stop at the first error:",
+        match failures.len() {
+            1 => "a source".to_string(),
+            count => format!("{count} sources"),
+        }
+    );
+    for failure in failures.iter() {
+        eprintln!(
+            "  {} ({} transactions read): {}",
+            failure.source, failure.transactions, failure.error
+        );
+    }
+}
@@ -0,0 +1,83 @@
+use crate::error::Result;
+use crate::history::{history_entries, Filters, KERNEL_PACKAGE_PREFIX, LIST_DATE_FORMAT};
+use std::fs;
+use stybulate::{Cell, Headers, Style, Table};
+
+const HEADERS: [&str; 6] = [
+    "Transaction",
+    "Date and time",
+    "Action",
+    "Package",
+    "Version",
+    "Running",
+];
+const KERNEL_HEADERS_PREFIX: &str = "linux-headers-";
+const OSRELEASE_PATH: &str = "/proc/sys/kernel/osrelease";
+const RUNNING_MARKER: &str = "yes";
+
+fn is_kernel_package(name: &str) -> bool {
+    name.starts_with(KERNEL_PACKAGE_PREFIX) || name.starts_with(KERNEL_HEADERS_PREFIX)
+}
+
+// Release of the running kernel as `uname -r` shows it, e.g. `6.1.0-37-amd64`.
+fn running_release() -> Option<String> {
+    let release = fs::read_to_string(OSRELEASE_PATH).ok()?;
+    Some(release.trim().to_string())
+}
+
+// Installations, upgrades and removals of kernel images and headers in chronological order. The
+// image package of the running kernel is flagged, and the summary tells which transaction
+// installed it.
+pub fn kernels(filters: &Filters) -> Result<()> {
+    let release = running_release();
+    let running = release
+        .as_ref()
+        .map(|r| format!("{KERNEL_PACKAGE_PREFIX}{r}"));
+    let mut rows: Vec<Vec<Cell>> = vec![];
+    let mut running_installed = None;
+    for entry in filters.apply(history_entries()?)? {
+        for action in entry.sorted_actions() {
+            let mut packages: Vec<(&String, &String)> = entry.affected[action]
+                .iter()
+                .flat_map(|(arch, pkgs)| pkgs.iter().map(move |p| (p, arch)))
+                .filter(|(p, _)| is_kernel_package(p))
+                .collect();
+            packages.sort();
+            for (pkg, arch) in packages {
+                let package = format!("{pkg}:{arch}");
+                let is_running = running.as_ref() == Some(pkg);
+                if is_running && action == "Install" {
+                    running_installed = Some(entry.id);
+                }
+                rows.push(vec![
+                    Cell::Int(entry.id as i32),
+                    Cell::from(&entry.start_date.format(LIST_DATE_FORMAT).to_string()),
+                    Cell::from(action),
+                    Cell::from(&package),
+                    Cell::from(&entry.package_versions(action, &package).join(" -> ")),
+                    Cell::from(if is_running { RUNNING_MARKER } else { "" }),
+                ]);
+            }
+        }
+    }
+
+    if rows.is_empty() {
+        println!("No recorded change of kernel packages");
+    } else {
+        let headers = Headers::from(HEADERS.to_vec());
+        println!(
+            "{}",
+            Table::new(Style::Presto, rows, Some(headers)).tabulate()
+        );
+    }
+    match (release, running_installed) {
+        (Some(release), Some(id)) => {
+            println!("Running kernel {release}, installed in transaction {id}")
+        }
+        (Some(release), None) => {
+            println!("Running kernel {release}, not installed by a recorded transaction")
+        }
+        (None, _) => println!("The release of the running kernel is unknown"),
+    }
+    Ok(())
+}
@@ -0,0 +1,449 @@
+mod apt;
+mod assertion;
+mod audit;
+mod autoremove;
+mod bundle;
+mod check;
+mod color;
+mod completions;
+mod config;
+mod csv;
+mod date;
+mod doctor;
+mod dpkg;
+mod error;
+mod explain;
+mod export;
+mod follow;
+mod frontend;
+mod history;
+mod hooks;
+mod i18n;
+mod imports;
+mod incomplete;
+mod json;
+mod kernels;
+mod lock;
+mod man;
+mod markdown;
+mod motd;
+mod net;
+mod notes;
+mod orphans;
+mod ownership;
+mod pager;
+mod preview;
+mod privileges;
+mod prune;
+mod redo;
+mod rename;
+mod render;
+mod repl;
+mod report;
+mod residue;
+mod rollback;
+mod search;
+mod security;
+mod shell;
+mod snapshot;
+mod sources;
+mod stats;
+mod storage;
+mod template;
+mod terminal;
+mod termlog;
+mod timeline;
+mod tui;
+mod undo;
+mod users;
+mod uuids;
+mod verify;
+mod version;
+mod vulns;
+mod yaml;
+
+use chrono::NaiveDateTime;
+use clap::{CommandFactory, Parser};
+use std::process;
+
+pub use error::{Error, Result};
+pub use history::{
+    history_entries, AlteredCount, Column, DisplayOptions, HistoryEntry, OutputFormat,
+};
+pub use render::{Renderer, TuiRenderer};
+pub use vulns::VulnerabilityData;
+
+const IDS_ONLY_FLAGS: [&str; 2] = ["-q", "--ids-only"];
+// `-0` is not a short flag for `--print0` as it is also the relative ID of the last transaction.
+const PRINT0_FLAGS: [&str; 1] = ["--print0"];
+// Commands whose output is paged, others are interactive or meant for other programs.
+const PAGED_COMMANDS: [&str; 12] = [
+    "audit", "info", "kernels", "list", "orphans", "package", "residue", "search", "security",
+    "stats", "timeline", "verify",
+];
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    #[arg(short, long)]
+    reverse: bool,
+
+    /// When to style output
+    #[arg(long, value_enum, default_value_t = color::ColorChoice::Auto)]
+    color: color::ColorChoice,
+
+    /// Do not page output that does not fit on the screen
+    #[arg(long)]
+    no_pager: bool,
+
+    /// Format to show transactions in with `list` and `info`
+    #[arg(long, value_enum, default_value_t = history::OutputFormat::Table)]
+    output: history::OutputFormat,
+
+    /// Comma separated columns to show with `list`, in the given order
+    #[arg(long, value_enum, value_delimiter = ',')]
+    columns: Vec<history::Column>,
+
+    /// Count altered packages once per transaction, or once per action they appear under
+    #[arg(long, value_enum, default_value_t = history::AlteredCount::Packages)]
+    altered: history::AlteredCount,
+
+    /// Show which log file and byte range each transaction was parsed from
+    #[arg(short, long)]
+    verbose: bool,
+
+    /// Age of the last upgrade after which `check` reports a warning
+    #[arg(long, default_value = "30d")]
+    warn_age: String,
+
+    /// Age of the last upgrade after which `check` reports a critical state
+    #[arg(long, default_value = "90d")]
+    crit_age: String,
+
+    /// Time a transaction may run for before `follow` warns that apt may be stuck at a prompt
+    #[arg(long, default_value = "1h")]
+    stuck_after: String,
+
+    /// Export the packages of a transaction as a Graphviz dependency graph
+    #[arg(long)]
+    dot: bool,
+
+    /// Export transactions as iCalendar events
+    #[arg(long)]
+    ics: bool,
+
+    /// Export the versions packages were left at by transactions
+    #[arg(long)]
+    manifest: bool,
+
+    /// Export metrics in Prometheus textfile collector format
+    #[arg(long)]
+    prometheus: bool,
+
+    /// Export transactions as SQL statements to load with `sqlite3`
+    #[arg(long)]
+    sqlite: bool,
+
+    /// Export the whole history with notes to a self-contained JSON archive at the given path, or
+    /// the transactions removed by `prune`
+    #[arg(long, value_name = "FILE")]
+    to: Option<String>,
+
+    /// Assert that no transactions happened since the given date
+    #[arg(long)]
+    no_changes_since: Option<String>,
+
+    /// Assert that no transactions changed the given package, can be repeated
+    #[arg(long)]
+    package_unchanged: Vec<String>,
+
+    /// Only show transactions started at or after the given date, such as `2024-06-01`,
+    /// `2024-06-01 12:30` or `2 weeks ago`
+    #[arg(long)]
+    since: Option<String>,
+
+    /// Only show transactions started before the given date, given as with `--since`
+    #[arg(long)]
+    until: Option<String>,
+
+    /// Only show transactions containing one of the comma separated actions, e.g. `remove,purge`
+    #[arg(long, value_delimiter = ',')]
+    action: Vec<String>,
+
+    /// Only show transactions run by the given front-end, e.g. `apt`, `apt-get` or `aptitude`
+    #[arg(long)]
+    tool: Option<String>,
+
+    /// Only show transactions requested by the given user, given by name or UID
+    #[arg(long, value_name = "NAME|UID")]
+    user: Option<String>,
+
+    /// Only show transactions changing a package of the given section, e.g. `kernel` or `libs`
+    #[arg(long)]
+    section: Option<String>,
+
+    /// Only show transactions changing a package of the given priority, e.g. `required`
+    #[arg(long)]
+    priority: Option<String>,
+
+    /// Leave out the middle of `list` tables and `info` package tables longer than this many lines
+    #[arg(long, value_name = "N")]
+    max_lines: Option<usize>,
+
+    /// Show all lines of tables even if a maximum number of lines is configured
+    #[arg(long)]
+    no_limit: bool,
+
+    /// Show the section and priority of packages with `info`, as found in the apt cache
+    #[arg(long)]
+    show_sections: bool,
+
+    /// Show command lines in full instead of truncating long ones
+    #[arg(long, visible_alias = "wide")]
+    full_commandline: bool,
+
+    /// Read logs and package state from a bundle written by `bundle` instead of this system
+    #[arg(long, value_name = "FILE")]
+    bundle: Option<String>,
+
+    /// Compare package changes over this many past weeks with `stats`
+    #[arg(long, value_name = "WEEKS")]
+    compare_weeks: Option<u32>,
+
+    /// Keep the transactions of this many past days with `prune`
+    #[arg(long, value_name = "DAYS")]
+    keep_days: Option<u32>,
+
+    /// Period to group transactions by with `timeline`
+    #[arg(long, value_enum, default_value_t = timeline::GroupBy::Day)]
+    group_by: timeline::GroupBy,
+
+    /// Print only the IDs of transactions, one per line
+    #[arg(short = 'q', long)]
+    ids_only: bool,
+
+    /// Terminate fields of `list` and `info` with NUL bytes, records have a field per column
+    #[arg(long)]
+    print0: bool,
+
+    /// Run the apt-get commands of `undo`, `redo` and `rollback` instead of printing them, and
+    /// rewrite the logs with `prune`
+    #[arg(long)]
+    run: bool,
+
+    /// Fail instead of skipping data that is not readable with the current privileges
+    #[arg(long)]
+    require_complete: bool,
+
+    /// Fail at the first log that cannot be read instead of showing the results of the others
+    #[arg(long)]
+    fail_fast: bool,
+
+    /// Show the histories imported from other hosts along with the one of this host
+    #[arg(long)]
+    imported: bool,
+
+    /// Show dates in `list` and `info` in a strftime format such as `%d.%m.%Y %H:%M`
+    #[arg(long, value_name = "FORMAT")]
+    date_format: Option<String>,
+
+    /// Show dates in `list` relative to now, such as `3 days ago`
+    #[arg(long)]
+    relative_dates: bool,
+
+    /// Show each transaction on a single line followed by its packages with `info`
+    #[arg(long)]
+    compact: bool,
+
+    /// Show one line per transaction from a template such as `{id}\t{start_date}\t{command_line}`,
+    /// variables are actions, altered, command_line, duration, end_date, error, foreign_root,
+    /// host, id, packages, source, start_date, tags, tool, user, uuid and versions
+    #[arg(long)]
+    format: Option<String>,
+
+    /// Show transactions with `info` exactly as they appear in the log files
+    #[arg(long)]
+    raw: bool,
+
+    /// Debian release to look up vulnerabilities for, instead of the codename in os-release
+    #[arg(long)]
+    release: Option<String>,
+
+    /// Show matching transactions in a single table
+    #[arg(long)]
+    table: bool,
+
+    /// Show known vulnerabilities affecting the versions installed by transactions
+    #[arg(long)]
+    vulns: bool,
+
+    /// Vulnerability data in Debian security tracker JSON format, as a file path or URL
+    #[arg(long, default_value = vulns::DEBIAN_SECURITY_TRACKER_URL)]
+    vulns_data: String,
+
+    #[arg(default_value = "list")]
+    command: String,
+
+    #[arg(allow_hyphen_values = true)]
+    transaction: Option<Vec<String>>,
+}
+
+// Query terms can start with a hyphen for relative IDs, so flags given after the query end up
+// among them, as in `list curl -q`. Only flags which cannot be mistaken for IDs are taken out.
+fn take_trailing_flag(query: &mut Option<Vec<String>>, names: &[&str]) -> bool {
+    let Some(terms) = query else {
+        return false;
+    };
+    let count = terms.len();
+    terms.retain(|t| !names.contains(&t.as_str()));
+    let found = terms.len() != count;
+    if terms.is_empty() {
+        *query = None;
+    }
+    found
+}
+
+fn parse_date_option(date: Option<&str>) -> Result<Option<NaiveDateTime>> {
+    date.map(date::parse_date).transpose().map_err(Error::Usage)
+}
+
+fn history(mut args: Args) -> Result<()> {
+    let ids_only = args.ids_only || take_trailing_flag(&mut args.transaction, &IDS_ONLY_FLAGS);
+    let print0 = args.print0 || take_trailing_flag(&mut args.transaction, &PRINT0_FLAGS);
+    color::init(args.color);
+    let config = config::Config::load()?;
+    color::init_marking(config.palette, config.markers);
+    if let Some(path) = &args.bundle {
+        bundle::open(path)?;
+    }
+    privileges::init(args.require_complete);
+    incomplete::init(args.fail_fast);
+    imports::init(args.imported);
+    history::check_actions(&args.action).map_err(Error::Usage)?;
+    let filters = history::Filters {
+        action: args.action,
+        priority: args.priority,
+        section: args.section,
+        since: parse_date_option(args.since.as_deref())?,
+        tool: args.tool,
+        until: parse_date_option(args.until.as_deref())?,
+        user: args.user,
+    };
+    let options = history::DisplayOptions {
+        columns: args.columns,
+        compact: args.compact,
+        counting: args.altered,
+        date_format: args
+            .date_format
+            .or(config.date_format)
+            .map(date::check_format)
+            .transpose()
+            .map_err(Error::Usage)?,
+        format: args.format.map(history::check_template).transpose()?,
+        ids_only,
+        max_lines: if args.no_limit {
+            None
+        } else {
+            args.max_lines.or(config.max_lines)
+        },
+        full_command_line: args.full_commandline,
+        output: args.output,
+        print0,
+        raw: args.raw,
+        relative_dates: args.relative_dates,
+        reverse: args.reverse,
+        sections: args.show_sections,
+        verbose: args.verbose,
+    };
+
+    match args.command.as_str() {
+        "list" => history::list(args.transaction, &filters, &options),
+        "import" => imports::import(args.transaction),
+        "info" => history::info(
+            args.transaction,
+            &filters,
+            &options,
+            args.vulns.then_some(args.vulns_data),
+            args.release,
+            args.table,
+        ),
+        "annotate" => notes::annotate(args.transaction),
+        "assert" => assertion::assert(args.no_changes_since, args.package_unchanged),
+        "audit" => audit::audit(),
+        "autoremove-preview" => autoremove::autoremove_preview(),
+        "blame" => ownership::blame(args.transaction),
+        "bundle" => bundle::bundle(args.transaction),
+        "check" => check::check(&args.warn_age, &args.crit_age),
+        "completions" => completions::completions(args.transaction, Args::command()),
+        "doctor" => doctor::doctor(),
+        "explain" => explain::explain(args.transaction),
+        "export" => export::export(
+            args.transaction,
+            &filters,
+            &options,
+            &export::Formats {
+                dot: args.dot,
+                ics: args.ics,
+                manifest: args.manifest,
+                prometheus: args.prometheus,
+                sqlite: args.sqlite,
+                to: args.to,
+            },
+        ),
+        "follow" => follow::follow(&filters, &options, &args.stuck_after),
+        "kernels" => kernels::kernels(&filters),
+        "man" => man::man(Args::command()),
+        "motd" => motd::motd(),
+        "notes" => notes::notes(args.transaction),
+        "orphans" => orphans::orphans(&filters),
+        "package" => ownership::package(args.transaction),
+        "preview-diff" => preview::preview_diff(),
+        "prune" => prune::prune(args.keep_days, args.to.as_deref(), args.run),
+        "redo" => redo::redo(args.transaction, args.run),
+        "repl" => repl::repl(&filters, &options),
+        "report" => report::report(args.transaction, &filters),
+        "residue" => residue::residue(),
+        "rollback" => rollback::rollback(args.transaction, args.run),
+        "search" => search::search(args.transaction, &filters, &options),
+        "security" => security::security(&filters),
+        "shell-init" => shell::shell_init(args.transaction),
+        "snapshot-sources" => snapshot::snapshot_sources(args.transaction),
+        "stats" => stats::stats(&filters, args.compare_weeks),
+        "timeline" => timeline::timeline(&filters, args.group_by),
+        "tui" => tui::tui(),
+        "undo" => undo::undo(args.transaction, args.run),
+        "verify" => verify::verify(),
+        "who-installed" => ownership::who_installed(args.transaction),
+        _ => Err(Error::UnknownCommand(args.command.clone())),
+    }
+}
+
+/// Runs the command line interface, exiting with the status of a failure if there is one.
+pub fn run() {
+    let args = Args::parse();
+    // The transaction browser takes over the terminal instead of writing output to page.
+    let browsing = args.output == history::OutputFormat::Tui;
+    let checking = args.command == "check";
+    if !args.no_pager
+        && !browsing
+        && PAGED_COMMANDS.contains(&args.command.as_str())
+        && pager::should_page()
+    {
+        pager::page();
+    }
+    let json = matches!(
+        args.output,
+        history::OutputFormat::Json | history::OutputFormat::Jsonl
+    );
+    let result = history(args);
+    privileges::notify();
+    incomplete::notify(json);
+    bundle::close();
+    if let Err(e) = result {
+        if checking {
+            check::unknown(&e);
+        }
+        eprintln!("apt-history: {e}");
+        process::exit(e.exit_code());
+    }
+}
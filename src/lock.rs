@@ -0,0 +1,23 @@
+use crate::bundle;
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+
+// Lock apt front-ends hold for the whole duration of a transaction.
+const FRONTEND_LOCK_PATH: &str = "/var/lib/dpkg/lock-frontend";
+const PROC_LOCKS_PATH: &str = "/proc/locks";
+
+// ID of the process holding the front-end lock, found by the inode of the lock file in
+// `/proc/locks` since the lock cannot be probed without trying to acquire it.
+pub(crate) fn frontend_lock_holder() -> Option<u32> {
+    let inode = fs::metadata(bundle::path(FRONTEND_LOCK_PATH)).ok()?.ino();
+    let locks = fs::read_to_string(PROC_LOCKS_PATH).ok()?;
+    locks.lines().find_map(|line| {
+        // e.g. `1: POSIX  ADVISORY  WRITE 1234 08:01:131 0 EOF`
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let lock_inode: u64 = fields.get(5)?.rsplit(':').next()?.parse().ok()?;
+        if lock_inode != inode {
+            return None;
+        }
+        fields.get(4)?.parse().ok()
+    })
+}
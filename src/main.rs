@@ -1,29 +1,3 @@
-mod history;
-
-use clap::Parser;
-
-#[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
-struct Args {
-    #[arg(short, long)]
-    reverse: bool,
-
-    #[arg(default_value = "list")]
-    command: String,
-
-    #[arg(allow_hyphen_values = true)]
-    transaction: Option<Vec<String>>,
-}
-
-fn history(args: Args) {
-    match args.command.as_str() {
-        "list" => history::list(args.transaction, args.reverse),
-        "info" => history::info(args.transaction),
-        _ => panic!("unknown command: `{}`", args.command),
-    }
-}
-
 fn main() {
-    let args = Args::parse();
-    history(args);
+    apt_history::run();
 }
@@ -0,0 +1,160 @@
+use crate::completions::COMMANDS;
+use crate::error::{
+    Result, EX_ASSERTION, EX_CANTCREAT, EX_DATAERR, EX_NOINPUT, EX_SOFTWARE, EX_UNAVAILABLE,
+    EX_USAGE,
+};
+use crate::history::APT_LOG_PATH;
+use crate::{prune, uuids};
+use clap::Command;
+
+const DESCRIPTION: &str = "Shows the transactions recorded by apt in `history.log` and its \
+rotated copies, with the packages each of them changed. Transactions are referred to by their \
+ID, counted from the oldest one, by a negative ID counted back from the latest one, by UUID or \
+by the name of a package they changed. Without a command, transactions are listed.";
+
+// Text for roff: backslashes and hyphens are escaped, lines cannot start with a control
+// character and `code` is set in bold, as in the help of the flags.
+fn escape(text: &str) -> String {
+    let escaped = text.replace('\\', r"\e").replace('-', r"\-");
+    let mut bold = false;
+    let mut formatted = String::new();
+    for c in escaped.chars() {
+        if c == '`' {
+            formatted.push_str(if bold { r"\fR" } else { r"\fB" });
+            bold = !bold;
+        } else {
+            formatted.push(c);
+        }
+    }
+    if formatted.starts_with(['.', '\'']) {
+        formatted.insert_str(0, r"\&");
+    }
+    formatted
+}
+
+fn options(command: &Command) -> String {
+    let mut section = String::new();
+    for arg in command.get_arguments().filter(|a| !a.is_positional()) {
+        let mut names = vec![];
+        if let Some(short) = arg.get_short() {
+            names.push(format!(r"\fB\-{short}\fR"));
+        }
+        if let Some(long) = arg.get_long() {
+            names.push(format!(r"\fB\-\-{}\fR", escape(long)));
+        }
+        let mut line = names.join(", ");
+        if arg.get_action().takes_values() {
+            let value = arg
+                .get_value_names()
+                .and_then(|names| names.first())
+                .map(|name| name.to_string())
+                .unwrap_or_else(|| arg.get_id().as_str().to_uppercase());
+            line.push_str(&format!(r" \fI{}\fR", escape(&value)));
+        }
+        let mut sentences: Vec<String> =
+            arg.get_help().map(|h| h.to_string()).into_iter().collect();
+        let choices: Vec<String> = arg
+            .get_possible_values()
+            .iter()
+            .filter(|v| !v.is_hide_set())
+            .map(|v| format!("`{}`", v.get_name()))
+            .collect();
+        if arg.get_action().takes_values() && !choices.is_empty() {
+            sentences.push(format!("One of {}", choices.join(", ")));
+        }
+        let defaults: Vec<String> = arg
+            .get_default_values()
+            .iter()
+            .map(|v| format!("`{}`", v.to_string_lossy()))
+            .collect();
+        if arg.get_action().takes_values() && !defaults.is_empty() {
+            sentences.push(format!("Defaults to {}", defaults.join(", ")));
+        }
+        section.push_str(&format!(".TP\n{line}\n"));
+        for sentence in sentences {
+            section.push_str(&format!("{}.\n", escape(&sentence)));
+        }
+    }
+    section
+}
+
+// Prints a manual page in roff format for packaging, generated from the flags of the command line
+// parser, e.g. `apt-history man > apt-history.1`.
+pub fn man(mut command: Command) -> Result<()> {
+    command.build();
+    let version = command.get_version().unwrap_or_default();
+    let mut page = format!(
+        ".TH APT\\-HISTORY 1 \"\" \"apt\\-history {}\" \"User Commands\"\n",
+        escape(version)
+    );
+    page.push_str(".SH NAME\napt\\-history \\- show the history of apt transactions\n");
+    page.push_str(".SH SYNOPSIS\n.B apt\\-history\n");
+    page.push_str("[\\fIOPTIONS\\fR] [\\fICOMMAND\\fR] [\\fITRANSACTION\\fR...]\n");
+    page.push_str(&format!(".SH DESCRIPTION\n{}\n", escape(DESCRIPTION)));
+
+    page.push_str(".SH COMMANDS\n");
+    for (name, summary) in COMMANDS {
+        page.push_str(&format!(".TP\n.B {}\n{}.\n", escape(name), escape(summary)));
+    }
+    page.push_str(".SH OPTIONS\n");
+    page.push_str(&options(&command));
+
+    page.push_str(".SH EXIT STATUS\n");
+    for (status, meaning) in [
+        (0, "Success, or no check or assertion failed."),
+        (EX_ASSERTION, "An assertion failed."),
+        (EX_USAGE, "Invalid command line."),
+        (
+            EX_DATAERR,
+            "Invalid data, such as a log or archive which cannot be parsed.",
+        ),
+        (EX_NOINPUT, "A log or input file cannot be read."),
+        (EX_CANTCREAT, "An output or state file cannot be written."),
+        (
+            EX_UNAVAILABLE,
+            "A network resource or a program run by apt-history is not available.",
+        ),
+        (
+            EX_SOFTWARE,
+            "A command run by apt-history was terminated by a signal, or a program it runs for itself, \
+             such as tar or dpkg, failed.",
+        ),
+    ] {
+        page.push_str(&format!(".TP\n.B {status}\n{}\n", escape(meaning)));
+    }
+    page.push_str(".PP\n");
+    page.push_str(&escape(
+        "Commands run by apt-history which fail pass on their exit status, and `check` exits \
+         with the status of its monitoring state, which is UNKNOWN (3) for any error.\n",
+    ));
+
+    page.push_str(".SH FILES\n");
+    for (path, meaning) in [
+        (
+            format!("{APT_LOG_PATH}/history.log*"),
+            "The transactions recorded by apt.",
+        ),
+        (uuids::INDEX_PATH.to_string(), "UUIDs of transactions."),
+        (
+            prune::INDEX_PATH.to_string(),
+            "Summaries of the transactions removed by `prune`.",
+        ),
+        (
+            "$XDG_CONFIG_HOME/apt-history/config".to_string(),
+            "Configuration.",
+        ),
+        (
+            "$XDG_DATA_HOME/apt-history/notes.json".to_string(),
+            "Notes of transactions.",
+        ),
+        (
+            "$XDG_DATA_HOME/apt-history/imports".to_string(),
+            "Histories imported from other hosts.",
+        ),
+    ] {
+        page.push_str(&format!(".TP\n.I {}\n{}\n", escape(&path), escape(meaning)));
+    }
+    page.push_str(".SH SEE ALSO\n.BR apt (8),\n.BR apt\\-get (8),\n.BR dpkg (1)\n");
+    print!("{page}");
+    Ok(())
+}
@@ -0,0 +1,23 @@
+// GitHub-flavored Markdown tables.
+fn cell(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('|', "\\|")
+        .replace('\n', " ")
+}
+
+fn row<S: AsRef<str>>(values: &[S]) -> String {
+    let cells: Vec<String> = values.iter().map(|v| cell(v.as_ref())).collect();
+    format!("| {} |", cells.join(" | "))
+}
+
+// Columns flagged as numeric are right aligned.
+pub(crate) fn table<S: AsRef<str>>(headers: &[&str], numeric: &[bool], rows: &[Vec<S>]) -> String {
+    let delimiters: Vec<&str> = numeric
+        .iter()
+        .map(|n| if *n { "---:" } else { "---" })
+        .collect();
+    let mut lines = vec![row(headers), format!("|{}|", delimiters.join("|"))];
+    lines.extend(rows.iter().map(|r| row(r)));
+    lines.join("\n")
+}
@@ -0,0 +1,50 @@
+use crate::error::Result;
+use crate::history::{history_entries, AlteredCount, LIST_DATE_FORMAT};
+use chrono::prelude::*;
+use chrono::Duration;
+use std::path::Path;
+
+const LABEL_WIDTH: usize = 20;
+const LINE_WIDTH: usize = 79;
+const RECENT_DAYS: i64 = 7;
+const REBOOT_REQUIRED_PATH: &str = "/var/run/reboot-required";
+
+fn line(label: &str, value: &str) {
+    let line = format!("{label:<LABEL_WIDTH$}{value}");
+    println!("{}", line.chars().take(LINE_WIDTH).collect::<String>());
+}
+
+// Summary meant to be dropped into `/etc/update-motd.d`, kept within 80 columns.
+pub fn motd() -> Result<()> {
+    let entries = history_entries()?;
+
+    match entries.last() {
+        Some(last) => line(
+            "Last apt change:",
+            &format!(
+                "{} {} {} ({} packages)",
+                last.start_date.format(LIST_DATE_FORMAT),
+                last.frontend,
+                last.command_line,
+                last.altered(AlteredCount::default())
+            ),
+        ),
+        None => line("Last apt change:", "none recorded"),
+    }
+
+    let since =
+        Local::now().naive_local() - Duration::try_days(RECENT_DAYS).expect("invalid day count");
+    let recent = entries.iter().filter(|e| e.start_date >= since).count();
+    line(
+        &format!("Past {RECENT_DAYS} days:"),
+        &format!("{recent} transactions"),
+    );
+
+    let reboot = if Path::new(REBOOT_REQUIRED_PATH).exists() {
+        "required"
+    } else {
+        "not required"
+    };
+    line("Reboot:", reboot);
+    Ok(())
+}
@@ -0,0 +1,162 @@
+use crate::config::{Config, NetworkSettings};
+use crate::error::{Error, Result};
+use crate::history::fnv_hash;
+use crate::storage;
+use std::env;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const CACHE_DIR: &str = "apt-history/http";
+const LAST_REQUEST_FILE: &str = "last-request";
+const SLOT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+const SLOT_PREFIX: &str = "slot-";
+
+fn cache_dir() -> Option<PathBuf> {
+    let cache_home = match env::var("XDG_CACHE_HOME") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var("HOME").ok()?).join(".cache"),
+    };
+    let dir = cache_home.join(CACHE_DIR);
+    fs::create_dir_all(&dir).ok()?;
+    Some(dir)
+}
+
+fn std_duration(duration: chrono::Duration) -> Duration {
+    duration.to_std().unwrap_or_default()
+}
+
+// A cached response if it is younger than the given age.
+fn cached(path: &Path, max_age: Duration) -> Option<String> {
+    let age = fs::metadata(path).ok()?.modified().ok()?.elapsed().ok()?;
+    (age < max_age).then(|| fs::read_to_string(path).ok())?
+}
+
+// Takes one of the request slots, waiting up to the timeout until another run frees one if all
+// are taken. The slot is freed when the returned file is closed.
+fn request_slot(url: &str, dir: &Path, slots: usize, timeout: Duration) -> Result<File> {
+    let start = Instant::now();
+    loop {
+        for slot in 0..slots {
+            let path = dir.join(format!("{SLOT_PREFIX}{slot}"));
+            let file = File::create(&path).map_err(|source| Error::OutputFile {
+                path: path.display().to_string(),
+                source,
+            })?;
+            if file.try_lock().is_ok() {
+                return Ok(file);
+            }
+        }
+        if start.elapsed() >= timeout {
+            return Err(Error::Network {
+                url: url.to_string(),
+                message: format!("timed out waiting for one of {slots} request slots"),
+            });
+        }
+        thread::sleep(SLOT_POLL_INTERVAL);
+    }
+}
+
+// Waits until the minimum interval since the last request of any run has passed and records the
+// start of this one. Runs waiting for their turn queue up on the lock.
+fn wait_turn(dir: &Path, interval: Duration) -> Result<()> {
+    let path = dir.join(LAST_REQUEST_FILE);
+    storage::locked(&path, || {
+        let last = fs::read_to_string(&path)
+            .ok()
+            .and_then(|c| c.trim().parse::<u64>().ok())
+            .map(|millis| UNIX_EPOCH + Duration::from_millis(millis));
+        if let Some(wait) = last.and_then(|l| (l + interval).duration_since(SystemTime::now()).ok())
+        {
+            thread::sleep(wait);
+        }
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        fs::write(&path, format!("{now}\n")).map_err(|source| Error::OutputFile {
+            path: path.display().to_string(),
+            source,
+        })
+    })
+}
+
+fn download(url: &str, settings: &NetworkSettings) -> Result<String> {
+    let timeout = std_duration(settings.timeout).as_secs().max(1).to_string();
+    let output = Command::new("curl")
+        .args(["--silent", "--show-error", "--fail", "--location"])
+        .args(["--max-time", &timeout, url])
+        .output()
+        .map_err(|e| Error::Network {
+            url: url.to_string(),
+            message: format!("error running curl: {e}"),
+        })?;
+    if !output.status.success() {
+        return Err(Error::Network {
+            url: url.to_string(),
+            message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+    String::from_utf8(output.stdout).map_err(|_| Error::Network {
+        url: url.to_string(),
+        message: "response is not valid UTF-8".to_string(),
+    })
+}
+
+// Body of a response, from the cache if it has been fetched recently. Requests are limited as
+// configured in the `[network]` section across all runs of the user, so that runs on many
+// machines sharing a home directory or started at once do not overload services. A stale
+// response is used with a warning if the request fails, as for slow or unreachable mirrors.
+pub(crate) fn fetch(url: &str) -> Result<String> {
+    let settings = Config::load()?.network;
+    let Some(dir) = cache_dir() else {
+        return download(url, &settings);
+    };
+    let path = dir.join(fnv_hash(url));
+    if let Some(body) = cached(&path, std_duration(settings.cache_ttl)) {
+        return Ok(body);
+    }
+
+    let slot = match request_slot(
+        url,
+        &dir,
+        settings.max_requests,
+        std_duration(settings.timeout),
+    ) {
+        Ok(slot) => Some(slot),
+        Err(e @ Error::Network { .. }) => return Err(e),
+        // Requests are still made when the cache directory is not writable, without limits.
+        Err(e) => {
+            eprintln!("Warning: {e}, requests are not rate limited");
+            None
+        }
+    };
+    if slot.is_some() {
+        if let Err(e) = wait_turn(&dir, std_duration(settings.min_interval)) {
+            eprintln!("Warning: {e}, requests are not rate limited");
+        }
+    }
+    let response = download(url, &settings);
+    drop(slot);
+    match response {
+        Ok(body) => {
+            // A response which cannot be cached is used all the same.
+            let _ = storage::replace(&path, |temporary| {
+                fs::write(temporary, &body).map_err(|source| Error::OutputFile {
+                    path: path.display().to_string(),
+                    source,
+                })
+            });
+            Ok(body)
+        }
+        Err(e) => match fs::read_to_string(&path) {
+            Ok(body) => {
+                eprintln!("Warning: {e}, using the response cached before");
+                Ok(body)
+            }
+            Err(_) => Err(e),
+        },
+    }
+}
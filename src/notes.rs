@@ -0,0 +1,341 @@
+use crate::error::{Error, Result};
+use crate::history::{history_entries, matching_entries, HistoryEntry, JSON_DATE_FORMAT};
+use crate::json::{self, Value};
+use crate::storage;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const DATA_DIR: &str = "apt-history";
+const FORMAT: &str = "apt-history-notes";
+const FORMAT_VERSION: f64 = 1.0;
+const NOTES_FILE: &str = "notes.json";
+
+// Comment, tags and pin of a transaction. The host, start date and command line only describe
+// the transaction to readers of the notes file, notes are matched by fingerprint.
+#[derive(Default)]
+pub(crate) struct Note {
+    command_line: String,
+    pub(crate) comment: Option<String>,
+    host: String,
+    pub(crate) pinned: bool,
+    start_date: String,
+    pub(crate) tags: Vec<String>,
+}
+
+impl Note {
+    pub(crate) fn new(entry: &HistoryEntry) -> Note {
+        Note {
+            command_line: format!("{} {}", entry.frontend, entry.full_command_line)
+                .trim_end()
+                .to_string(),
+            host: entry.host.clone(),
+            start_date: entry.start_date.format(JSON_DATE_FORMAT).to_string(),
+            ..Note::default()
+        }
+    }
+
+    pub(crate) fn to_json(&self, fingerprint: &str) -> Value {
+        let tags = self.tags.iter().map(|t| Value::String(t.clone())).collect();
+        Value::Object(vec![
+            (
+                "transaction".to_string(),
+                Value::String(fingerprint.to_string()),
+            ),
+            ("host".to_string(), Value::String(self.host.clone())),
+            (
+                "start_date".to_string(),
+                Value::String(self.start_date.clone()),
+            ),
+            (
+                "command_line".to_string(),
+                Value::String(self.command_line.clone()),
+            ),
+            (
+                "comment".to_string(),
+                self.comment.clone().map_or(Value::Null, Value::String),
+            ),
+            ("tags".to_string(), Value::Array(tags)),
+            ("pinned".to_string(), Value::Bool(self.pinned)),
+        ])
+    }
+
+    pub(crate) fn from_json(value: &Value) -> std::result::Result<(String, Note), String> {
+        let text = |key: &str| value.get(key).and_then(|v| v.as_str()).map(str::to_string);
+        let fingerprint = text("transaction").ok_or("note without a transaction")?;
+        let mut tags = match value.get("tags") {
+            Some(tags) => tags
+                .as_array()
+                .ok_or(format!("tags of note `{fingerprint}` are not a list"))?
+                .iter()
+                .map(|t| t.as_str().map(str::to_string))
+                .collect::<Option<Vec<String>>>()
+                .ok_or(format!("tags of note `{fingerprint}` are not all text"))?,
+            None => vec![],
+        };
+        tags.sort();
+        tags.dedup();
+        let note = Note {
+            command_line: text("command_line").unwrap_or_default(),
+            comment: text("comment"),
+            host: text("host").unwrap_or_default(),
+            pinned: value
+                .get("pinned")
+                .and_then(|p| p.as_bool())
+                .unwrap_or_default(),
+            start_date: text("start_date").unwrap_or_default(),
+            tags,
+        };
+        Ok((fingerprint, note))
+    }
+
+    // Adds the tags and pin of another note of the same transaction. Of two different comments the
+    // existing one is kept, which is reported by returning false.
+    fn merge(&mut self, other: Note) -> bool {
+        for tag in other.tags {
+            if !self.tags.contains(&tag) {
+                self.tags.push(tag);
+            }
+        }
+        self.tags.sort();
+        self.pinned |= other.pinned;
+        match (&self.comment, other.comment) {
+            (None, comment) => self.comment = comment,
+            (Some(comment), Some(other)) if *comment != other => return false,
+            _ => (),
+        }
+        true
+    }
+}
+
+// Notes by transaction fingerprint, see `HistoryEntry::fingerprint`.
+#[derive(Default)]
+pub(crate) struct Notes(pub(crate) BTreeMap<String, Note>);
+
+impl Notes {
+    // Notes kept in `$XDG_DATA_HOME/apt-history/notes.json`, which is in the same format as
+    // exported notes so that it can be copied between machines as it is.
+    pub(crate) fn load() -> Result<Notes> {
+        let Some(path) = notes_path() else {
+            return Ok(Notes::default());
+        };
+        match fs::read_to_string(&path) {
+            Ok(content) => Notes::parse(&content, &path),
+            Err(_) => Ok(Notes::default()),
+        }
+    }
+
+    fn parse(content: &str, path: &Path) -> Result<Notes> {
+        let invalid = |message: String| Error::InvalidInput {
+            path: path.display().to_string(),
+            message,
+        };
+        if content.trim().is_empty() {
+            return Ok(Notes::default());
+        }
+        let document = json::parse(content).map_err(invalid)?;
+        if document.get("format").and_then(|f| f.as_str()) != Some(FORMAT) {
+            return Err(invalid(format!("not an `{FORMAT}` document")));
+        }
+        match document.get("version") {
+            Some(Value::Number(version)) if *version == FORMAT_VERSION => (),
+            _ => return Err(invalid("unsupported format version".to_string())),
+        }
+        let notes = document
+            .get("notes")
+            .and_then(|n| n.as_array())
+            .ok_or_else(|| invalid("no list of notes".to_string()))?;
+
+        let mut parsed = Notes::default();
+        for note in notes {
+            let (fingerprint, note) = Note::from_json(note).map_err(invalid)?;
+            parsed.0.insert(fingerprint, note);
+        }
+        Ok(parsed)
+    }
+
+    // One note per line, so that changes to a checked in notes file are easy to review.
+    fn to_document(&self) -> String {
+        let notes: Vec<String> = self
+            .0
+            .iter()
+            .map(|(fingerprint, note)| format!("\n{}", note.to_json(fingerprint)))
+            .collect();
+        let end = if notes.is_empty() { "" } else { "\n" };
+        format!(
+            "{{\"format\":{},\"version\":{FORMAT_VERSION},\"notes\":[{}{end}]}}\n",
+            Value::String(FORMAT.to_string()),
+            notes.join(",")
+        )
+    }
+
+    // Fingerprints of the imported notes with comments conflicting with existing ones.
+    fn merge(&mut self, other: Notes) -> Vec<String> {
+        let mut conflicts = vec![];
+        for (fingerprint, note) in other.0 {
+            match self.0.get_mut(&fingerprint) {
+                Some(existing) => {
+                    if !existing.merge(note) {
+                        conflicts.push(fingerprint);
+                    }
+                }
+                None => {
+                    self.0.insert(fingerprint, note);
+                }
+            }
+        }
+        conflicts
+    }
+}
+
+fn notes_path() -> Option<PathBuf> {
+    let data_home = match env::var("XDG_DATA_HOME") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var("HOME").ok()?).join(".local/share"),
+    };
+    Some(data_home.join(DATA_DIR).join(NOTES_FILE))
+}
+
+// Path of the notes file for writing, which requires a data directory to keep it in.
+fn required_notes_path() -> Result<PathBuf> {
+    notes_path().ok_or_else(|| {
+        Error::Usage(
+            "unable to determine the notes file path, set `XDG_DATA_HOME` or `HOME`".to_string(),
+        )
+    })
+}
+
+// Adds a note to the notes file, merged with the existing note of the transaction if there is one.
+pub(crate) fn add(entry: &HistoryEntry, note: Note) -> Result<()> {
+    let notes_path = required_notes_path()?;
+    let mut added = Notes::default();
+    added.0.insert(entry.fingerprint(), note);
+    let mut conflicts = vec![];
+    storage::update(&notes_path, |content| {
+        let mut notes = Notes::parse(content, &notes_path)?;
+        conflicts = notes.merge(added);
+        Ok(notes.to_document())
+    })?;
+    if !conflicts.is_empty() {
+        eprintln!(
+            "Transaction {} already has a different comment, keeping the existing one",
+            entry.id
+        );
+    }
+    Ok(())
+}
+
+// Merges notes into the notes file and returns its path. Comments of transactions which already
+// have a different one are left out.
+pub(crate) fn store(imported: Notes) -> Result<PathBuf> {
+    let notes_path = required_notes_path()?;
+    let mut conflicts = vec![];
+    storage::update(&notes_path, |content| {
+        let mut notes = Notes::parse(content, &notes_path)?;
+        conflicts = notes.merge(imported);
+        Ok(notes.to_document())
+    })?;
+    for fingerprint in conflicts {
+        eprintln!("Conflicting comments for transaction `{fingerprint}`, keeping the existing one");
+    }
+    Ok(notes_path)
+}
+
+// Sets or, when there is none, removes the comment of a transaction, replacing the comment it had.
+// Notes left without a comment, tags or pin are dropped.
+fn set_comment(entry: &HistoryEntry, comment: Option<String>) -> Result<()> {
+    let notes_path = required_notes_path()?;
+    storage::update(&notes_path, |content| {
+        let mut notes = Notes::parse(content, &notes_path)?;
+        let fingerprint = entry.fingerprint();
+        let note = notes
+            .0
+            .entry(fingerprint.clone())
+            .or_insert_with(|| Note::new(entry));
+        note.comment = comment;
+        if note.comment.is_none() && note.tags.is_empty() && !note.pinned {
+            notes.0.remove(&fingerprint);
+        }
+        Ok(notes.to_document())
+    })
+}
+
+// Comments are kept in the notes file, so they stay with a transaction when logs are rotated and
+// can be exported along with the other notes.
+pub fn annotate(arguments: Option<Vec<String>>) -> Result<()> {
+    let Some([transaction, words @ ..]) = arguments.as_deref() else {
+        return Err(Error::Usage(
+            "`annotate` requires a transaction ID and a comment".to_string(),
+        ));
+    };
+    if words.is_empty() {
+        return Err(Error::Usage(
+            "`annotate` requires a transaction ID and a comment, `\"\"` to remove it".to_string(),
+        ));
+    }
+    let entries = matching_entries(Some(vec![transaction.clone()]))?;
+    let Some(entry) = entries.last() else {
+        println!("No matching transaction");
+        return Ok(());
+    };
+    let comment = words.join(" ");
+    if comment.trim().is_empty() {
+        set_comment(entry, None)?;
+        println!("Removed the comment of transaction {}", entry.id);
+    } else {
+        set_comment(entry, Some(comment))?;
+        println!("Annotated transaction {}", entry.id);
+    }
+    Ok(())
+}
+
+fn export(path: Option<&String>) -> Result<()> {
+    let document = Notes::load()?.to_document();
+    let Some(path) = path else {
+        print!("{document}");
+        return Ok(());
+    };
+    storage::replace(Path::new(path), |temporary| {
+        fs::write(temporary, &document).map_err(|source| Error::OutputFile {
+            path: path.to_string(),
+            source,
+        })
+    })
+}
+
+fn import(paths: &[String]) -> Result<()> {
+    let mut imported = Notes::default();
+    for path in paths {
+        let content = fs::read_to_string(path).map_err(|source| Error::InputFile {
+            path: path.clone(),
+            source,
+        })?;
+        for fingerprint in imported.merge(Notes::parse(&content, Path::new(path))?) {
+            eprintln!("Conflicting comments for transaction `{fingerprint}` in `{path}`, keeping the first one");
+        }
+    }
+    let count = imported.0.len();
+    let notes_path = store(imported)?;
+
+    let known: Vec<String> = history_entries()?.iter().map(|e| e.fingerprint()).collect();
+    let notes = Notes::load()?;
+    let unknown = notes.0.keys().filter(|f| !known.contains(f)).count();
+    println!("Imported {count} notes into `{}`", notes_path.display());
+    if unknown > 0 {
+        println!("{unknown} notes are for transactions not in the history of this host");
+    }
+    Ok(())
+}
+
+pub fn notes(arguments: Option<Vec<String>>) -> Result<()> {
+    match arguments.as_deref() {
+        Some([command, path @ ..]) if command == "export" && path.len() <= 1 => {
+            export(path.first())
+        }
+        Some([command, paths @ ..]) if command == "import" && !paths.is_empty() => import(paths),
+        _ => Err(Error::Usage(
+            "`notes` requires `export [FILE]` or `import FILE...`".to_string(),
+        )),
+    }
+}
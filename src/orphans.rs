@@ -0,0 +1,169 @@
+use crate::error::Result;
+use crate::history::{history_entries, Filters, HistoryEntry, LIST_DATE_FORMAT};
+use chrono::NaiveDateTime;
+use std::collections::HashMap;
+use stybulate::{Cell, Headers, Style, Table};
+
+const AUTOREMOVE_FLAGS: [&str; 2] = ["--autoremove", "--auto-remove"];
+const AUTOREMOVE_OPERATIONS: [&str; 2] = ["autoremove", "autopurge"];
+const HEADERS: [&str; 5] = [
+    "Package",
+    "Removed in",
+    "Date and time",
+    "Reason",
+    "Installed again in",
+];
+const INSTALLING_ACTIONS: [&str; 3] = ["Install", "Reinstall", "Downgrade"];
+// Options of apt and apt-get taking a value as the next argument.
+const OPTIONS_WITH_VALUES: [&str; 7] = [
+    "-c",
+    "--config-file",
+    "-o",
+    "--option",
+    "-t",
+    "--target-release",
+    "--default-release",
+];
+const REMOVING_ACTIONS: [&str; 2] = ["Remove", "Purge"];
+// Unattended upgrades remove unused dependencies when `Remove-Unused-Dependencies` is set.
+const UNATTENDED_FRONTEND: &str = "unattended-upgrades";
+
+// Why a package was removed, told from the command line of the transaction removing it.
+#[derive(Clone, Copy, Eq, Hash, PartialEq)]
+enum Reason {
+    // Removed as no longer needed, by `autoremove` or `--autoremove`.
+    Autoremove,
+    // Named on the command line.
+    Explicit,
+    // Removed along with the named packages, e.g. as it depended on them.
+    Dependent,
+}
+
+impl Reason {
+    fn label(&self) -> &'static str {
+        match self {
+            Reason::Autoremove => "autoremove",
+            Reason::Explicit => "explicit",
+            Reason::Dependent => "along with others",
+        }
+    }
+}
+
+// The operation and the package names given on a command line without the front-end, such as
+// `remove --autoremove foo:amd64`, along with whether unused dependencies were removed too.
+fn parse_arguments(arguments: &str) -> (Option<String>, Vec<String>, bool) {
+    let mut operation = None;
+    let mut names = vec![];
+    let mut autoremove = false;
+    let mut terms = arguments.split_whitespace();
+    while let Some(term) = terms.next() {
+        if OPTIONS_WITH_VALUES.contains(&term) {
+            terms.next();
+        } else if AUTOREMOVE_FLAGS.contains(&term) {
+            autoremove = true;
+        } else if term.starts_with('-') {
+            continue;
+        } else if operation.is_none() {
+            operation = Some(term.to_string());
+        } else {
+            // Names can be qualified with an architecture or version, or given with the suffix
+            // apt uses to remove packages with `install`.
+            let name = term
+                .split([':', '=', '/'])
+                .next()
+                .unwrap_or(term)
+                .trim_end_matches(['-', '+']);
+            names.push(name.to_string());
+        }
+    }
+    (operation, names, autoremove)
+}
+
+fn removal_reason(entry: &HistoryEntry, package: &str) -> Reason {
+    if entry.frontend == UNATTENDED_FRONTEND {
+        return Reason::Autoremove;
+    }
+    let (operation, names, autoremove) = parse_arguments(&entry.full_command_line);
+    if operation.is_some_and(|o| AUTOREMOVE_OPERATIONS.contains(&o.as_str())) {
+        Reason::Autoremove
+    } else if names.iter().any(|n| n == package) {
+        Reason::Explicit
+    } else if autoremove {
+        Reason::Autoremove
+    } else {
+        Reason::Dependent
+    }
+}
+
+fn packages<'a>(entry: &'a HistoryEntry, actions: &[&str]) -> Vec<(&'a String, String)> {
+    entry
+        .affected
+        .iter()
+        .filter(|(action, _)| actions.contains(&action.as_str()))
+        .flat_map(|(_, pkg_map)| pkg_map.iter())
+        .flat_map(|(arch, pkgs)| pkgs.iter().map(move |p| (p, format!("{p}:{arch}"))))
+        .collect()
+}
+
+// Every removal with the reason for it, so that packages autoremove took are told apart from
+// the ones removed on purpose, along with the transaction installing them again if there was
+// one.
+pub fn orphans(filters: &Filters) -> Result<()> {
+    let entries = history_entries()?;
+    let mut reinstalls: HashMap<String, Vec<(u32, NaiveDateTime)>> = HashMap::new();
+    for entry in &entries {
+        for (_, package) in packages(entry, &INSTALLING_ACTIONS) {
+            reinstalls
+                .entry(package)
+                .or_default()
+                .push((entry.id, entry.start_date));
+        }
+    }
+
+    let mut rows: Vec<Vec<Cell>> = vec![];
+    let mut counts: HashMap<Reason, usize> = HashMap::new();
+    let mut wanted = 0;
+    for entry in filters.apply(entries)? {
+        let mut removed = packages(&entry, &REMOVING_ACTIONS);
+        removed.sort_by(|a, b| a.1.cmp(&b.1));
+        for (name, package) in removed {
+            let reason = removal_reason(&entry, name);
+            *counts.entry(reason).or_default() += 1;
+            let installed_again = reinstalls
+                .get(&package)
+                .and_then(|r| r.iter().find(|(_, date)| *date > entry.start_date))
+                .map(|(id, _)| *id);
+            if reason == Reason::Autoremove && installed_again.is_some() {
+                wanted += 1;
+            }
+            rows.push(vec![
+                Cell::from(&package),
+                Cell::Int(entry.id as i32),
+                Cell::from(&entry.start_date.format(LIST_DATE_FORMAT).to_string()),
+                Cell::from(reason.label()),
+                Cell::from(&installed_again.map(|id| id.to_string()).unwrap_or_default()),
+            ]);
+        }
+    }
+
+    if rows.is_empty() {
+        println!("No recorded removals");
+        return Ok(());
+    }
+    let headers = Headers::from(HEADERS.to_vec());
+    println!(
+        "{}",
+        Table::new(Style::Presto, rows, Some(headers)).tabulate()
+    );
+    let count = |reason: Reason| counts.get(&reason).copied().unwrap_or_default();
+    println!(
+        "{} packages removed by autoremove, {} explicitly and {} along with others",
+        count(Reason::Autoremove),
+        count(Reason::Explicit),
+        count(Reason::Dependent)
+    );
+    if wanted > 0 {
+        println!("{wanted} packages removed by autoremove were installed again later");
+    }
+    Ok(())
+}
@@ -0,0 +1,280 @@
+use crate::error::{Error, Result};
+use crate::history::{history_entries, HistoryEntry, LIST_DATE_FORMAT};
+use std::collections::BTreeMap;
+use stybulate::{Cell, Headers, Style, Table};
+
+const BLAME_HEADERS: [&str; 6] = [
+    "Package",
+    "Last changed in",
+    "Date and time",
+    "Action",
+    "Version",
+    "Requested by",
+];
+const HEADERS: [&str; 6] = [
+    "Package",
+    "Installed in",
+    "Date and time",
+    "Requested by",
+    "Reason",
+    "Removed in",
+];
+const LIFECYCLE_HEADERS: [&str; 5] = [
+    "Transaction",
+    "Date and time",
+    "Action",
+    "Version",
+    "Requested by",
+];
+const INSTALL_ACTION: &str = "Install";
+const REMOVING_ACTIONS: [&str; 2] = ["Remove", "Purge"];
+// Transactions run without `sudo` have no `Requested-By` field.
+const ROOT_USER: &str = "root";
+const UNATTENDED_FRONTEND: &str = "unattended-upgrades";
+
+struct Installation {
+    date: String,
+    id: u32,
+    reason: &'static str,
+    // Transaction removing the package after it was installed, if any.
+    removed_in: Option<u32>,
+    requested_by: String,
+}
+
+fn installation(entry: &HistoryEntry, package: &str) -> Installation {
+    let reason = if entry.frontend == UNATTENDED_FRONTEND {
+        "unattended"
+    } else if entry.is_automatic(INSTALL_ACTION, package) {
+        "dependency"
+    } else {
+        "requested"
+    };
+    Installation {
+        date: entry.start_date.format(LIST_DATE_FORMAT).to_string(),
+        id: entry.id,
+        reason,
+        removed_in: None,
+        requested_by: entry.requested_by.clone().unwrap_or(ROOT_USER.to_string()),
+    }
+}
+
+// Change of a package by any action.
+struct Change {
+    action: String,
+    date: String,
+    id: u32,
+    requested_by: String,
+    version: String,
+}
+
+fn package_name(package: &str) -> &str {
+    package.split(':').next().unwrap_or(package)
+}
+
+// Whether a `name:arch` package is the one asked for, either by name only or with the
+// architecture.
+fn is_queried(package: &str, name: &str, query: &str) -> bool {
+    query == package || query == name
+}
+
+// Last installation of each `name:arch` package matching the query, along with the removal
+// following it if there was one.
+fn installations(queries: &[String]) -> Result<BTreeMap<String, Installation>> {
+    let mut installations: BTreeMap<String, Installation> = BTreeMap::new();
+    for entry in history_entries()? {
+        for action in entry.sorted_actions() {
+            for (arch, pkgs) in entry.affected[action.as_str()].iter() {
+                for pkg in pkgs {
+                    let package = format!("{pkg}:{arch}");
+                    if !queries.iter().any(|q| is_queried(&package, pkg, q)) {
+                        continue;
+                    }
+                    if action == INSTALL_ACTION {
+                        installations.insert(package.clone(), installation(&entry, &package));
+                    } else if REMOVING_ACTIONS.contains(&action.as_str()) {
+                        if let Some(installation) = installations.get_mut(&package) {
+                            installation.removed_in.get_or_insert(entry.id);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    Ok(installations)
+}
+
+fn change(entry: &HistoryEntry, action: &str, package: &str) -> Change {
+    Change {
+        action: action.to_string(),
+        date: entry.start_date.format(LIST_DATE_FORMAT).to_string(),
+        id: entry.id,
+        requested_by: entry.requested_by.clone().unwrap_or(ROOT_USER.to_string()),
+        version: entry.package_versions(action, package).join(" -> "),
+    }
+}
+
+// All changes of each `name:arch` package matching the query, from the oldest to the most recent.
+fn changes(queries: &[String]) -> Result<BTreeMap<String, Vec<Change>>> {
+    let mut changes: BTreeMap<String, Vec<Change>> = BTreeMap::new();
+    for entry in history_entries()? {
+        for action in entry.sorted_actions() {
+            for (arch, pkgs) in entry.affected[action.as_str()].iter() {
+                for pkg in pkgs {
+                    let package = format!("{pkg}:{arch}");
+                    if !queries.iter().any(|q| is_queried(&package, pkg, q)) {
+                        continue;
+                    }
+                    let change = change(&entry, action, &package);
+                    changes.entry(package).or_default().push(change);
+                }
+            }
+        }
+    }
+    Ok(changes)
+}
+
+// Last change of each `name:arch` package matching the query, including removals.
+fn last_changes(queries: &[String]) -> Result<BTreeMap<String, Change>> {
+    let mut changes = BTreeMap::new();
+    for entry in history_entries()? {
+        // Follows the order of actions, so that the last change is the same on every run.
+        for action in entry.sorted_actions() {
+            for (arch, pkgs) in entry.affected[action.as_str()].iter() {
+                for pkg in pkgs {
+                    let package = format!("{pkg}:{arch}");
+                    if !queries.iter().any(|q| is_queried(&package, pkg, q)) {
+                        continue;
+                    }
+                    let change = change(&entry, action, &package);
+                    changes.insert(package, change);
+                }
+            }
+        }
+    }
+    Ok(changes)
+}
+
+pub fn blame(query: Option<Vec<String>>) -> Result<()> {
+    let Some(queries) = query else {
+        return Err(Error::Usage(
+            "`blame` requires at least one package name".to_string(),
+        ));
+    };
+    let changes = last_changes(&queries)?;
+
+    for query in &queries {
+        if !changes
+            .keys()
+            .any(|p| is_queried(p, package_name(p), query))
+        {
+            println!("No recorded change of `{query}`");
+        }
+    }
+    if changes.is_empty() {
+        return Ok(());
+    }
+
+    let rows: Vec<Vec<Cell>> = changes
+        .iter()
+        .map(|(package, c)| {
+            vec![
+                Cell::from(package),
+                Cell::Int(c.id as i32),
+                Cell::from(&c.date),
+                Cell::from(&c.action),
+                Cell::from(&c.version),
+                Cell::from(&c.requested_by),
+            ]
+        })
+        .collect();
+    let headers = Headers::from(BLAME_HEADERS.to_vec());
+    println!(
+        "{}",
+        Table::new(Style::Presto, rows, Some(headers)).tabulate()
+    );
+    Ok(())
+}
+
+// Every install, upgrade, removal and purge of a package in chronological order, with a table
+// per architecture the package was changed for.
+pub fn package(query: Option<Vec<String>>) -> Result<()> {
+    let queries = match query {
+        Some(queries) if queries.len() == 1 => queries,
+        _ => {
+            return Err(Error::Usage(
+                "`package` requires a single package name".to_string(),
+            ))
+        }
+    };
+    let changes = changes(&queries)?;
+    if changes.is_empty() {
+        println!("No recorded change of `{}`", queries[0]);
+        return Ok(());
+    }
+
+    for (index, (package, package_changes)) in changes.iter().enumerate() {
+        if index > 0 {
+            println!();
+        }
+        println!("{package}");
+        let rows: Vec<Vec<Cell>> = package_changes
+            .iter()
+            .map(|c| {
+                vec![
+                    Cell::Int(c.id as i32),
+                    Cell::from(&c.date),
+                    Cell::from(&c.action),
+                    Cell::from(&c.version),
+                    Cell::from(&c.requested_by),
+                ]
+            })
+            .collect();
+        let headers = Headers::from(LIFECYCLE_HEADERS.to_vec());
+        println!(
+            "{}",
+            Table::new(Style::Presto, rows, Some(headers)).tabulate()
+        );
+    }
+    Ok(())
+}
+
+pub fn who_installed(query: Option<Vec<String>>) -> Result<()> {
+    let Some(queries) = query else {
+        return Err(Error::Usage(
+            "`who-installed` requires at least one package name".to_string(),
+        ));
+    };
+    let installations = installations(&queries)?;
+
+    for query in &queries {
+        if !installations
+            .keys()
+            .any(|p| is_queried(p, package_name(p), query))
+        {
+            println!("No recorded installation of `{query}`");
+        }
+    }
+    if installations.is_empty() {
+        return Ok(());
+    }
+
+    let rows: Vec<Vec<Cell>> = installations
+        .iter()
+        .map(|(package, i)| {
+            vec![
+                Cell::from(package),
+                Cell::Int(i.id as i32),
+                Cell::from(&i.date),
+                Cell::from(&i.requested_by),
+                Cell::from(i.reason),
+                Cell::from(&i.removed_in.map(|id| id.to_string()).unwrap_or_default()),
+            ]
+        })
+        .collect();
+    let headers = Headers::from(HEADERS.to_vec());
+    println!(
+        "{}",
+        Table::new(Style::Presto, rows, Some(headers)).tabulate()
+    );
+    Ok(())
+}
@@ -0,0 +1,113 @@
+use crate::terminal::{self, PAGED_VARIABLE};
+use std::env;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::process::{self, Child, Command, Stdio};
+
+const DEFAULT_PAGER: &str = "less -R";
+const READ_BUFFER_LEN: usize = 8192;
+const PAGER_VARIABLE: &str = "PAGER";
+
+fn pager_command() -> Option<String> {
+    let pager = env::var(PAGER_VARIABLE).unwrap_or_else(|_| DEFAULT_PAGER.to_string());
+    let pager = pager.trim();
+    (!pager.is_empty() && pager != "cat").then(|| pager.to_string())
+}
+
+// Whether output should go through a pager, which is the case for a terminal unless this process
+// is already the one being paged.
+pub(crate) fn should_page() -> bool {
+    env::var_os(PAGED_VARIABLE).is_none()
+        && terminal::stdout_is_terminal()
+        && pager_command().is_some()
+}
+
+// Whether a program can be found, either by its path or in `PATH`.
+fn is_installed(program: &str) -> bool {
+    if program.contains('/') {
+        return Path::new(program).is_file();
+    }
+    env::var_os("PATH")
+        .is_some_and(|path| env::split_paths(&path).any(|dir| dir.join(program).is_file()))
+}
+
+// The pager reading from a pipe, none if it is not installed, in which case output is written
+// without it. The pager command is run by the shell, so its program is the first word which is
+// not a variable assignment.
+fn spawn_pager() -> Option<Child> {
+    let pager = pager_command()?;
+    let program = pager.split_whitespace().find(|w| !w.contains('='))?;
+    if !is_installed(program) {
+        return None;
+    }
+    Command::new("sh")
+        .args(["-c", &pager])
+        .stdin(Stdio::piped())
+        .spawn()
+        .ok()
+}
+
+// Writes the start of the output and then the rest as it is read. The rest is still read after
+// writing fails, e.g. when the user quits the pager early, so that the paged process is not
+// stopped by a broken pipe.
+fn forward(start: &[u8], mut rest: impl Read, mut out: impl Write) {
+    let written = out
+        .write_all(start)
+        .and_then(|_| io::copy(&mut rest, &mut out))
+        .and_then(|_| out.flush());
+    if written.is_err() {
+        let _ = io::copy(&mut rest, &mut io::sink());
+    }
+}
+
+// Runs this program again with the same arguments and shows its output through the pager if it
+// does not fit on the screen, exiting with the status of the paged process. Output is passed on
+// as it is written once it fills the screen. Returns if this program cannot be run again, so that
+// output is written without paging.
+pub(crate) fn page() {
+    let Ok(executable) = env::current_exe() else {
+        return;
+    };
+    let mut command = Command::new(executable);
+    command
+        .args(env::args_os().skip(1))
+        .env(PAGED_VARIABLE, "1")
+        .stdout(Stdio::piped());
+    // The paged process cannot query the terminal size through the pipe.
+    if let Some(width) = terminal::width() {
+        command.env("COLUMNS", width.to_string());
+    }
+    let Ok(mut child) = command.spawn() else {
+        return;
+    };
+    let mut output = child
+        .stdout
+        .take()
+        .expect("error getting output of paged process");
+
+    let height = terminal::height();
+    let mut start = vec![];
+    let mut buffer = [0; READ_BUFFER_LEN];
+    let fits = loop {
+        // A read error ends the output like the end of it does.
+        let read = output.read(&mut buffer).unwrap_or(0);
+        if read == 0 {
+            break true;
+        }
+        start.extend_from_slice(&buffer[..read]);
+        let lines = start.iter().filter(|b| **b == b'\n').count();
+        if height.is_some_and(|height| lines >= height) {
+            break false;
+        }
+    };
+    let mut pager = if fits { None } else { spawn_pager() };
+    match pager.as_mut().and_then(|p| p.stdin.take()) {
+        Some(input) => forward(&start, output, input),
+        None => forward(&start, output, io::stdout().lock()),
+    }
+    if let Some(mut pager) = pager {
+        let _ = pager.wait();
+    }
+    let code = child.wait().ok().and_then(|s| s.code()).unwrap_or(1);
+    process::exit(code);
+}
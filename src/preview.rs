@@ -0,0 +1,92 @@
+use crate::apt::{simulated_upgrade, SimulatedChange};
+use crate::error::Result;
+use crate::history::{history_entries, HistoryEntry};
+use std::collections::BTreeMap;
+use stybulate::{Cell, Headers, Style, Table};
+
+const UPGRADE_ACTION: &str = "Upgrade";
+
+// Change of a package as shown in the table, e.g. `Upgrade 1.0-1 -> 1.0-2`.
+fn describe(action: &str, versions: &[&str]) -> String {
+    if versions.is_empty() {
+        action.to_string()
+    } else {
+        format!("{action} {}", versions.join(" -> "))
+    }
+}
+
+fn simulated(change: &SimulatedChange) -> String {
+    let versions: Vec<&str> = [&change.from, &change.to]
+        .iter()
+        .filter_map(|v| v.as_deref())
+        .collect();
+    describe(change.action, &versions)
+}
+
+fn recorded(entry: &HistoryEntry) -> BTreeMap<String, String> {
+    let mut changes = BTreeMap::new();
+    for (action, pkg_map) in entry.affected.iter() {
+        for (arch, pkgs) in pkg_map.iter() {
+            for pkg in pkgs {
+                let package = format!("{pkg}:{arch}");
+                let change = describe(action, &entry.package_versions(action, &package));
+                changes.insert(package, change);
+            }
+        }
+    }
+    changes
+}
+
+// Changes the next upgrade would make next to those of the last transaction upgrading packages,
+// or the last one at all if none did. Packages changed by both come first as they are the ones
+// most likely to act up the same way again.
+pub fn preview_diff() -> Result<()> {
+    let next: BTreeMap<String, String> = simulated_upgrade()?
+        .iter()
+        .map(|c| (c.package.clone(), simulated(c)))
+        .collect();
+    let entries = history_entries()?;
+    let last = entries
+        .iter()
+        .rev()
+        .find(|e| e.affected.contains_key(UPGRADE_ACTION))
+        .or(entries.last());
+    let Some(last) = last else {
+        println!("No transactions to compare the next upgrade with");
+        return Ok(());
+    };
+    let previous = recorded(last);
+    if next.is_empty() {
+        println!("No packages would be upgraded");
+        return Ok(());
+    }
+
+    let mut packages: Vec<&String> = next.keys().chain(previous.keys()).collect();
+    packages.sort_by_key(|p| (!(next.contains_key(*p) && previous.contains_key(*p)), *p));
+    packages.dedup();
+    let rows: Vec<Vec<Cell>> = packages
+        .iter()
+        .map(|p| {
+            vec![
+                Cell::from(p),
+                Cell::from(previous.get(*p).map(|c| c.as_str()).unwrap_or("")),
+                Cell::from(next.get(*p).map(|c| c.as_str()).unwrap_or("")),
+            ]
+        })
+        .collect();
+    let last_header = format!("Transaction {}", last.id);
+    let headers = Headers::from(vec!["Package", &last_header, "Next upgrade"]);
+    println!(
+        "{}",
+        Table::new(Style::Presto, rows, Some(headers)).tabulate()
+    );
+
+    let both = next.keys().filter(|p| previous.contains_key(*p)).count();
+    println!(
+        "{both} changed by both, {} only by the next upgrade, {} only by transaction {}",
+        next.len() - both,
+        previous.len() - both,
+        last.id
+    );
+    Ok(())
+}
@@ -0,0 +1,46 @@
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+static REQUIRE_COMPLETE: AtomicBool = AtomicBool::new(false);
+// Data sources skipped as they are not readable with the current privileges.
+static UNAVAILABLE: Mutex<Vec<String>> = Mutex::new(vec![]);
+
+pub fn init(require_complete: bool) {
+    REQUIRE_COMPLETE.store(require_complete, Ordering::Relaxed);
+}
+
+// Whether an error reading a data source is due to missing privileges, in which case the source
+// is skipped and mentioned in the notice at the end, or rejected if complete data is required.
+pub(crate) fn is_unavailable(path: &str, error: &io::Error) -> bool {
+    if error.kind() != io::ErrorKind::PermissionDenied {
+        return false;
+    }
+    if REQUIRE_COMPLETE.load(Ordering::Relaxed) {
+        panic!(
+            "`{path}` is not readable with the current privileges and complete data is required"
+        );
+    }
+    let mut unavailable = UNAVAILABLE
+        .lock()
+        .expect("error locking unavailable sources");
+    if !unavailable.iter().any(|p| p == path) {
+        unavailable.push(path.to_string());
+    }
+    true
+}
+
+// A single notice for all skipped sources, on stderr to keep it out of parsed output.
+pub fn notify() {
+    let unavailable = UNAVAILABLE
+        .lock()
+        .expect("error locking unavailable sources");
+    if unavailable.is_empty() {
+        return;
+    }
+    eprintln!(
+        "Note: results are incomplete as these are not readable with the current privileges: {}. \
+         Run as root or as a member of the `adm` group for complete data.",
+        unavailable.join(", ")
+    );
+}
@@ -0,0 +1,192 @@
+use crate::error::{Error, Result};
+use crate::export::archive_document;
+use crate::history::{history_entries, open_log, AlteredCount, HistoryEntry};
+use crate::imports;
+use crate::lock;
+use crate::notes::Notes;
+use crate::storage;
+use chrono::{Duration, Local};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io::{Read, Write};
+use std::os::unix::fs::{chown, MetadataExt, PermissionsExt};
+use std::path::Path;
+
+// Summaries of pruned transactions, one JSON line per transaction as in `--output jsonl`.
+pub(crate) const INDEX_PATH: &str = "/var/lib/apt-history/pruned";
+
+fn read_log(path: &str) -> Result<Vec<u8>> {
+    let mut content = vec![];
+    open_log(path)
+        .and_then(|mut log| log.read_to_end(&mut content))
+        .map_err(|source| Error::LogFile {
+            path: path.to_string(),
+            source,
+        })?;
+    Ok(content)
+}
+
+// Content of a log with only the given entries, which are the ones it was parsed into. Entries
+// are written as apt does, each after an empty line.
+fn compact(content: &[u8], entries: &[&HistoryEntry]) -> Vec<u8> {
+    let mut compacted = vec![];
+    for entry in entries {
+        let range = entry.provenance.start as usize..entry.provenance.end as usize;
+        compacted.push(b'\n');
+        compacted.extend_from_slice(&content[range]);
+    }
+    compacted
+}
+
+// Replaces a log with the given content, compressed again for rotated logs, keeping its owner and
+// permissions. The log is left as it is if it changed since it was parsed, as apt could have
+// added a transaction in the meantime.
+fn rewrite(path: &str, parsed: &[u8], content: &[u8]) -> Result<()> {
+    let metadata = fs::metadata(path).map_err(|source| Error::LogFile {
+        path: path.to_string(),
+        source,
+    })?;
+    let log_error = |source| Error::LogFile {
+        path: path.to_string(),
+        source,
+    };
+    storage::replace(Path::new(path), |temporary| {
+        if read_log(path)? != parsed {
+            return Err(Error::Usage(format!(
+                "`{path}` changed while pruning, run `prune` again"
+            )));
+        }
+        let written = fs::File::create(temporary).and_then(|mut file| {
+            if path.ends_with(".gz") {
+                let mut encoder = GzEncoder::new(file, Compression::default());
+                encoder.write_all(content)?;
+                encoder.finish().map(|_| ())
+            } else {
+                file.write_all(content)
+            }
+        });
+        written
+            .and_then(|_| {
+                fs::set_permissions(
+                    temporary,
+                    fs::Permissions::from_mode(metadata.mode() & 0o7777),
+                )
+            })
+            .and_then(|_| chown(temporary, Some(metadata.uid()), Some(metadata.gid())))
+            .map_err(log_error)
+    })
+}
+
+// Appends summaries of transactions to the index of pruned transactions.
+fn record(entries: &[&HistoryEntry]) -> Result<()> {
+    storage::update(Path::new(INDEX_PATH), |content| {
+        let mut content = content.to_string();
+        for entry in entries {
+            let mut full = (*entry).clone();
+            full.command_line = entry.full_command_line.clone();
+            content.push_str(&format!(
+                "{}\n",
+                full.to_summary_json(AlteredCount::Packages)
+            ));
+        }
+        Ok(content)
+    })
+}
+
+// Drops transactions which ended more than the given number of days ago from the history logs,
+// to keep logs small on hosts with little space. Pruned transactions are only printed unless
+// `run` is set, in which case their summaries are kept in `INDEX_PATH` and the transactions
+// themselves are written to an archive first if a path is given.
+pub fn prune(keep_days: Option<u32>, archive: Option<&str>, run: bool) -> Result<()> {
+    let Some(keep_days) = keep_days else {
+        return Err(Error::Usage(
+            "`prune` requires `--keep-days` with the number of days to keep".to_string(),
+        ));
+    };
+    let cutoff = Local::now().naive_local()
+        - Duration::try_days(keep_days.into()).expect("invalid day count");
+    let entries: Vec<HistoryEntry> = history_entries()?
+        .into_iter()
+        .filter(|e| !imports::is_imported(e))
+        .collect();
+    let pruned: Vec<&HistoryEntry> = entries
+        .iter()
+        .filter(|e| e.finished && e.end_date < cutoff)
+        .collect();
+    let pruned_ids: HashSet<u32> = pruned.iter().map(|e| e.id).collect();
+    if pruned.is_empty() {
+        println!("No transactions ended more than {keep_days} days ago");
+        return Ok(());
+    }
+
+    let mut by_file: BTreeMap<&str, Vec<&HistoryEntry>> = BTreeMap::new();
+    for entry in &entries {
+        by_file
+            .entry(entry.provenance.file.as_str())
+            .or_default()
+            .push(entry);
+    }
+    if !run {
+        for entry in &pruned {
+            println!(
+                "{}  {}  {}",
+                entry.id, entry.start_date, entry.full_command_line
+            );
+        }
+        println!(
+            "Would prune {} transactions, run with --run to rewrite the logs",
+            pruned.len()
+        );
+        return Ok(());
+    }
+    if let Some(holder) = lock::frontend_lock_holder() {
+        return Err(Error::Usage(format!(
+            "apt is running as process {holder}, prune once it has finished"
+        )));
+    }
+
+    // Logs are only rewritten if they are made of their entries alone.
+    let mut rewrites = vec![];
+    for (file, mut file_entries) in by_file {
+        if !file_entries.iter().any(|e| pruned_ids.contains(&e.id)) {
+            continue;
+        }
+        file_entries.sort_by_key(|e| e.provenance.start);
+        let content = read_log(file)?;
+        if compact(&content, &file_entries) != content {
+            return Err(Error::Usage(format!(
+                "`{file}` has content besides its transactions, it is left as it is"
+            )));
+        }
+        let kept: Vec<&HistoryEntry> = file_entries
+            .into_iter()
+            .filter(|e| !pruned_ids.contains(&e.id))
+            .collect();
+        rewrites.push((file, content, kept));
+    }
+
+    if let Some(path) = archive {
+        let archived: Vec<HistoryEntry> = pruned.iter().map(|e| (*e).clone()).collect();
+        let document = archive_document(&archived, &Notes::load()?);
+        storage::replace(Path::new(path), |temporary| {
+            fs::write(temporary, &document).map_err(|source| Error::OutputFile {
+                path: path.to_string(),
+                source,
+            })
+        })?;
+    }
+    record(&pruned)?;
+    for (file, content, kept) in rewrites {
+        let compacted = compact(&content, &kept);
+        rewrite(file, &content, &compacted)?;
+        let removed = content.len() - compacted.len();
+        println!("Removed {removed} bytes from {file}");
+    }
+    println!(
+        "Pruned {} transactions, their summaries are kept in {INDEX_PATH}",
+        pruned.len()
+    );
+    Ok(())
+}
@@ -0,0 +1,57 @@
+use crate::error::{Error, Result};
+use crate::history::{matching_entries, HistoryEntry};
+use crate::undo::{apt_commands, check_availability, print_or_run, Origin, PackageTargets};
+
+// Changes a transaction made: packages are brought to the version it left them at and removed
+// or purged ones removed again. Packages installed as dependencies are left for apt to pull in,
+// so that they are marked as automatically installed again.
+fn replay(entry: &HistoryEntry) -> PackageTargets {
+    let mut targets = PackageTargets::new();
+    for action in entry.sorted_actions() {
+        for (arch, pkgs) in entry.affected[action.as_str()].iter() {
+            for pkg in pkgs {
+                let package = format!("{pkg}:{arch}");
+                if entry.is_automatic(action, &package) {
+                    continue;
+                }
+                let versions = entry.package_versions(action, &package);
+                let target = match action.as_str() {
+                    "Install" | "Reinstall" | "Upgrade" | "Downgrade" => {
+                        Some(versions.last().copied().unwrap_or_default())
+                    }
+                    "Remove" | "Purge" => None,
+                    _ => continue,
+                };
+                targets.insert(package, target.map(|v| v.to_string()));
+            }
+        }
+    }
+    targets
+}
+
+pub fn redo(query: Option<Vec<String>>, run: bool) -> Result<()> {
+    let entries = match query.as_deref() {
+        Some([_]) => matching_entries(query)?,
+        _ => {
+            return Err(Error::Usage(
+                "`redo` requires a single transaction ID".to_string(),
+            ))
+        }
+    };
+    let Some(entry) = entries.last() else {
+        println!("No matching transaction");
+        return Ok(());
+    };
+    let targets = replay(entry);
+    if targets.is_empty() {
+        println!("Transaction {} has no changes to redo", entry.id);
+        return Ok(());
+    }
+    check_availability(&targets);
+    let origin = Origin {
+        entry,
+        relation: "Redo of",
+        tag: "redo",
+    };
+    print_or_run(&apt_commands(&targets)?, run, &origin)
+}
@@ -0,0 +1,59 @@
+use crate::dpkg::status_packages;
+use crate::history::HistoryEntry;
+use std::collections::HashMap;
+
+pub(crate) const INSTALL_ACTION: &str = "Install";
+const REMOVING_ACTIONS: [&str; 2] = ["Remove", "Purge"];
+
+// A package removed in favor of another one installed in the same transaction.
+pub(crate) struct Rename {
+    pub(crate) action: String,
+    pub(crate) from: String,
+    pub(crate) to: String,
+}
+
+// Packages replaced by each installed package as declared by their `Replaces` fields. Only
+// packages that are still installed are known, their metadata is not kept after removal.
+pub(crate) fn replacements() -> HashMap<String, Vec<String>> {
+    status_packages()
+        .into_iter()
+        .filter(|p| p.is_installed())
+        .map(|p| (p.name.clone(), p.replaces()))
+        .filter(|(_, replaces)| !replaces.is_empty())
+        .collect()
+}
+
+// Removals and installs of an entry that make up renames, packages are in `name:arch` form.
+pub(crate) fn renames(
+    entry: &HistoryEntry,
+    replacements: &HashMap<String, Vec<String>>,
+) -> Vec<Rename> {
+    let Some(installed) = entry.affected.get(INSTALL_ACTION) else {
+        return vec![];
+    };
+
+    let mut renames = vec![];
+    for action in REMOVING_ACTIONS {
+        let Some(removed) = entry.affected.get(action) else {
+            continue;
+        };
+        for (arch, pkgs) in installed {
+            for pkg in pkgs {
+                let Some(replaced) = replacements.get(pkg) else {
+                    continue;
+                };
+                for (removed_arch, removed_pkgs) in removed {
+                    for old in removed_pkgs.iter().filter(|p| replaced.contains(p)) {
+                        renames.push(Rename {
+                            action: action.to_string(),
+                            from: format!("{old}:{removed_arch}"),
+                            to: format!("{pkg}:{arch}"),
+                        });
+                    }
+                }
+            }
+        }
+    }
+    renames.sort_by(|a, b| a.from.cmp(&b.from));
+    renames
+}
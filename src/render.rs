@@ -0,0 +1,245 @@
+use crate::error::Result;
+use crate::history::{
+    show_formatted, show_list_csv, show_list_markdown, show_list_nul, show_list_table, show_raw,
+    show_transaction_details, show_transactions_csv, show_transactions_markdown,
+    show_transactions_nul, transactions_json, DisplayOptions, HistoryEntry, OutputFormat,
+};
+use crate::json::Value;
+use crate::tui;
+use crate::vulns::VulnerabilityData;
+use crate::yaml;
+
+/// Output of `list` and `info` in one format. Entries are selected and ordered before they are
+/// rendered, so a new format only needs an implementation and a case in `renderer`. Applications
+/// embedding this crate can implement it to render entries their own way.
+pub trait Renderer {
+    /// One line or row per entry.
+    fn list(&self, entries: &[HistoryEntry], options: &DisplayOptions) -> Result<()>;
+
+    /// Entries with their packages, and their known vulnerabilities if vulnerability data is
+    /// given.
+    fn info(
+        &self,
+        entries: &[HistoryEntry],
+        options: &DisplayOptions,
+        vulnerabilities: Option<&VulnerabilityData>,
+    ) -> Result<()>;
+}
+
+struct TableRenderer;
+
+impl Renderer for TableRenderer {
+    fn list(&self, entries: &[HistoryEntry], options: &DisplayOptions) -> Result<()> {
+        show_list_table(entries, options);
+        Ok(())
+    }
+
+    fn info(
+        &self,
+        entries: &[HistoryEntry],
+        options: &DisplayOptions,
+        vulnerabilities: Option<&VulnerabilityData>,
+    ) -> Result<()> {
+        show_transaction_details(entries, options, vulnerabilities)
+    }
+}
+
+struct CsvRenderer;
+
+impl Renderer for CsvRenderer {
+    fn list(&self, entries: &[HistoryEntry], options: &DisplayOptions) -> Result<()> {
+        show_list_csv(entries, options);
+        Ok(())
+    }
+
+    fn info(
+        &self,
+        entries: &[HistoryEntry],
+        options: &DisplayOptions,
+        _: Option<&VulnerabilityData>,
+    ) -> Result<()> {
+        show_transactions_csv(entries, options);
+        Ok(())
+    }
+}
+
+struct MarkdownRenderer;
+
+impl Renderer for MarkdownRenderer {
+    fn list(&self, entries: &[HistoryEntry], options: &DisplayOptions) -> Result<()> {
+        show_list_markdown(entries, options);
+        Ok(())
+    }
+
+    fn info(
+        &self,
+        entries: &[HistoryEntry],
+        options: &DisplayOptions,
+        _: Option<&VulnerabilityData>,
+    ) -> Result<()> {
+        show_transactions_markdown(entries, options);
+        Ok(())
+    }
+}
+
+// Formats writing JSON values, summaries for `list` and full details for `info`.
+enum ValueRenderer {
+    Json,
+    JsonLines,
+    Yaml,
+}
+
+impl ValueRenderer {
+    fn write(&self, values: Vec<Value>) {
+        match self {
+            ValueRenderer::Json => println!("{}", Value::Array(values)),
+            ValueRenderer::JsonLines => values.iter().for_each(|v| println!("{v}")),
+            ValueRenderer::Yaml => values.iter().for_each(|v| print!("{}", yaml::document(v))),
+        }
+    }
+}
+
+impl Renderer for ValueRenderer {
+    fn list(&self, entries: &[HistoryEntry], options: &DisplayOptions) -> Result<()> {
+        let summaries = entries.iter().map(|e| e.to_summary_json(options.counting));
+        self.write(summaries.collect());
+        Ok(())
+    }
+
+    fn info(
+        &self,
+        entries: &[HistoryEntry],
+        options: &DisplayOptions,
+        vulnerabilities: Option<&VulnerabilityData>,
+    ) -> Result<()> {
+        self.write(transactions_json(entries, options, vulnerabilities));
+        Ok(())
+    }
+}
+
+// Only the IDs of entries, each followed by the terminator, for both commands.
+struct IdsRenderer(char);
+
+impl Renderer for IdsRenderer {
+    fn list(&self, entries: &[HistoryEntry], _: &DisplayOptions) -> Result<()> {
+        entries.iter().for_each(|e| print!("{}{}", e.id, self.0));
+        Ok(())
+    }
+
+    fn info(
+        &self,
+        entries: &[HistoryEntry],
+        options: &DisplayOptions,
+        _: Option<&VulnerabilityData>,
+    ) -> Result<()> {
+        self.list(entries, options)
+    }
+}
+
+// NUL terminated fields of list columns or of altered packages.
+struct NulRenderer;
+
+impl Renderer for NulRenderer {
+    fn list(&self, entries: &[HistoryEntry], options: &DisplayOptions) -> Result<()> {
+        show_list_nul(entries, options);
+        Ok(())
+    }
+
+    fn info(
+        &self,
+        entries: &[HistoryEntry],
+        options: &DisplayOptions,
+        _: Option<&VulnerabilityData>,
+    ) -> Result<()> {
+        show_transactions_nul(entries, options);
+        Ok(())
+    }
+}
+
+// One line per entry from a `--format` template, for both commands.
+struct TemplateRenderer(String);
+
+impl Renderer for TemplateRenderer {
+    fn list(&self, entries: &[HistoryEntry], options: &DisplayOptions) -> Result<()> {
+        show_formatted(entries, &self.0, options);
+        Ok(())
+    }
+
+    fn info(
+        &self,
+        entries: &[HistoryEntry],
+        options: &DisplayOptions,
+        _: Option<&VulnerabilityData>,
+    ) -> Result<()> {
+        show_formatted(entries, &self.0, options);
+        Ok(())
+    }
+}
+
+/// Entries browsed full screen as with `tui`, in the order they are given, with the details of
+/// the selected one below them. Both commands browse the same way.
+pub struct TuiRenderer;
+
+impl Renderer for TuiRenderer {
+    fn list(&self, entries: &[HistoryEntry], _: &DisplayOptions) -> Result<()> {
+        tui::browse_entries(entries.to_vec())
+    }
+
+    fn info(
+        &self,
+        entries: &[HistoryEntry],
+        options: &DisplayOptions,
+        _: Option<&VulnerabilityData>,
+    ) -> Result<()> {
+        self.list(entries, options)
+    }
+}
+
+// Log blocks as they were written for `info`, lists are rendered in the output format.
+struct RawRenderer(Box<dyn Renderer>);
+
+impl Renderer for RawRenderer {
+    fn list(&self, entries: &[HistoryEntry], options: &DisplayOptions) -> Result<()> {
+        self.0.list(entries, options)
+    }
+
+    fn info(
+        &self,
+        entries: &[HistoryEntry],
+        _: &DisplayOptions,
+        _: Option<&VulnerabilityData>,
+    ) -> Result<()> {
+        show_raw(entries)
+    }
+}
+
+fn output_renderer(output: OutputFormat) -> Box<dyn Renderer> {
+    match output {
+        OutputFormat::Csv => Box::new(CsvRenderer),
+        OutputFormat::Json => Box::new(ValueRenderer::Json),
+        OutputFormat::Jsonl => Box::new(ValueRenderer::JsonLines),
+        OutputFormat::Markdown => Box::new(MarkdownRenderer),
+        OutputFormat::Table => Box::new(TableRenderer),
+        OutputFormat::Tui => Box::new(TuiRenderer),
+        OutputFormat::Yaml => Box::new(ValueRenderer::Yaml),
+    }
+}
+
+// IDs only take precedence over a template, which takes precedence over NUL terminated fields,
+// raw output and then the output format. IDs are NUL terminated as well with `print0`.
+pub(crate) fn renderer(options: &DisplayOptions) -> Box<dyn Renderer> {
+    if options.ids_only {
+        return Box::new(IdsRenderer(if options.print0 { '\0' } else { '\n' }));
+    }
+    if let Some(format) = &options.format {
+        return Box::new(TemplateRenderer(format.clone()));
+    }
+    if options.print0 {
+        return Box::new(NulRenderer);
+    }
+    let renderer = output_renderer(options.output);
+    if options.raw {
+        return Box::new(RawRenderer(renderer));
+    }
+    renderer
+}
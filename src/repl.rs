@@ -0,0 +1,120 @@
+use crate::date::parse_date;
+use crate::error::{Error, Result};
+use crate::history::{
+    check_actions, history_entries, select_entries, show_list, show_transactions, DisplayOptions,
+    Filters, HistoryEntry,
+};
+use std::io::{self, BufRead, Write};
+
+const PROMPT: &str = "apt-history> ";
+const HELP: &str = "\
+<query>          refine the current selection with IDs or package names, as on the command line
+since <date>     keep transactions started at or after a date, e.g. `2024-06-01` or `2 weeks ago`
+until <date>     keep transactions started before a date
+action <action>  keep transactions containing one of the comma separated actions, e.g. `remove`
+info [query]     show details of the current selection, or of matching entries within it
+list [query]     list the current selection, or matching entries within it
+reset            start over from the transactions matching the command line filters
+help             show this help
+quit             exit";
+
+fn prompt() -> Result<()> {
+    let mut out = io::stdout();
+    out.write_all(PROMPT.as_bytes())
+        .and_then(|()| out.flush())
+        .map_err(|source| Error::OutputFile {
+            path: "stdout".to_string(),
+            source,
+        })
+}
+
+fn query_terms(terms: &[&str]) -> Option<Vec<String>> {
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.iter().map(|t| t.to_string()).collect())
+    }
+}
+
+fn within(selection: &[HistoryEntry], terms: &[&str]) -> Vec<HistoryEntry> {
+    match query_terms(terms) {
+        Some(query) => select_entries(selection, Some(query)),
+        None => selection.to_vec(),
+    }
+}
+
+// Filters of a `since`, `until` or `action` command with the rest of its line as the argument.
+fn command_filters(command: &str, argument: &str) -> std::result::Result<Filters, String> {
+    let mut filters = Filters::default();
+    match command {
+        "since" => filters.since = Some(parse_date(argument)?),
+        "until" => filters.until = Some(parse_date(argument)?),
+        _ => {
+            filters.action = argument
+                .split([',', ' '])
+                .filter(|a| !a.is_empty())
+                .map(str::to_string)
+                .collect();
+            if filters.action.is_empty() {
+                return Err(format!("`{command}` requires an action, e.g. `remove`"));
+            }
+            check_actions(&filters.action)?;
+        }
+    }
+    Ok(filters)
+}
+
+// Logs are parsed once and each query narrows down the previous selection, starting from the
+// entries matching the filters of the command line.
+pub fn repl(filters: &Filters, options: &DisplayOptions) -> Result<()> {
+    let entries = filters.apply(history_entries()?)?;
+    let mut selection = entries.clone();
+
+    prompt()?;
+    for line in io::stdin().lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            // Lines which are not valid UTF-8 are skipped, the ones after them are still read.
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                eprintln!("Error: the line is not valid UTF-8");
+                prompt()?;
+                continue;
+            }
+            Err(source) => {
+                return Err(Error::InputFile {
+                    path: "stdin".to_string(),
+                    source,
+                })
+            }
+        };
+        let terms: Vec<&str> = line.split_whitespace().collect();
+
+        match terms.split_first() {
+            None => show_list(selection.clone(), options)?,
+            Some((&"quit" | &"exit", _)) => return Ok(()),
+            Some((&"help", _)) => println!("{HELP}"),
+            Some((&"reset", _)) => {
+                selection = entries.clone();
+                show_list(selection.clone(), options)?;
+            }
+            Some((&"info", rest)) => show_transactions(&within(&selection, rest), options, None)?,
+            Some((&"list", rest)) => show_list(within(&selection, rest), options)?,
+            Some((&command @ ("since" | "until" | "action"), rest)) => {
+                match command_filters(command, &rest.join(" ")) {
+                    Ok(filters) => {
+                        selection = filters.apply(selection)?;
+                        show_list(selection.clone(), options)?;
+                    }
+                    Err(message) => eprintln!("{message}"),
+                }
+            }
+            Some(_) => {
+                selection = within(&selection, &terms);
+                show_list(selection.clone(), options)?;
+            }
+        }
+        prompt()?;
+    }
+    println!();
+    Ok(())
+}
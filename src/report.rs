@@ -0,0 +1,191 @@
+use crate::config::{Config, ReportProfile};
+use crate::error::{Error, Result};
+use crate::history::{history_entries, matching_entries, AlteredCount, Filters, HistoryEntry};
+use crate::storage;
+use chrono::prelude::*;
+use std::env;
+use std::fs::File;
+use std::process::{Command, Stdio};
+
+const DATE_FORMAT: &str = "%F %T";
+const STYLE: &str = "\
+body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #ccc; padding: 0.2em 0.6em; text-align: left; }
+th { background: #eee; cursor: pointer; }
+td.number { text-align: right; }
+section { margin-top: 2em; }";
+// Sorts the transaction table by the clicked column, numerically where both values are numbers.
+const SCRIPT: &str = "\
+document.querySelectorAll('#transactions th').forEach((th, column) => {
+  th.addEventListener('click', () => {
+    const body = th.closest('table').tBodies[0];
+    const ascending = th.dataset.order !== 'asc';
+    th.dataset.order = ascending ? 'asc' : 'desc';
+    const value = row => row.cells[column].dataset.value ?? row.cells[column].textContent;
+    const rows = Array.from(body.rows).sort((a, b) => {
+      const [x, y] = [value(a), value(b)];
+      const order = isNaN(x) || isNaN(y) ? x.localeCompare(y) : x - y;
+      return ascending ? order : -order;
+    });
+    rows.forEach(row => body.appendChild(row));
+  });
+});";
+const TITLE: &str = "apt-history report";
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn summary_row(entry: &HistoryEntry) -> String {
+    let actions: Vec<&str> = entry.sorted_actions().iter().map(|a| a.as_str()).collect();
+    format!(
+        "<tr><td class=\"number\"><a href=\"#transaction-{id}\">{id}</a></td><td>{}</td><td>{}</td>\
+         <td>{}</td><td>{}</td><td class=\"number\">{}</td></tr>",
+        escape(&entry.frontend),
+        escape(&entry.command_line),
+        entry.start_date.format(DATE_FORMAT),
+        escape(&actions.join(", ")),
+        entry.altered(AlteredCount::default()),
+        id = entry.id,
+    )
+}
+
+fn detail_section(entry: &HistoryEntry) -> Vec<String> {
+    let end_time = match entry.duration() {
+        Some(duration) => format!(
+            "{} ({} seconds)",
+            entry.end_date.format(DATE_FORMAT),
+            duration.num_seconds()
+        ),
+        None => "not finished".to_string(),
+    };
+    let mut lines = vec![
+        format!("<section id=\"transaction-{}\">", entry.id),
+        format!("<h2>Transaction {}</h2>", entry.id),
+        "<dl>".to_string(),
+        format!(
+            "<dt>Begin time</dt><dd>{}</dd>",
+            entry.start_date.format(DATE_FORMAT)
+        ),
+        format!("<dt>End time</dt><dd>{end_time}</dd>"),
+        format!("<dt>Tool</dt><dd>{}</dd>", escape(&entry.frontend)),
+        format!(
+            "<dt>Command line</dt><dd><code>{}</code></dd>",
+            escape(&entry.command_line)
+        ),
+    ];
+    if let Some(error) = &entry.error {
+        lines.push(format!("<dt>Error</dt><dd>{}</dd>", escape(error)));
+    }
+    lines.push("</dl>".to_string());
+
+    lines.push(
+        "<table><thead><tr><th>Action</th><th>Package</th><th>Version</th></tr></thead><tbody>"
+            .to_string(),
+    );
+    for action in entry.sorted_actions() {
+        let mut packages: Vec<String> = entry.affected[action]
+            .iter()
+            .flat_map(|(arch, pkgs)| pkgs.iter().map(move |pkg| format!("{pkg}:{arch}")))
+            .collect();
+        packages.sort();
+        for package in packages {
+            let version = entry.package_version(action, &package).unwrap_or_default();
+            lines.push(format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td></tr>",
+                escape(action),
+                escape(&package),
+                escape(version)
+            ));
+        }
+    }
+    lines.push("</tbody></table>".to_string());
+    lines.push("</section>".to_string());
+    lines
+}
+
+// Runs this program with the arguments of a report profile, failing with its exit status if it
+// fails. Output written to a file replaces it only once the report is complete.
+fn run_profile(name: &str, profile: &ReportProfile) -> Result<()> {
+    let run = |stdout: Stdio| {
+        let spawn_error = |source| Error::Spawn {
+            command: format!("report `{name}`"),
+            source,
+        };
+        let status = Command::new(env::current_exe().map_err(spawn_error)?)
+            .args(profile.arguments())
+            .stdout(stdout)
+            .status()
+            .map_err(spawn_error)?;
+        if status.success() {
+            return Ok(());
+        }
+        Err(Error::CommandFailed {
+            command: format!("report {name}"),
+            code: status.code(),
+            message: None,
+        })
+    };
+    let Some(path) = &profile.path else {
+        return run(Stdio::inherit());
+    };
+    storage::replace(path, |temporary| {
+        let file = File::create(temporary).map_err(|source| Error::OutputFile {
+            path: path.display().to_string(),
+            source,
+        })?;
+        run(Stdio::from(file))
+    })
+}
+
+// Standalone HTML page with a sortable table of transactions, most recent first, followed by the
+// details of each transaction. A single query term naming a report profile runs that profile
+// instead.
+pub fn report(query: Option<Vec<String>>, filters: &Filters) -> Result<()> {
+    if let Some([name]) = query.as_deref() {
+        if let Some(profile) = Config::load()?.report_profiles.get(name) {
+            return run_profile(name, profile);
+        }
+    }
+    let entries = if query.is_some() {
+        matching_entries(query)?
+    } else {
+        history_entries()?
+    };
+    let mut entries = filters.apply(entries)?;
+    entries.reverse();
+
+    let generated = Local::now().naive_local().format(DATE_FORMAT);
+    let mut lines = vec![
+        "<!DOCTYPE html>".to_string(),
+        "<html lang=\"en\">".to_string(),
+        "<head>".to_string(),
+        "<meta charset=\"utf-8\">".to_string(),
+        format!("<title>{TITLE}</title>"),
+        format!("<style>\n{STYLE}\n</style>"),
+        "</head>".to_string(),
+        "<body>".to_string(),
+        format!("<h1>{TITLE}</h1>"),
+        format!(
+            "<p>Generated {generated}, {} transactions.</p>",
+            entries.len()
+        ),
+        "<table id=\"transactions\"><thead><tr><th>ID</th><th>Tool</th><th>Command line</th>\
+         <th>Date and time</th><th>Action(s)</th><th>Altered</th></tr></thead><tbody>"
+            .to_string(),
+    ];
+    lines.extend(entries.iter().map(summary_row));
+    lines.push("</tbody></table>".to_string());
+    for entry in &entries {
+        lines.extend(detail_section(entry));
+    }
+    lines.push(format!("<script>\n{SCRIPT}\n</script>"));
+    lines.push("</body>".to_string());
+    lines.push("</html>".to_string());
+    println!("{}", lines.join("\n"));
+    Ok(())
+}
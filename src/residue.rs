@@ -0,0 +1,85 @@
+use crate::dpkg::status_packages;
+use crate::error::Result;
+use crate::history::{history_entries, LIST_DATE_FORMAT};
+use std::collections::{HashMap, HashSet};
+use stybulate::{Cell, Headers, Style, Table};
+
+const HEADERS: [&str; 3] = ["Package", "Removed in", "Date and time"];
+
+struct Removal {
+    date: String,
+    id: u32,
+}
+
+// Last transaction removing each package, unless it was purged or installed again afterwards.
+// Removals are kept when the later change overlaps with them, as its order is uncertain.
+fn last_removals() -> Result<HashMap<String, Removal>> {
+    let mut removals: HashMap<String, Removal> = HashMap::new();
+
+    for entry in history_entries()? {
+        for action in entry.sorted_actions() {
+            for (arch, pkgs) in entry.affected[action.as_str()].iter() {
+                for pkg in pkgs {
+                    let package = format!("{pkg}:{arch}");
+                    match action.as_str() {
+                        "Remove" => {
+                            removals.insert(
+                                package,
+                                Removal {
+                                    date: entry.start_date.format(LIST_DATE_FORMAT).to_string(),
+                                    id: entry.id,
+                                },
+                            );
+                        }
+                        "Install" | "Purge" | "Reinstall"
+                            if removals
+                                .get(&package)
+                                .is_none_or(|r| !entry.overlapping.contains(&r.id)) =>
+                        {
+                            removals.remove(&package);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(removals)
+}
+
+pub fn residue() -> Result<()> {
+    let removals = last_removals()?;
+    let residual: HashSet<String> = status_packages()
+        .iter()
+        .filter(|p| p.has_config_residue())
+        .map(|p| format!("{}:{}", p.name, p.arch))
+        .collect();
+
+    let mut packages: Vec<&String> = removals.keys().filter(|p| residual.contains(*p)).collect();
+    if packages.is_empty() {
+        println!("No configuration residue left by removed packages");
+        return Ok(());
+    }
+    packages.sort();
+
+    let rows: Vec<Vec<Cell>> = packages
+        .iter()
+        .map(|p| {
+            let removal = removals.get(*p).expect("unexpected entry miss in map");
+            vec![
+                Cell::from(p),
+                Cell::Int(removal.id as i32),
+                Cell::from(&removal.date),
+            ]
+        })
+        .collect();
+
+    let table = Table::new(Style::Presto, rows, Some(Headers::from(HEADERS.to_vec()))).tabulate();
+    println!("{}", table);
+
+    let names: Vec<&str> = packages.iter().map(|p| p.as_str()).collect();
+    println!();
+    println!("apt purge {}", names.join(" "));
+    Ok(())
+}
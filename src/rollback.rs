@@ -0,0 +1,78 @@
+use crate::error::{Error, Result};
+use crate::history::{history_entries, select_entries, HistoryEntry};
+use crate::undo::{apt_commands, check_availability, print_or_run, Origin, PackageTargets};
+use std::collections::BTreeMap;
+
+// Versions a package had before and after an action, none when it was not installed.
+fn states(
+    entry: &HistoryEntry,
+    action: &str,
+    package: &str,
+) -> Option<(Option<String>, Option<String>)> {
+    let versions = entry.package_versions(action, package);
+    let first = versions.first().copied().unwrap_or_default().to_string();
+    let last = versions.last().copied().unwrap_or_default().to_string();
+    match action {
+        "Install" => Some((None, Some(last))),
+        "Remove" | "Purge" => Some((Some(last), None)),
+        "Reinstall" | "Upgrade" | "Downgrade" => Some((Some(first), Some(last))),
+        _ => None,
+    }
+}
+
+// Changes returning packages to the state they were in after the given transaction: each package
+// changed since is brought back to what it was before the first of the later changes, unless the
+// later ones cancel each other out.
+fn restoration(later: &[HistoryEntry]) -> PackageTargets {
+    let mut changes: BTreeMap<String, (Option<String>, Option<String>)> = BTreeMap::new();
+    for entry in later {
+        for action in entry.sorted_actions() {
+            for (arch, pkgs) in entry.affected[action.as_str()].iter() {
+                for pkg in pkgs {
+                    let package = format!("{pkg}:{arch}");
+                    let Some((before, after)) = states(entry, action, &package) else {
+                        continue;
+                    };
+                    changes
+                        .entry(package)
+                        .and_modify(|(_, current)| *current = after.clone())
+                        .or_insert((before, after));
+                }
+            }
+        }
+    }
+    changes
+        .into_iter()
+        .filter(|(_, (before, current))| before != current)
+        .map(|(package, (before, _))| (package, before))
+        .collect()
+}
+
+pub fn rollback(query: Option<Vec<String>>, run: bool) -> Result<()> {
+    if !matches!(query.as_deref(), Some([_])) {
+        return Err(Error::Usage(
+            "`rollback` requires a single transaction ID".to_string(),
+        ));
+    }
+    let entries = history_entries()?;
+    let Some(target) = select_entries(&entries, query).pop() else {
+        println!("No matching transaction");
+        return Ok(());
+    };
+    let later: Vec<HistoryEntry> = entries
+        .into_iter()
+        .filter(|e| e.host == target.host && e.id > target.id)
+        .collect();
+    let targets = restoration(&later);
+    if targets.is_empty() {
+        println!("No changes since transaction {} to roll back", target.id);
+        return Ok(());
+    }
+    check_availability(&targets);
+    let origin = Origin {
+        entry: &target,
+        relation: "Rollback to",
+        tag: "rollback",
+    };
+    print_or_run(&apt_commands(&targets)?, run, &origin)
+}
@@ -0,0 +1,68 @@
+use crate::color;
+use crate::error::{Error, Result};
+use crate::history::{history_entries, DisplayOptions, Filters, HistoryEntry};
+use ansi_term::Colour;
+use regex::Regex;
+
+// Matches painted in place, so that the text stays the same without colors.
+fn highlight(pattern: &Regex, text: &str) -> String {
+    pattern
+        .replace_all(text, |captures: &regex::Captures| {
+            color::paint(Colour::Red.bold(), &captures[0])
+        })
+        .to_string()
+}
+
+// Packages of an entry whose name matches, as action and `name:arch` pairs sorted by package.
+fn matching_packages<'a>(pattern: &Regex, entry: &'a HistoryEntry) -> Vec<(&'a str, String)> {
+    let mut packages = vec![];
+    for action in entry.sorted_actions() {
+        for (arch, pkgs) in entry.affected[action.as_str()].iter() {
+            for pkg in pkgs.iter().filter(|p| pattern.is_match(p)) {
+                packages.push((action.as_str(), format!("{pkg}:{arch}")));
+            }
+        }
+    }
+    packages.sort_by(|a, b| a.1.cmp(&b.1));
+    packages
+}
+
+// Transactions whose command line or package names match a regular expression, with the matches
+// highlighted. Architectures are not matched against so that `:amd64` does not match everything.
+pub fn search(
+    query: Option<Vec<String>>,
+    filters: &Filters,
+    options: &DisplayOptions,
+) -> Result<()> {
+    let pattern = match query.as_deref() {
+        Some([pattern]) => Regex::new(pattern)
+            .map_err(|e| Error::Usage(format!("invalid regular expression `{pattern}`: {e}")))?,
+        _ => {
+            return Err(Error::Usage(
+                "`search` requires a single regular expression".to_string(),
+            ))
+        }
+    };
+
+    let mut entries = filters.apply(history_entries()?)?;
+    if !options.reverse {
+        entries.reverse();
+    }
+    for entry in entries {
+        let packages = matching_packages(&pattern, &entry);
+        if packages.is_empty() && !pattern.is_match(&entry.full_command_line) {
+            continue;
+        }
+        println!(
+            "{}  {}  {}",
+            color::bold(&entry.id.to_string()),
+            entry.start_date.format(options.list_date_format()),
+            highlight(&pattern, &entry.full_command_line)
+        );
+        for (action, package) in packages {
+            let (name, arch) = package.rsplit_once(':').unwrap_or((&package, ""));
+            println!("    {action} {}:{arch}", highlight(&pattern, name));
+        }
+    }
+    Ok(())
+}
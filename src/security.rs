@@ -0,0 +1,139 @@
+use crate::apt::{package_policies, PackagePolicy};
+use crate::error::Result;
+use crate::history::{history_entries, Filters, HistoryEntry, LIST_DATE_FORMAT};
+use std::collections::{BTreeSet, HashMap};
+use stybulate::{Cell, Headers, Style, Table};
+
+const HEADERS: [&str; 5] = [
+    "Transaction",
+    "Date and time",
+    "Security updates",
+    "Other upgrades",
+    "Unknown",
+];
+// Suites of security pockets end with this, e.g. `bookworm-security` or `jammy-security`.
+const SECURITY_SUITE_SUFFIX: &str = "-security";
+const UPGRADE_ACTION: &str = "Upgrade";
+
+// Where the version an upgrade installed came from, as far as apt still knows the version.
+#[derive(PartialEq)]
+enum Pocket {
+    Security,
+    Other,
+    Unknown,
+}
+
+fn pocket(policy: Option<&PackagePolicy>, version: &str) -> Pocket {
+    let Some(version) = policy.and_then(|p| p.version(version)) else {
+        return Pocket::Unknown;
+    };
+    let origins = version.origins();
+    if origins.is_empty() {
+        return Pocket::Unknown;
+    }
+    // Origins name the suite along with the component, e.g. `bookworm-security/main`.
+    let security = origins.iter().any(|o| {
+        o.split_whitespace()
+            .nth(1)
+            .and_then(|suite| suite.split('/').next())
+            .is_some_and(|suite| suite.ends_with(SECURITY_SUITE_SUFFIX))
+    });
+    if security {
+        Pocket::Security
+    } else {
+        Pocket::Other
+    }
+}
+
+// Upgraded `name:arch` packages of an entry with the version they were upgraded to.
+fn upgrades(entry: &HistoryEntry) -> Vec<(String, String)> {
+    let Some(pkg_map) = entry.affected.get(UPGRADE_ACTION) else {
+        return vec![];
+    };
+    let mut upgrades: Vec<(String, String)> = pkg_map
+        .iter()
+        .flat_map(|(arch, pkgs)| pkgs.iter().map(move |p| format!("{p}:{arch}")))
+        .map(|package| {
+            let version = entry
+                .package_versions(UPGRADE_ACTION, &package)
+                .last()
+                .copied()
+                .unwrap_or_default()
+                .to_string();
+            (package, version)
+        })
+        .collect();
+    upgrades.sort();
+    upgrades
+}
+
+// Upgrades per transaction split by whether the version came from a security pocket, according
+// to the sources apt has for the version now. Versions apt does not know anymore, e.g. after
+// later updates superseded them, cannot be told apart.
+pub fn security(filters: &Filters) -> Result<()> {
+    let entries: Vec<HistoryEntry> = filters
+        .apply(history_entries()?)?
+        .into_iter()
+        .filter(|e| e.affected.contains_key(UPGRADE_ACTION))
+        .collect();
+    let packages: BTreeSet<String> = entries
+        .iter()
+        .flat_map(|e| upgrades(e).into_iter().map(|(p, _)| p))
+        .collect();
+    let policies: HashMap<String, PackagePolicy> =
+        package_policies(&packages.into_iter().collect::<Vec<String>>())?
+            .into_iter()
+            .collect();
+
+    let mut rows: Vec<Vec<Cell>> = vec![];
+    let mut security_updates = 0;
+    let mut security_transactions = 0;
+    let mut last_security = None;
+    let mut unknown_updates = 0;
+    for entry in &entries {
+        let mut security = vec![];
+        let (mut other, mut unknown) = (0, 0);
+        for (package, version) in upgrades(entry) {
+            match pocket(policies.get(&package), &version) {
+                Pocket::Security => security.push(package),
+                Pocket::Other => other += 1,
+                Pocket::Unknown => unknown += 1,
+            }
+        }
+        if !security.is_empty() {
+            security_updates += security.len();
+            security_transactions += 1;
+            last_security = Some(entry.start_date);
+        }
+        unknown_updates += unknown;
+        rows.push(vec![
+            Cell::Int(entry.id as i32),
+            Cell::from(&entry.start_date.format(LIST_DATE_FORMAT).to_string()),
+            Cell::from(&security.join(", ")),
+            Cell::Int(other),
+            Cell::Int(unknown),
+        ]);
+    }
+
+    if rows.is_empty() {
+        println!("No recorded upgrades");
+        return Ok(());
+    }
+    let headers = Headers::from(HEADERS.to_vec());
+    println!(
+        "{}",
+        Table::new(Style::Presto, rows, Some(headers)).tabulate()
+    );
+    match last_security {
+        Some(last) => println!(
+            "{security_updates} security updates in {security_transactions} transactions, the \
+             last one on {}",
+            last.format(LIST_DATE_FORMAT)
+        ),
+        None => println!("No upgrades known to come from a security pocket"),
+    }
+    if unknown_updates > 0 {
+        println!("{unknown_updates} upgrades installed versions apt no longer knows the source of");
+    }
+    Ok(())
+}
@@ -0,0 +1,82 @@
+use crate::error::{Error, Result};
+
+// Functions shared by the shells: `ah-last` shows the last transaction or the given ones,
+// `ah-undo-last` prints the commands undoing the last transaction, or runs them with `--run`,
+// `ah-pick` selects a transaction with fzf and prints its ID, and a widget bound to Ctrl-X Ctrl-H
+// inserts the picked ID at the cursor.
+const BASH: &str = r#"# apt-history shell integration, enable with `eval "$(apt-history shell-init bash)"`.
+ah-last() { apt-history info "$@"; }
+ah-undo-last() { apt-history "$@" undo 0; }
+
+ah-pick() {
+    apt-history --no-pager --format '{id}\t{start_date}\t{tool} {command_line}' list |
+        fzf --delimiter '\t' --with-nth 2.. --preview 'apt-history --no-pager --color always info {1}' |
+        cut -f1
+}
+
+__ah_insert_pick() {
+    local id
+    id=$(ah-pick) || return
+    READLINE_LINE="${READLINE_LINE:0:READLINE_POINT}${id}${READLINE_LINE:READLINE_POINT}"
+    READLINE_POINT=$((READLINE_POINT + ${#id}))
+}
+bind -x '"\C-x\C-h": __ah_insert_pick'
+"#;
+const FISH: &str = r#"# apt-history shell integration, enable with `apt-history shell-init fish | source`.
+function ah-last
+    apt-history info $argv
+end
+
+function ah-undo-last
+    apt-history $argv undo 0
+end
+
+function ah-pick
+    apt-history --no-pager --format '{id}\t{start_date}\t{tool} {command_line}' list |
+        fzf --delimiter '\t' --with-nth 2.. --preview 'apt-history --no-pager --color always info {1}' |
+        cut -f1
+end
+
+function __ah_insert_pick
+    set -l id (ah-pick); or return
+    commandline --insert $id
+    commandline --function repaint
+end
+bind \cx\ch __ah_insert_pick
+"#;
+pub(crate) const SHELLS: [&str; 3] = ["bash", "fish", "zsh"];
+const ZSH: &str = r#"# apt-history shell integration, enable with `eval "$(apt-history shell-init zsh)"`.
+ah-last() { apt-history info "$@"; }
+ah-undo-last() { apt-history "$@" undo 0; }
+
+ah-pick() {
+    apt-history --no-pager --format '{id}\t{start_date}\t{tool} {command_line}' list |
+        fzf --delimiter '\t' --with-nth 2.. --preview 'apt-history --no-pager --color always info {1}' |
+        cut -f1
+}
+
+__ah_insert_pick() {
+    local id
+    id=$(ah-pick) || return
+    LBUFFER+=$id
+    zle reset-prompt
+}
+zle -N __ah_insert_pick
+bindkey '^X^H' __ah_insert_pick
+"#;
+
+pub fn shell_init(shell: Option<Vec<String>>) -> Result<()> {
+    let script = match shell.as_deref() {
+        Some([shell]) if shell == "bash" => BASH,
+        Some([shell]) if shell == "fish" => FISH,
+        Some([shell]) if shell == "zsh" => ZSH,
+        _ => {
+            return Err(Error::Usage(format!(
+                "`shell-init` requires a shell, one of: {}",
+                SHELLS.join(", ")
+            )))
+        }
+    };
+    print!("{script}");
+    Ok(())
+}
@@ -0,0 +1,221 @@
+use crate::bundle;
+use crate::dpkg::parse_paragraph;
+use crate::error::{Error, Result};
+use crate::history::matching_entries;
+use chrono::{Local, NaiveDateTime, TimeZone, Utc};
+use std::fs;
+
+// Archives kept by snapshot services, as the domain of their mirrors, the path of the archive on
+// them and the base URL of its snapshots.
+const ARCHIVES: [(&str, &str, &str); 4] = [
+    (
+        "debian.org",
+        "/debian",
+        "https://snapshot.debian.org/archive/debian",
+    ),
+    (
+        "debian.org",
+        "/debian-security",
+        "https://snapshot.debian.org/archive/debian-security",
+    ),
+    (
+        "ubuntu.com",
+        "/ubuntu",
+        "https://snapshot.ubuntu.com/ubuntu",
+    ),
+    (
+        "ubuntu.com",
+        "/ubuntu-ports",
+        "https://snapshot.ubuntu.com/ubuntu-ports",
+    ),
+];
+const DEBIAN_SNAPSHOT_PREFIX: &str = "snapshot.debian.org/archive/";
+const SOURCES_DIR: &str = "/etc/apt/sources.list.d";
+const SOURCES_LIST: &str = "/etc/apt/sources.list";
+const TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+const UBUNTU_SNAPSHOT_PREFIX: &str = "snapshot.ubuntu.com/";
+
+// A repository apt is configured to fetch packages from, in either sources format.
+struct Source {
+    components: Vec<String>,
+    kind: String,
+    signed_by: Option<String>,
+    suite: String,
+    uri: String,
+}
+
+// Sources of a `.list` file, one per `deb` or `deb-src` line.
+fn one_line_sources(content: &str) -> Vec<Source> {
+    let mut sources = vec![];
+    for line in content.lines() {
+        let line = line.split('#').next().unwrap_or_default();
+        let mut fields = line.split_whitespace();
+        let Some(kind) = fields.next() else {
+            continue;
+        };
+        let mut signed_by = None;
+        let mut next = fields.next();
+        if next.is_some_and(|f| f.starts_with('[')) {
+            // Options such as `[arch=amd64 signed-by=/usr/share/keyrings/key.gpg]`.
+            while let Some(option) = next {
+                let option = option.trim_start_matches('[');
+                if let Some(value) = option.trim_end_matches(']').strip_prefix("signed-by=") {
+                    signed_by = Some(value.to_string());
+                }
+                next = fields.next();
+                if option.ends_with(']') {
+                    break;
+                }
+            }
+        }
+        let (Some(uri), Some(suite)) = (next, fields.next()) else {
+            continue;
+        };
+        sources.push(Source {
+            components: fields.map(|c| c.to_string()).collect(),
+            kind: kind.to_string(),
+            signed_by,
+            suite: suite.to_string(),
+            uri: uri.to_string(),
+        });
+    }
+    sources
+}
+
+// Sources of a `.sources` file in the deb822 format, one per type, URI and suite of each
+// enabled paragraph.
+fn deb822_sources(content: &str) -> Vec<Source> {
+    let lines: Vec<&str> = content.lines().filter(|l| !l.starts_with('#')).collect();
+    let mut sources = vec![];
+    for paragraph in lines.join("\n").split("\n\n") {
+        let fields = parse_paragraph(paragraph);
+        if fields.get("Enabled").is_some_and(|e| e == "no") {
+            continue;
+        }
+        let values = |name: &str| -> Vec<String> {
+            fields
+                .get(name)
+                .map(|v| v.split_whitespace().map(|s| s.to_string()).collect())
+                .unwrap_or_default()
+        };
+        let components = values("Components");
+        for kind in values("Types") {
+            for uri in values("URIs") {
+                for suite in values("Suites") {
+                    sources.push(Source {
+                        components: components.clone(),
+                        kind: kind.clone(),
+                        signed_by: fields.get("Signed-By").cloned(),
+                        suite,
+                        uri: uri.clone(),
+                    });
+                }
+            }
+        }
+    }
+    sources
+}
+
+fn configured_sources() -> Vec<Source> {
+    let mut sources = vec![];
+    if let Ok(content) = fs::read_to_string(bundle::path(SOURCES_LIST)) {
+        sources.extend(one_line_sources(&content));
+    }
+    let mut files: Vec<_> = fs::read_dir(bundle::path(SOURCES_DIR))
+        .map(|dir| dir.flatten().map(|e| e.path()).collect())
+        .unwrap_or_default();
+    files.sort();
+    for file in files {
+        let Ok(content) = fs::read_to_string(&file) else {
+            continue;
+        };
+        match file.extension().and_then(|e| e.to_str()) {
+            Some("list") => sources.extend(one_line_sources(&content)),
+            Some("sources") => sources.extend(deb822_sources(&content)),
+            _ => {}
+        }
+    }
+    sources
+}
+
+// Base URL of the snapshots of the archive a URI points to, also for URIs which already point to
+// a snapshot.
+fn snapshot_base(uri: &str) -> Option<String> {
+    let location = uri.split_once("://").map_or(uri, |(_, l)| l);
+    let location = location.trim_end_matches('/');
+    for prefix in [DEBIAN_SNAPSHOT_PREFIX, UBUNTU_SNAPSHOT_PREFIX] {
+        if let Some(rest) = location.strip_prefix(prefix) {
+            let archive = rest.split('/').next()?;
+            return Some(format!("https://{prefix}{archive}"));
+        }
+    }
+    let (host, path) = location.split_once('/')?;
+    ARCHIVES.iter().find_map(|(domain, archive_path, base)| {
+        let on_mirror = host == *domain || host.ends_with(&format!(".{domain}"));
+        (on_mirror && format!("/{path}") == *archive_path).then(|| base.to_string())
+    })
+}
+
+fn utc_timestamp(date: &NaiveDateTime) -> String {
+    Local
+        .from_local_datetime(date)
+        .earliest()
+        .map(|d| d.with_timezone(&Utc).naive_utc())
+        .unwrap_or(*date)
+        .format(TIMESTAMP_FORMAT)
+        .to_string()
+}
+
+// Sources pinned to the state of their archives when a transaction ended, so that the packages
+// it installed can be fetched again. Snapshots only expire by age, so their Release files are
+// not checked for being valid. Sources not kept by a snapshot service are listed as comments.
+pub fn snapshot_sources(query: Option<Vec<String>>) -> Result<()> {
+    let entries = match query.as_deref() {
+        Some([_]) => matching_entries(query)?,
+        _ => {
+            return Err(Error::Usage(
+                "`snapshot-sources` requires a single transaction ID".to_string(),
+            ))
+        }
+    };
+    let Some(entry) = entries.last() else {
+        println!("No matching transaction");
+        return Ok(());
+    };
+    let sources = configured_sources();
+    if sources.is_empty() {
+        println!("No apt sources are configured");
+        return Ok(());
+    }
+
+    let timestamp = utc_timestamp(&entry.end_date);
+    println!(
+        "# Archives as of the end of transaction {} ({timestamp})",
+        entry.id
+    );
+    for source in sources {
+        let line = |uri: &str, options: &str| {
+            format!(
+                "{} {options}{uri} {} {}",
+                source.kind,
+                source.suite,
+                source.components.join(" ")
+            )
+            .trim_end()
+            .to_string()
+        };
+        match snapshot_base(&source.uri) {
+            Some(base) => {
+                let mut options = vec!["check-valid-until=no".to_string()];
+                // Keys embedded in deb822 sources do not fit on a line.
+                if let Some(key) = source.signed_by.as_ref().filter(|k| !k.contains('\n')) {
+                    options.push(format!("signed-by={key}"));
+                }
+                let options = format!("[{}] ", options.join(" "));
+                println!("{}", line(&format!("{base}/{timestamp}/"), &options));
+            }
+            None => println!("# No snapshots of: {}", line(&source.uri, "")),
+        }
+    }
+    Ok(())
+}
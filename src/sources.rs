@@ -0,0 +1,28 @@
+use crate::config::Config;
+use crate::error::Result;
+use crate::history::{AptHistoryLog, HistoryEntry};
+use crate::imports::ImportedHistories;
+use std::path::PathBuf;
+
+// A log transactions are read from, such as the apt history log. Sources only parse their own
+// format, IDs, hosts and users are filled in the same way for entries of all sources.
+pub(crate) trait HistorySource {
+    // Files of the source from the oldest to the current one.
+    fn files(&self) -> Result<Vec<PathBuf>>;
+
+    // Passes the entries of a file to `sink` in the order they are logged, numbering them from
+    // `index_start` on, and returns how many there were.
+    fn parse(
+        &self,
+        filename: &str,
+        index_start: u32,
+        config: &Config,
+        sink: &mut dyn FnMut(HistoryEntry),
+    ) -> Result<u32>;
+}
+
+// Sources entries are read from, in this order. Logs of other package managers or front-ends are
+// supported by adding a source implementing `HistorySource` here.
+pub(crate) fn registered() -> Vec<Box<dyn HistorySource>> {
+    vec![Box::new(AptHistoryLog), Box::new(ImportedHistories)]
+}
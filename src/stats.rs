@@ -0,0 +1,314 @@
+use crate::apt::package_policies;
+use crate::error::Result;
+use crate::history::{history_entries, AlteredCount, Filters, HistoryEntry};
+use chrono::prelude::*;
+use chrono::Duration;
+use std::collections::{BTreeMap, HashMap};
+use stybulate::{Cell, Headers, Style, Table};
+
+const ACTION_HEADERS: [&str; 3] = ["Action", "Transactions", "Packages"];
+const INSTALLING_ACTIONS: [&str; 4] = ["Install", "Upgrade", "Downgrade", "Reinstall"];
+const MONTH_FORMAT: &str = "%Y-%m";
+const ORIGIN_HEADERS: [&str; 3] = ["Origin", "Changes", "Share"];
+const NO_DELTA: &str = "-";
+const REINSTALL_ACTION: &str = "Reinstall";
+const REINSTALL_HEADERS: [&str; 3] = ["Package", "Reinstalls", "Last transaction"];
+const SECURITY_SUITE_MARKER: &str = "-security";
+const UNAVAILABLE_ORIGIN: &str = "(no longer available)";
+const WEEK_DATE_FORMAT: &str = "%F";
+const WEEKLY_HEADERS: [&str; 4] = ["Week of", "Transactions", "Changes", "Change"];
+
+// Totals over the entries, the busiest periods are the earliest ones in case of ties.
+struct Aggregates {
+    // Transactions and packages by action.
+    actions: BTreeMap<String, (usize, usize)>,
+    altered: usize,
+    busiest_day: Option<(NaiveDate, usize)>,
+    busiest_month: Option<(String, usize)>,
+    longest: Option<(u32, Duration)>,
+    transactions: usize,
+}
+
+// Key with the highest count, the smallest key for ties.
+fn busiest<K: Clone + Ord>(counts: &BTreeMap<K, usize>) -> Option<(K, usize)> {
+    counts
+        .iter()
+        .max_by(|a, b| a.1.cmp(b.1).then(b.0.cmp(a.0)))
+        .map(|(key, count)| (key.clone(), *count))
+}
+
+impl Aggregates {
+    fn new(entries: &[HistoryEntry]) -> Aggregates {
+        let mut actions: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+        let mut days: BTreeMap<NaiveDate, usize> = BTreeMap::new();
+        let mut months: BTreeMap<String, usize> = BTreeMap::new();
+        for entry in entries {
+            for (action, pkg_map) in entry.affected.iter() {
+                let counts = actions.entry(action.clone()).or_default();
+                counts.0 += 1;
+                counts.1 += pkg_map.values().map(|pkgs| pkgs.len()).sum::<usize>();
+            }
+            *days.entry(entry.start_date.date()).or_default() += 1;
+            let month = entry.start_date.format(MONTH_FORMAT).to_string();
+            *months.entry(month).or_default() += 1;
+        }
+        let longest = entries
+            .iter()
+            .filter_map(|e| Some((e.id, e.duration()?)))
+            .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)));
+        Aggregates {
+            actions,
+            altered: entries
+                .iter()
+                .map(|e| e.altered(AlteredCount::Packages))
+                .sum(),
+            busiest_day: busiest(&days),
+            busiest_month: busiest(&months),
+            longest,
+            transactions: entries.len(),
+        }
+    }
+
+    fn average_altered(&self) -> f64 {
+        if self.transactions == 0 {
+            0.0
+        } else {
+            self.altered as f64 / self.transactions as f64
+        }
+    }
+}
+
+fn show_aggregates(entries: &[HistoryEntry]) {
+    let aggregates = Aggregates::new(entries);
+    println!("Transactions: {}", aggregates.transactions);
+    println!(
+        "Average packages altered: {:.1}",
+        aggregates.average_altered()
+    );
+    if let Some((day, count)) = &aggregates.busiest_day {
+        println!(
+            "Busiest day: {} ({count} transactions)",
+            day.format(WEEK_DATE_FORMAT)
+        );
+    }
+    if let Some((month, count)) = &aggregates.busiest_month {
+        println!("Busiest month: {month} ({count} transactions)");
+    }
+    if let Some((id, duration)) = &aggregates.longest {
+        println!(
+            "Longest transaction: {id} ({} seconds)",
+            duration.num_seconds()
+        );
+    }
+    if aggregates.actions.is_empty() {
+        return;
+    }
+
+    let rows: Vec<Vec<Cell>> = aggregates
+        .actions
+        .iter()
+        .map(|(action, (transactions, packages))| {
+            vec![
+                Cell::from(action),
+                Cell::Int(*transactions as i32),
+                Cell::Int(*packages as i32),
+            ]
+        })
+        .collect();
+    println!();
+    println!("Transactions by action:");
+    let headers = Headers::from(ACTION_HEADERS.to_vec());
+    println!(
+        "{}",
+        Table::new(Style::Presto, rows, Some(headers)).tabulate()
+    );
+}
+
+// Versions installed by the entries in `name:arch` and version pairs.
+fn installed_versions(entries: &[HistoryEntry]) -> Vec<(String, String)> {
+    let mut installed = vec![];
+    for entry in entries {
+        for action in INSTALLING_ACTIONS {
+            let Some(pkg_map) = entry.affected.get(action) else {
+                continue;
+            };
+            for (arch, pkgs) in pkg_map.iter() {
+                for pkg in pkgs {
+                    let package = format!("{pkg}:{arch}");
+                    if let Some(version) = entry.package_version(action, &package) {
+                        installed.push((package, version.to_string()));
+                    }
+                }
+            }
+        }
+    }
+    installed
+}
+
+// Versions available from several repositories are attributed to the security pocket if that is
+// one of them, as point releases also ship packages which were security updates at the time.
+fn changes_by_origin(entries: &[HistoryEntry]) -> Result<BTreeMap<String, usize>> {
+    let installed = installed_versions(entries);
+    let mut packages: Vec<String> = installed.iter().map(|(p, _)| p.clone()).collect();
+    packages.sort();
+    packages.dedup();
+    let policies: HashMap<String, _> = package_policies(&packages)?.into_iter().collect();
+
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for (package, version) in installed {
+        let origins = policies
+            .get(&package)
+            .and_then(|p| p.version(&version))
+            .map(|v| v.origins())
+            .unwrap_or_default();
+        let origin = origins
+            .iter()
+            .find(|o| o.contains(SECURITY_SUITE_MARKER))
+            .or(origins.first())
+            .map(|o| o.as_str())
+            .unwrap_or(UNAVAILABLE_ORIGIN);
+        *counts.entry(origin.to_string()).or_default() += 1;
+    }
+    Ok(counts)
+}
+
+fn show_origins(entries: &[HistoryEntry]) -> Result<()> {
+    let counts = changes_by_origin(entries)?;
+    let total: usize = counts.values().sum();
+
+    let mut origins: Vec<(&String, &usize)> = counts.iter().collect();
+    origins.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+    let rows: Vec<Vec<Cell>> = origins
+        .iter()
+        .map(|(origin, count)| {
+            vec![
+                Cell::from(origin),
+                Cell::Int(**count as i32),
+                Cell::from(&format!("{:.1}%", **count as f64 * 100.0 / total as f64)),
+            ]
+        })
+        .collect();
+
+    println!("Package changes by origin:");
+    let table = Table::new(
+        Style::Presto,
+        rows,
+        Some(Headers::from(ORIGIN_HEADERS.to_vec())),
+    )
+    .tabulate();
+    println!("{}", table);
+    Ok(())
+}
+
+// Reinstalls are counted separately from installs, packages reinstalled repeatedly usually point
+// to debugging a broken installation.
+fn show_reinstalls(entries: &[HistoryEntry]) {
+    let mut reinstalls: BTreeMap<String, (usize, u32)> = BTreeMap::new();
+    let mut transactions = 0;
+    for entry in entries {
+        let Some(pkg_map) = entry.affected.get(REINSTALL_ACTION) else {
+            continue;
+        };
+        transactions += 1;
+        for (arch, pkgs) in pkg_map.iter() {
+            for pkg in pkgs {
+                let reinstall = reinstalls.entry(format!("{pkg}:{arch}")).or_default();
+                reinstall.0 += 1;
+                reinstall.1 = entry.id;
+            }
+        }
+    }
+
+    let total: usize = reinstalls.values().map(|(count, _)| count).sum();
+    println!("Reinstalls: {total} packages in {transactions} transactions");
+    if reinstalls.is_empty() {
+        return;
+    }
+
+    let mut packages: Vec<(&String, &(usize, u32))> = reinstalls.iter().collect();
+    packages.sort_by(|a, b| (b.1).0.cmp(&(a.1).0).then(a.0.cmp(b.0)));
+    let rows: Vec<Vec<Cell>> = packages
+        .iter()
+        .map(|(package, (count, id))| {
+            vec![
+                Cell::from(package),
+                Cell::Int(*count as i32),
+                Cell::Int(*id as i32),
+            ]
+        })
+        .collect();
+    let headers = Headers::from(REINSTALL_HEADERS.to_vec());
+    println!(
+        "{}",
+        Table::new(Style::Presto, rows, Some(headers)).tabulate()
+    );
+}
+
+// Percentage change from the previous value, undefined if there were no changes before.
+fn delta(previous: usize, current: usize) -> String {
+    if previous == 0 {
+        return NO_DELTA.to_string();
+    }
+    let change = (current as f64 - previous as f64) * 100.0 / previous as f64;
+    format!("{change:+.1}%")
+}
+
+// Weeks start on Monday, the current week is the last one and counts changes up to now. The week
+// before the first shown is only used for the first delta.
+fn show_weekly_trend(entries: &[HistoryEntry], weeks: u32) {
+    let today = Local::now().date_naive();
+    let weekday = today.weekday().num_days_from_monday() as i64;
+    let current_week = today - Duration::try_days(weekday).expect("invalid day count");
+    let week_starts: Vec<NaiveDate> = (0..=weeks as i64)
+        .rev()
+        .map(|w| current_week - Duration::try_weeks(w).expect("invalid week count"))
+        .collect();
+
+    let mut totals = vec![(0usize, 0usize); week_starts.len()];
+    for entry in entries {
+        let day = entry.start_date.date();
+        let Some(week) = week_starts.iter().rposition(|start| *start <= day) else {
+            continue;
+        };
+        totals[week].0 += 1;
+        totals[week].1 += entry.altered(AlteredCount::Packages);
+    }
+
+    let rows: Vec<Vec<Cell>> = week_starts
+        .iter()
+        .zip(totals.iter())
+        .enumerate()
+        .skip(1)
+        .map(|(index, (start, (transactions, changes)))| {
+            vec![
+                Cell::from(&start.format(WEEK_DATE_FORMAT).to_string()),
+                Cell::Int(*transactions as i32),
+                Cell::Int(*changes as i32),
+                Cell::from(&delta(totals[index - 1].1, *changes)),
+            ]
+        })
+        .collect();
+
+    println!("Package changes per week:");
+    let headers = Headers::from(WEEKLY_HEADERS.to_vec());
+    println!(
+        "{}",
+        Table::new(Style::Presto, rows, Some(headers)).tabulate()
+    );
+}
+
+pub fn stats(filters: &Filters, compare_weeks: Option<u32>) -> Result<()> {
+    let entries = filters.apply(history_entries()?)?;
+
+    if let Some(weeks) = compare_weeks {
+        show_weekly_trend(&entries, weeks);
+        return Ok(());
+    }
+
+    show_aggregates(&entries);
+    println!();
+    show_origins(&entries)?;
+    println!();
+    show_reinstalls(&entries);
+    Ok(())
+}
@@ -0,0 +1,121 @@
+use crate::error::{Error, Result};
+use crate::history::fnv_hash;
+use std::env;
+use std::ffi::OsString;
+use std::fs::{self, DirBuilder, File};
+use std::io;
+use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+use std::path::{self, Path, PathBuf};
+use std::process;
+
+const LOCK_DIR: &str = "apt-history-locks";
+const LOCK_SUFFIX: &str = ".lock";
+// Owned by the effective user of the process.
+const PROCESS_PATH: &str = "/proc/self";
+const TEMPORARY_SUFFIX: &str = ".tmp";
+
+fn sibling(path: &Path, prefix: &str, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(prefix);
+    name.push(path.file_name().expect("error getting file name"));
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+// Directory of lock files private to the user, in `$XDG_RUNTIME_DIR` if it is set. Otherwise it is
+// in the temporary directory, where it must not have been created by another user.
+fn lock_dir() -> Result<PathBuf> {
+    let uid = fs::metadata(PROCESS_PATH)
+        .map_err(|source| Error::InputFile {
+            path: PROCESS_PATH.to_string(),
+            source,
+        })?
+        .uid();
+    let dir = match env::var("XDG_RUNTIME_DIR") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir).join(LOCK_DIR),
+        _ => env::temp_dir().join(format!("{LOCK_DIR}-{uid}")),
+    };
+    let dir_error = |source| Error::OutputFile {
+        path: dir.display().to_string(),
+        source,
+    };
+    DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(&dir)
+        .map_err(dir_error)?;
+    let metadata = fs::symlink_metadata(&dir).map_err(dir_error)?;
+    if !metadata.is_dir() || metadata.uid() != uid {
+        return Err(dir_error(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "not a directory of the current user",
+        )));
+    }
+    Ok(dir)
+}
+
+fn write_error(path: &Path) -> impl FnOnce(io::Error) -> Error + '_ {
+    |source| Error::OutputFile {
+        path: path.display().to_string(),
+        source,
+    }
+}
+
+// Runs `update` holding an exclusive lock for the file. The lock is kept in a file of the lock
+// directory named after the path, as the file itself is replaced on writes and lock files next
+// to it would be left behind. Updates reading a file before writing it back have to happen
+// within the lock so that concurrent runs do not lose each other's changes.
+pub(crate) fn locked<T>(path: &Path, update: impl FnOnce() -> Result<T>) -> Result<T> {
+    let absolute = path::absolute(path).map_err(write_error(path))?;
+    let lock_path = lock_dir()?.join(format!(
+        "{}{LOCK_SUFFIX}",
+        fnv_hash(&absolute.to_string_lossy())
+    ));
+    let lock = File::create(&lock_path).map_err(write_error(&lock_path))?;
+    lock.lock().map_err(write_error(&lock_path))?;
+    // The lock is released when the file is closed.
+    update()
+}
+
+fn replace_unlocked(path: &Path, write: impl FnOnce(&Path) -> Result<()>) -> Result<()> {
+    let temporary = sibling(path, ".", &format!(".{}{TEMPORARY_SUFFIX}", process::id()));
+    if let Err(e) = write(&temporary) {
+        let _ = fs::remove_file(&temporary);
+        return Err(e);
+    }
+    fs::rename(&temporary, path).map_err(|e| {
+        let _ = fs::remove_file(&temporary);
+        write_error(path)(e)
+    })
+}
+
+// Replaces a file with the one `write` creates at the given temporary path, so that readers see
+// either the previous or the complete new content, even when several runs write at once. The
+// file is left as it was if `write` fails.
+pub(crate) fn replace(path: &Path, write: impl FnOnce(&Path) -> Result<()>) -> Result<()> {
+    locked(path, || replace_unlocked(path, write))
+}
+
+// Replaces the content of a file, or an empty one if it does not exist yet, with the one derived
+// from it by `update`, creating its directory if needed. The file is read and written within the
+// lock so that concurrent updates are applied one after the other.
+pub(crate) fn update(path: &Path, update: impl FnOnce(&str) -> Result<String>) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(write_error(dir))?;
+    }
+    locked(path, || {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(source) => {
+                return Err(Error::InputFile {
+                    path: path.display().to_string(),
+                    source,
+                })
+            }
+        };
+        let updated = update(&content)?;
+        replace_unlocked(path, |temporary| {
+            fs::write(temporary, &updated).map_err(write_error(path))
+        })
+    })
+}
@@ -0,0 +1,111 @@
+// Templates with `{name}` placeholders, `{{` and `}}` stand for literal braces and `\t`, `\n` and
+// `\\` for tabs, newlines and backslashes so that templates can be written inside shell quotes.
+enum Segment {
+    Literal(String),
+    Variable(String),
+}
+
+pub(crate) struct Template {
+    segments: Vec<Segment>,
+}
+
+impl Template {
+    pub(crate) fn parse(
+        template: &str,
+        variables: &[&str],
+    ) -> std::result::Result<Template, String> {
+        let mut segments = vec![];
+        let mut literal = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match (c, chars.peek()) {
+                ('{', Some('{')) | ('}', Some('}')) => {
+                    chars.next();
+                    literal.push(c);
+                }
+                ('\\', Some(&escaped @ ('t' | 'n' | '\\'))) => {
+                    chars.next();
+                    literal.push(match escaped {
+                        't' => '\t',
+                        'n' => '\n',
+                        _ => '\\',
+                    });
+                }
+                ('{', _) => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => return Err(format!("unmatched `{{` in template `{template}`")),
+                        }
+                    }
+                    if !variables.contains(&name.as_str()) {
+                        return Err(format!(
+                            "unknown template variable `{name}`, expected one of: {}",
+                            variables.join(", ")
+                        ));
+                    }
+                    segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    segments.push(Segment::Variable(name));
+                }
+                ('}', _) => return Err(format!("unmatched `}}` in template `{template}`")),
+                _ => literal.push(c),
+            }
+        }
+        segments.push(Segment::Literal(literal));
+        Ok(Template { segments })
+    }
+
+    pub(crate) fn render<F: Fn(&str) -> String>(&self, value: F) -> String {
+        self.segments
+            .iter()
+            .map(|s| match s {
+                Segment::Literal(l) => l.clone(),
+                Segment::Variable(v) => value(v),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VARIABLES: [&str; 2] = ["id", "tool"];
+
+    fn render(template: &str) -> String {
+        Template::parse(template, &VARIABLES)
+            .expect("error parsing template")
+            .render(|v| format!("<{v}>"))
+    }
+
+    fn parse_error(template: &str) -> String {
+        match Template::parse(template, &VARIABLES) {
+            Ok(_) => panic!("template `{template}` parsed"),
+            Err(e) => e,
+        }
+    }
+
+    #[test]
+    fn renders_variables_and_escapes() {
+        assert_eq!(render("{id}\\t{tool}\\n"), "<id>\t<tool>\n");
+        assert_eq!(render("{{id}} \\\\ \\x"), "{id} \\ \\x");
+    }
+
+    #[test]
+    fn rejects_unknown_variables() {
+        assert_eq!(
+            parse_error("{id} {user}"),
+            "unknown template variable `user`, expected one of: id, tool"
+        );
+        assert!(parse_error("{}").starts_with("unknown template variable ``"));
+    }
+
+    #[test]
+    fn rejects_unmatched_braces() {
+        assert_eq!(parse_error("{id"), "unmatched `{` in template `{id`");
+        assert_eq!(parse_error("id}"), "unmatched `}` in template `id}`");
+        assert_eq!(parse_error("{{id}"), "unmatched `}` in template `{{id}`");
+    }
+}
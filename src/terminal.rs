@@ -0,0 +1,72 @@
+use std::env;
+use std::fs::File;
+use std::io::{self, IsTerminal};
+use std::process::{Command, Stdio};
+
+// Set for processes whose output is paged, their stdout is meant for a terminal even though it is
+// a pipe to the pager.
+pub(crate) const PAGED_VARIABLE: &str = "APT_HISTORY_PAGED";
+pub(crate) const TTY_PATH: &str = "/dev/tty";
+
+pub(crate) fn stdout_is_terminal() -> bool {
+    io::stdout().is_terminal() || env::var_os(PAGED_VARIABLE).is_some()
+}
+
+// Output of `stty` run on the controlling terminal.
+fn stty(args: &[&str]) -> Option<String> {
+    let tty = File::open(TTY_PATH).ok()?;
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(Stdio::from(tty))
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout).ok()
+}
+
+// Rows and columns of the controlling terminal as reported by `stty`.
+fn stty_size() -> Option<(usize, usize)> {
+    let size = stty(&["size"])?;
+    let mut size = size.split_whitespace().map(|s| s.parse().ok());
+    Some((size.next()??, size.next()??))
+}
+
+// Settings of the controlling terminal before `raw_mode`, restored when dropped.
+pub(crate) struct RawMode(String);
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = stty(&[&self.0]);
+    }
+}
+
+// Puts the controlling terminal into a mode where keys are read as they are pressed, without
+// echo. Reads wait for a key and return all bytes available, so that a lone escape can be told
+// apart from the start of an escape sequence, which terminals send at once. A read returning no
+// bytes means the terminal was closed.
+pub(crate) fn raw_mode() -> Option<RawMode> {
+    let saved = stty(&["-g"])?;
+    stty(&["-icanon", "-echo", "min", "1", "time", "0"])?;
+    Some(RawMode(saved.trim().to_string()))
+}
+
+pub(crate) fn open_tty() -> io::Result<File> {
+    File::open(TTY_PATH)
+}
+
+fn env_size(variable: &str) -> Option<usize> {
+    env::var(variable).ok().and_then(|c| c.parse().ok())
+}
+
+// Width of the controlling terminal, from `COLUMNS` if set or as reported by `stty`.
+pub(crate) fn width() -> Option<usize> {
+    env_size("COLUMNS").or_else(|| stty_size().map(|(_, columns)| columns))
+}
+
+// Height of the controlling terminal, from `LINES` if set or as reported by `stty`.
+pub(crate) fn height() -> Option<usize> {
+    env_size("LINES").or_else(|| stty_size().map(|(rows, _)| rows))
+}
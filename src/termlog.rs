@@ -0,0 +1,87 @@
+use crate::error::{Error, Result};
+use crate::history::{log_files, open_log};
+use crate::incomplete;
+use crate::privileges;
+use chrono::NaiveDateTime;
+use regex::Regex;
+use std::collections::HashMap;
+use std::io::{self, BufRead};
+
+// Lines apt prints once packages are downloaded, e.g. `Fetched 12.3 MB in 4s (3,080 kB/s)`.
+const FETCHED_PATTERN: &str = r"Fetched (\S+ \S*B) in (\S+) \(([^)]+/s)\)";
+const LOG_STARTED_PREFIX: &str = "Log started: ";
+const LOG_DATE_FORMAT: &str = "%F  %T";
+const TERM_LOG_PATTERN: &str = r"^term\.log(?:\.([0-9]+)(?:\.gz)?)?$";
+
+// Packages downloaded by a transaction as reported by apt, in its units.
+pub(crate) struct Download {
+    size: String,
+    speed: String,
+    time: String,
+}
+
+impl Download {
+    pub(crate) fn describe(&self) -> String {
+        format!("{} in {} ({})", self.size, self.time, self.speed)
+    }
+}
+
+// Adds the downloads of a term log, counting the ones read so far in `read`.
+fn file_downloads(
+    filename: &str,
+    fetched: &Regex,
+    downloads: &mut HashMap<NaiveDateTime, Download>,
+    read: &mut u32,
+) -> io::Result<()> {
+    let mut started = None;
+    // Output of maintainer scripts is logged as it is, which need not be valid UTF-8.
+    for line in open_log(filename)?.split(b'\n') {
+        let line = String::from_utf8_lossy(&line?).to_string();
+        if let Some(date) = line.strip_prefix(LOG_STARTED_PREFIX) {
+            started = NaiveDateTime::parse_from_str(date.trim(), LOG_DATE_FORMAT).ok();
+            continue;
+        }
+        let (Some(start), Some(captures)) = (started, fetched.captures(&line)) else {
+            continue;
+        };
+        downloads.insert(
+            start,
+            Download {
+                size: captures[1].to_string(),
+                speed: captures[3].to_string(),
+                time: captures[2].to_string(),
+            },
+        );
+        *read += 1;
+    }
+    Ok(())
+}
+
+// Downloads by the start date of their transaction, which the sections of the term log share
+// with the history log. The term log is only readable by the `adm` group by default, without it
+// there are no downloads to show. Other term logs which cannot be read, such as truncated rotated
+// ones, are summarized like history logs.
+pub(crate) fn downloads() -> Result<HashMap<NaiveDateTime, Download>> {
+    let fetched = Regex::new(FETCHED_PATTERN).expect("error parsing fetched regex");
+    let mut downloads = HashMap::new();
+    // Without a readable log directory there are no transactions to show downloads of either.
+    let Ok(files) = log_files(TERM_LOG_PATTERN) else {
+        return Ok(downloads);
+    };
+    for file in files {
+        let filename = file.display().to_string();
+        let mut read = 0;
+        match file_downloads(&filename, &fetched, &mut downloads, &mut read) {
+            Ok(()) => (),
+            Err(e) if privileges::is_unavailable(&filename, &e) => (),
+            Err(source) => {
+                let error = Error::LogFile {
+                    path: filename.clone(),
+                    source,
+                };
+                incomplete::tolerate(&filename, read, error)?;
+            }
+        }
+    }
+    Ok(downloads)
+}
@@ -0,0 +1,83 @@
+use crate::error::Result;
+use crate::history::{history_entries, Filters, HistoryEntry};
+use clap::ValueEnum;
+use std::collections::BTreeMap;
+use stybulate::{Cell, Headers, Style, Table};
+
+const HEADERS: [&str; 5] = ["Period", "Transactions", "Installed", "Removed", "Upgraded"];
+const INSTALL_ACTION: &str = "Install";
+const REMOVING_ACTIONS: [&str; 2] = ["Remove", "Purge"];
+const UPGRADE_ACTION: &str = "Upgrade";
+
+// Periods transactions are grouped by in `timeline`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum GroupBy {
+    #[default]
+    Day,
+    // ISO weeks, which start on Monday.
+    Week,
+    Month,
+}
+
+impl GroupBy {
+    // Labels sort in chronological order.
+    fn format(&self) -> &'static str {
+        match self {
+            GroupBy::Day => "%F",
+            GroupBy::Week => "%G-W%V",
+            GroupBy::Month => "%Y-%m",
+        }
+    }
+}
+
+#[derive(Default)]
+struct Period {
+    installed: usize,
+    removed: usize,
+    transactions: usize,
+    upgraded: usize,
+}
+
+impl Period {
+    fn add(&mut self, entry: &HistoryEntry) {
+        self.transactions += 1;
+        for (action, pkg_map) in entry.affected.iter() {
+            let count: usize = pkg_map.values().map(|pkgs| pkgs.len()).sum();
+            if action == INSTALL_ACTION {
+                self.installed += count;
+            } else if REMOVING_ACTIONS.contains(&action.as_str()) {
+                self.removed += count;
+            } else if action == UPGRADE_ACTION {
+                self.upgraded += count;
+            }
+        }
+    }
+}
+
+// A row per period with transactions, from the oldest to the most recent one.
+pub fn timeline(filters: &Filters, group_by: GroupBy) -> Result<()> {
+    let mut periods: BTreeMap<String, Period> = BTreeMap::new();
+    for entry in filters.apply(history_entries()?)? {
+        let label = entry.start_date.format(group_by.format()).to_string();
+        periods.entry(label).or_default().add(&entry);
+    }
+
+    let rows: Vec<Vec<Cell>> = periods
+        .iter()
+        .map(|(label, period)| {
+            vec![
+                Cell::from(label),
+                Cell::Int(period.transactions as i32),
+                Cell::Int(period.installed as i32),
+                Cell::Int(period.removed as i32),
+                Cell::Int(period.upgraded as i32),
+            ]
+        })
+        .collect();
+    let headers = Headers::from(HEADERS.to_vec());
+    println!(
+        "{}",
+        Table::new(Style::Presto, rows, Some(headers)).tabulate()
+    );
+    Ok(())
+}
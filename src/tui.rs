@@ -0,0 +1,432 @@
+use crate::error::{Error, Result};
+use crate::history::{history_entries, HistoryEntry, LIST_DATE_FORMAT};
+use crate::terminal::{self, RawMode};
+use crate::undo::{apt_commands, reversal};
+use ansi_term::Style;
+use std::fs::File;
+use std::io::{self, IsTerminal, Read, Write};
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const CLEAR_SCREEN: &str = "\x1b[H\x1b[2J";
+const DEFAULT_HEIGHT: usize = 24;
+const DEFAULT_WIDTH: usize = 80;
+const ENTER_SCREEN: &str = "\x1b[?1049h\x1b[?25l";
+const HELP: &str =
+    "j/k move  g/G first/last  PgUp/PgDn page  / search  u copy undo  J/K scroll details  q quit";
+const INPUT_BUFFER_SIZE: usize = 64;
+// Path output errors are reported for, as the screen is drawn to stdout.
+const OUTPUT_PATH: &str = "stdout";
+const LEAVE_SCREEN: &str = "\x1b[?25h\x1b[?1049l";
+// Rows of the screen besides the panes: the list header, the pane separator and the status line.
+const FIXED_ROWS: usize = 3;
+
+enum Key {
+    Backspace,
+    Char(char),
+    Down,
+    End,
+    Enter,
+    Escape,
+    Home,
+    PageDown,
+    PageUp,
+    Up,
+}
+
+// Keys pressed since the last read, which returns escape sequences and the bytes of a UTF-8
+// character at once. Unknown sequences are left out.
+fn parse_keys(mut bytes: &[u8]) -> Vec<Key> {
+    let mut keys = vec![];
+    while let Some((&first, rest)) = bytes.split_first() {
+        bytes = rest;
+        let key = match first {
+            0x1b => match bytes.split_first() {
+                None => Some(Key::Escape),
+                Some((b'[', rest)) => {
+                    let end = rest
+                        .iter()
+                        .position(|b| b.is_ascii_alphabetic() || *b == b'~')
+                        .map_or(rest.len(), |end| end + 1);
+                    let (sequence, rest) = rest.split_at(end);
+                    bytes = rest;
+                    match sequence {
+                        b"A" => Some(Key::Up),
+                        b"B" => Some(Key::Down),
+                        b"H" | b"1~" => Some(Key::Home),
+                        b"F" | b"4~" => Some(Key::End),
+                        b"5~" => Some(Key::PageUp),
+                        b"6~" => Some(Key::PageDown),
+                        _ => None,
+                    }
+                }
+                Some((_, rest)) => {
+                    bytes = rest;
+                    None
+                }
+            },
+            b'\r' | b'\n' => Some(Key::Enter),
+            0x7f | 0x08 => Some(Key::Backspace),
+            byte if byte < 0x80 => Some(Key::Char(byte as char)),
+            first => {
+                let length = (first.leading_ones() as usize).saturating_sub(1);
+                let (continuation, rest) = bytes.split_at(length.min(bytes.len()));
+                bytes = rest;
+                let character = [&[first], continuation].concat();
+                String::from_utf8(character)
+                    .ok()
+                    .and_then(|c| c.chars().next())
+                    .map(Key::Char)
+            }
+        };
+        keys.extend(key);
+    }
+    keys
+}
+
+// Standard base64 with padding, as OSC 52 expects.
+fn base64(data: &[u8]) -> String {
+    let mut encoded = String::new();
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let bits = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for position in 0..4 {
+            if position <= chunk.len() {
+                let index = (bits >> (18 - 6 * position)) & 0x3f;
+                encoded.push(BASE64_ALPHABET[index as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+// At most as many characters as fit in the width, padded to it so that lines of the previous
+// frame are overwritten.
+fn fit(text: &str, width: usize) -> String {
+    let fitted: String = text.chars().take(width).collect();
+    format!("{fitted:width$}")
+}
+
+fn list_line(entry: &HistoryEntry) -> String {
+    format!(
+        "{:>5}  {}  {:<8}  {}",
+        entry.id,
+        entry.start_date.format(LIST_DATE_FORMAT),
+        entry.frontend,
+        entry.full_command_line
+    )
+}
+
+fn detail_lines(entry: &HistoryEntry) -> Vec<String> {
+    let mut lines = vec![format!(
+        "Transaction {}{}",
+        entry.id,
+        entry
+            .uuid
+            .as_ref()
+            .map_or(String::new(), |u| format!(" ({u})"))
+    )];
+    lines.push(match entry.logged_end_date() {
+        Some(end_date) => format!(
+            "Started {}, ended {}",
+            entry.start_date.format(LIST_DATE_FORMAT),
+            end_date.format(LIST_DATE_FORMAT)
+        ),
+        None => format!(
+            "Started {}, not finished",
+            entry.start_date.format(LIST_DATE_FORMAT)
+        ),
+    });
+    lines.push(format!("{} {}", entry.frontend, entry.full_command_line));
+    if let Some(user) = &entry.requested_by {
+        lines.push(format!("Requested by {user}"));
+    }
+    if let Some(error) = &entry.error {
+        lines.extend(error.lines().map(|l| format!("Error: {l}")));
+    }
+    for action in entry.sorted_actions() {
+        let mut packages: Vec<String> = entry.affected[action]
+            .iter()
+            .flat_map(|(arch, pkgs)| pkgs.iter().map(move |p| format!("{p}:{arch}")))
+            .collect();
+        packages.sort();
+        lines.push(format!("{action} ({})", packages.len()));
+        for package in packages {
+            let versions = entry.package_versions(action, &package).join(" -> ");
+            lines.push(format!("  {package} {versions}"));
+        }
+    }
+    lines
+}
+
+// Entries whose command line or package names contain the search text, ignoring case.
+fn matches(entry: &HistoryEntry, search: &str) -> bool {
+    let search = search.to_lowercase();
+    entry.full_command_line.to_lowercase().contains(&search)
+        || entry
+            .affected
+            .values()
+            .flat_map(|pkg_map| pkg_map.values())
+            .flatten()
+            .any(|p| p.to_lowercase().contains(&search))
+}
+
+struct Browser {
+    detail_offset: usize,
+    entries: Vec<HistoryEntry>,
+    // Indices of the entries matching the search, in the order of the entries.
+    filtered: Vec<usize>,
+    list_offset: usize,
+    message: Option<String>,
+    search: String,
+    searching: bool,
+    selected: usize,
+}
+
+impl Browser {
+    fn new(entries: Vec<HistoryEntry>) -> Browser {
+        let mut browser = Browser {
+            detail_offset: 0,
+            entries,
+            filtered: vec![],
+            list_offset: 0,
+            message: None,
+            search: String::new(),
+            searching: false,
+            selected: 0,
+        };
+        browser.filter();
+        browser
+    }
+
+    fn filter(&mut self) {
+        self.filtered = (0..self.entries.len())
+            .filter(|i| matches(&self.entries[*i], &self.search))
+            .collect();
+        self.selected = 0;
+        self.list_offset = 0;
+        self.detail_offset = 0;
+    }
+
+    fn current(&self) -> Option<&HistoryEntry> {
+        self.filtered.get(self.selected).map(|i| &self.entries[*i])
+    }
+
+    fn select(&mut self, selected: usize) {
+        self.selected = selected.min(self.filtered.len().saturating_sub(1));
+        self.detail_offset = 0;
+    }
+
+    // Copies the commands undoing the current entry to the clipboard of the terminal.
+    fn copy_undo(&mut self, out: &mut impl Write) -> io::Result<()> {
+        let Some(entry) = self.current() else {
+            return Ok(());
+        };
+        let commands: Vec<String> = match apt_commands(&reversal(entry)) {
+            Ok(commands) => commands.iter().map(|c| c.join(" ")).collect(),
+            Err(e) => {
+                self.message = Some(format!("Error: {e}"));
+                return Ok(());
+            }
+        };
+        if commands.is_empty() {
+            self.message = Some(format!("Transaction {} has no changes to undo", entry.id));
+            return Ok(());
+        }
+        let text = commands.join("\n");
+        write!(out, "\x1b]52;c;{}\x07", base64(text.as_bytes()))?;
+        self.message = Some(format!("Copied: {}", commands.join("; ")));
+        Ok(())
+    }
+
+    fn draw(&mut self, out: &mut impl Write) -> io::Result<()> {
+        let width = terminal::width().unwrap_or(DEFAULT_WIDTH);
+        let height = terminal::height().unwrap_or(DEFAULT_HEIGHT);
+        let panes = height.saturating_sub(FIXED_ROWS);
+        let list_height = (panes / 2).max(1);
+        let detail_height = panes.saturating_sub(list_height);
+        if self.selected < self.list_offset {
+            self.list_offset = self.selected;
+        } else if self.selected >= self.list_offset + list_height {
+            self.list_offset = self.selected + 1 - list_height;
+        }
+
+        let bold = Style::new().bold();
+        let mut screen = String::from(CLEAR_SCREEN);
+        let header = format!(
+            "{:>5}  {:<16}  {:<8}  {}",
+            "ID", "Date and time", "Tool", "Command line"
+        );
+        screen.push_str(&format!("{}\n", bold.paint(fit(&header, width))));
+        for row in 0..list_height {
+            let position = self.list_offset + row;
+            let line = match self.filtered.get(position) {
+                Some(i) => fit(&list_line(&self.entries[*i]), width),
+                None => fit("", width),
+            };
+            if position == self.selected && position < self.filtered.len() {
+                screen.push_str(&Style::new().reverse().paint(line).to_string());
+            } else {
+                screen.push_str(&line);
+            }
+            screen.push('\n');
+        }
+        screen.push_str(&format!("{}\n", fit(&"-".repeat(width), width)));
+        let details = self.current().map(detail_lines).unwrap_or_default();
+        self.detail_offset = self
+            .detail_offset
+            .min(details.len().saturating_sub(detail_height));
+        for row in 0..detail_height {
+            let line = details
+                .get(self.detail_offset + row)
+                .map_or(String::new(), |l| l.clone());
+            screen.push_str(&format!("{}\n", fit(&line, width)));
+        }
+
+        let status = if self.searching {
+            format!("/{}", self.search)
+        } else {
+            let position = format!(
+                "{}/{}",
+                (self.selected + 1).min(self.filtered.len()),
+                self.filtered.len()
+            );
+            let text = self.message.clone().unwrap_or(HELP.to_string());
+            format!("{position}  {text}")
+        };
+        screen.push_str(&bold.paint(fit(&status, width)).to_string());
+        out.write_all(screen.as_bytes())?;
+        out.flush()
+    }
+
+    // Handles a key, telling whether to go on.
+    fn handle(&mut self, key: Key, out: &mut impl Write) -> io::Result<bool> {
+        let page = terminal::height()
+            .unwrap_or(DEFAULT_HEIGHT)
+            .saturating_sub(FIXED_ROWS)
+            / 2;
+        self.message = None;
+        if self.searching {
+            match key {
+                Key::Enter => self.searching = false,
+                Key::Escape => {
+                    self.searching = false;
+                    self.search.clear();
+                    self.filter();
+                }
+                Key::Backspace => {
+                    self.search.pop();
+                    self.filter();
+                }
+                Key::Char(c) if !c.is_control() => {
+                    self.search.push(c);
+                    self.filter();
+                }
+                _ => (),
+            }
+            return Ok(true);
+        }
+        match key {
+            Key::Char('q') => return Ok(false),
+            Key::Escape if !self.search.is_empty() => {
+                self.search.clear();
+                self.filter();
+            }
+            Key::Char('/') => {
+                self.searching = true;
+                self.search.clear();
+                self.filter();
+            }
+            Key::Down | Key::Char('j') => self.select(self.selected + 1),
+            Key::Up | Key::Char('k') => self.select(self.selected.saturating_sub(1)),
+            Key::PageDown => self.select(self.selected + page.max(1)),
+            Key::PageUp => self.select(self.selected.saturating_sub(page.max(1))),
+            Key::Home | Key::Char('g') => self.select(0),
+            Key::End | Key::Char('G') => self.select(self.filtered.len()),
+            Key::Char('J') => self.detail_offset += 1,
+            Key::Char('K') => self.detail_offset = self.detail_offset.saturating_sub(1),
+            Key::Char('u') => self.copy_undo(out)?,
+            _ => (),
+        }
+        Ok(true)
+    }
+}
+
+fn output_error(source: io::Error) -> Error {
+    Error::OutputFile {
+        path: OUTPUT_PATH.to_string(),
+        source,
+    }
+}
+
+// Handles keys until quit is pressed or the terminal is closed.
+fn browse(browser: &mut Browser, tty: &mut File) -> Result<()> {
+    let mut out = io::stdout().lock();
+    browser.draw(&mut out).map_err(output_error)?;
+    let mut buffer = [0; INPUT_BUFFER_SIZE];
+    loop {
+        let read = match tty.read(&mut buffer) {
+            Ok(0) => return Ok(()),
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(source) => {
+                return Err(Error::InputFile {
+                    path: terminal::TTY_PATH.to_string(),
+                    source,
+                })
+            }
+        };
+        for key in parse_keys(&buffer[..read]) {
+            if !browser.handle(key, &mut out).map_err(output_error)? {
+                return Ok(());
+            }
+        }
+        browser.draw(&mut out).map_err(output_error)?;
+    }
+}
+
+// Browses the given entries full screen, the first one at the top, with the details of the
+// selected one below them. The undo commands are copied with OSC 52, which terminals such as
+// xterm, kitty and tmux pass on to the clipboard.
+pub(crate) fn browse_entries(entries: Vec<HistoryEntry>) -> Result<()> {
+    if !io::stdout().is_terminal() {
+        return Err(Error::Usage(
+            "browsing transactions requires a terminal".to_string(),
+        ));
+    }
+    let mut browser = Browser::new(entries);
+    let mut tty = terminal::open_tty().map_err(|source| Error::InputFile {
+        path: terminal::TTY_PATH.to_string(),
+        source,
+    })?;
+    let raw: Option<RawMode> = terminal::raw_mode();
+    if raw.is_none() {
+        return Err(Error::Usage(
+            "browsing transactions requires a terminal which `stty` can configure".to_string(),
+        ));
+    }
+    let mut out = io::stdout();
+    out.write_all(ENTER_SCREEN.as_bytes())
+        .map_err(output_error)?;
+    let result = browse(&mut browser, &mut tty);
+    // The screen is left even if drawing it failed, which the error is reported for.
+    let left = out
+        .write_all(LEAVE_SCREEN.as_bytes())
+        .and_then(|()| out.flush())
+        .map_err(output_error);
+    drop(raw);
+    result.and(left)
+}
+
+// Browses transactions newest first.
+pub fn tui() -> Result<()> {
+    let mut entries = history_entries()?;
+    entries.reverse();
+    browse_entries(entries)
+}
@@ -0,0 +1,250 @@
+use crate::apt::package_policies;
+use crate::dpkg::native_architecture;
+use crate::error::{Error, Result};
+use crate::history::{history_entries, matching_entries, HistoryEntry};
+use crate::imports;
+use crate::notes::{self, Note};
+use chrono::prelude::*;
+use std::collections::BTreeMap;
+use std::io::{self, Read, Write};
+use std::process::{Command, ExitStatus, Stdio};
+
+const ARCH_INDEPENDENT: &str = "all";
+const DOWNGRADE_OPTION: &str = "--allow-downgrades";
+const OUTPUT_BUFFER_SIZE: usize = 4096;
+// Suffix of packages to remove in `apt-get install` arguments.
+const REMOVAL_SUFFIX: char = '-';
+
+// Version a `name:arch` package is to be brought to, none for packages to remove and an empty
+// one for the candidate version if the version is not known.
+pub(crate) type PackageTargets = BTreeMap<String, Option<String>>;
+
+// Changes reverting a transaction: installed packages are removed, removed ones installed again
+// at the version they had and upgrades and downgrades moved back to the version before them.
+pub(crate) fn reversal(entry: &HistoryEntry) -> PackageTargets {
+    let mut targets = PackageTargets::new();
+    for action in entry.sorted_actions() {
+        for (arch, pkgs) in entry.affected[action.as_str()].iter() {
+            for pkg in pkgs {
+                let package = format!("{pkg}:{arch}");
+                let versions = entry.package_versions(action, &package);
+                let target = match action.as_str() {
+                    "Install" => None,
+                    "Remove" | "Purge" => Some(versions.last().copied().unwrap_or_default()),
+                    "Upgrade" | "Downgrade" => Some(versions.first().copied().unwrap_or_default()),
+                    _ => continue,
+                };
+                targets.insert(package, target.map(|v| v.to_string()));
+            }
+        }
+    }
+    targets
+}
+
+// Packages as apt names them, only those of foreign architectures are qualified.
+fn apt_name(package: &str, native_arch: &str) -> String {
+    match package.rsplit_once(':') {
+        Some((name, arch)) if arch == native_arch || arch == ARCH_INDEPENDENT => name.to_string(),
+        _ => package.to_string(),
+    }
+}
+
+// A single `apt-get install` bringing all packages to their targets at once when anything is to
+// be installed, as replaced packages cannot be installed before their replacements are removed
+// and the other way around.
+pub(crate) fn apt_commands(targets: &PackageTargets) -> Result<Vec<Vec<String>>> {
+    if targets.is_empty() {
+        return Ok(vec![]);
+    }
+    let native_arch = native_architecture()?;
+    let removals: Vec<String> = targets
+        .iter()
+        .filter(|(_, t)| t.is_none())
+        .map(|(p, _)| apt_name(p, &native_arch))
+        .collect();
+    if removals.len() == targets.len() {
+        let mut command = vec!["apt-get".to_string(), "remove".to_string()];
+        command.extend(removals);
+        return Ok(vec![command]);
+    }
+
+    let mut command = vec![
+        "apt-get".to_string(),
+        "install".to_string(),
+        DOWNGRADE_OPTION.to_string(),
+    ];
+    for (package, target) in targets {
+        let name = apt_name(package, &native_arch);
+        command.push(match target.as_deref() {
+            None => format!("{name}{REMOVAL_SUFFIX}"),
+            Some("") => name,
+            Some(version) => format!("{name}={version}"),
+        });
+    }
+    Ok(vec![command])
+}
+
+// Warns about target versions no repository provides anymore, apt cannot install those. Versions
+// are not checked without apt-cache, e.g. when reading a bundle on another distribution.
+pub(crate) fn check_availability(targets: &PackageTargets) {
+    let pinned: Vec<(&String, &String)> = targets
+        .iter()
+        .filter_map(|(p, t)| Some((p, t.as_ref().filter(|v| !v.is_empty())?)))
+        .collect();
+    let packages: Vec<String> = pinned.iter().map(|(p, _)| p.to_string()).collect();
+    let policies: BTreeMap<String, _> = match package_policies(&packages) {
+        Ok(policies) => policies.into_iter().collect(),
+        Err(e) => {
+            eprintln!("Warning: {e}, the availability of versions is not checked");
+            return;
+        }
+    };
+    for (package, version) in pinned {
+        let available = policies
+            .get(package)
+            .and_then(|p| p.version(version))
+            .is_some_and(|v| !v.is_local_only());
+        if !available {
+            eprintln!("Warning: {package} {version} is not available from any repository");
+        }
+    }
+}
+
+// Transaction the commands are derived from and how, e.g. `Undo of`, for the note added to the
+// transactions they make.
+pub(crate) struct Origin<'a> {
+    pub(crate) entry: &'a HistoryEntry,
+    pub(crate) relation: &'static str,
+    // Tag of the note, e.g. `undo`.
+    pub(crate) tag: &'static str,
+}
+
+// Runs a command passing its output through, which is also returned. Prompts are passed on as
+// they are written as apt does not end them with a newline.
+fn run_command(command: &[String]) -> Result<(ExitStatus, String)> {
+    let run_error = |source| Error::Spawn {
+        command: command[0].clone(),
+        source,
+    };
+    let mut child = Command::new(&command[0])
+        .args(&command[1..])
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(run_error)?;
+    let mut stdout = child.stdout.take().expect("error getting command output");
+    let mut output = vec![];
+    let mut buffer = [0u8; OUTPUT_BUFFER_SIZE];
+    loop {
+        let read = match stdout.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(run_error(e)),
+        };
+        let mut out = io::stdout().lock();
+        // The output is still collected if apt-history's own output is closed.
+        let _ = out.write_all(&buffer[..read]).and_then(|_| out.flush());
+        output.extend_from_slice(&buffer[..read]);
+    }
+    let status = child.wait().map_err(run_error)?;
+    Ok((status, String::from_utf8_lossy(&output).to_string()))
+}
+
+// Line apt ends its plan with, e.g. `0 upgraded, 1 newly installed, 0 to remove and 3 not
+// upgraded.`
+fn apt_summary(output: &str) -> Option<&str> {
+    output
+        .lines()
+        .rev()
+        .map(|l| l.trim())
+        .find(|l| l.contains(" newly installed, "))
+}
+
+// Notes the transactions apt logged since `started` as made from the origin, so that their info
+// shows what they were for.
+fn annotate(origin: &Origin, started: NaiveDateTime, summary: Option<&str>) -> Result<()> {
+    let key = origin
+        .entry
+        .uuid
+        .clone()
+        .unwrap_or_else(|| origin.entry.fingerprint());
+    let mut comment = format!(
+        "{} transaction {} ({key})",
+        origin.relation, origin.entry.id
+    );
+    if let Some(summary) = summary {
+        comment.push_str(&format!(": {summary}"));
+    }
+    for entry in history_entries()?
+        .iter()
+        .filter(|e| e.start_date >= started)
+    {
+        let mut note = Note::new(entry);
+        note.comment = Some(comment.clone());
+        note.tags = vec![origin.tag.to_string()];
+        notes::add(entry, note)?;
+        println!("Transaction {}: {comment}", entry.id);
+    }
+    Ok(())
+}
+
+// Prints the commands, or runs them one after the other until one fails. Transactions made by
+// running them are noted as made from the origin and the exit status of a failed command is
+// passed on.
+pub(crate) fn print_or_run(commands: &[Vec<String>], run: bool, origin: &Origin) -> Result<()> {
+    if !run {
+        commands.iter().for_each(|c| println!("{}", c.join(" ")));
+        return Ok(());
+    }
+    if imports::is_imported(origin.entry) {
+        return Err(Error::Usage(format!(
+            "transaction {}:{} was imported from another host, its commands can only be printed",
+            origin.entry.host, origin.entry.id
+        )));
+    }
+    // History logs have a resolution of seconds.
+    let started = Local::now()
+        .naive_local()
+        .with_nanosecond(0)
+        .expect("error truncating the current time");
+    for command in commands {
+        let (status, output) = run_command(command)?;
+        // Failed commands can also have made a transaction, which logs the error.
+        annotate(origin, started, apt_summary(&output))?;
+        if !status.success() {
+            return Err(Error::CommandFailed {
+                command: command.join(" "),
+                code: status.code(),
+                message: None,
+            });
+        }
+    }
+    Ok(())
+}
+
+pub fn undo(query: Option<Vec<String>>, run: bool) -> Result<()> {
+    let entries = match query.as_deref() {
+        Some([_]) => matching_entries(query)?,
+        _ => {
+            return Err(Error::Usage(
+                "`undo` requires a single transaction ID".to_string(),
+            ))
+        }
+    };
+    let Some(entry) = entries.last() else {
+        println!("No matching transaction");
+        return Ok(());
+    };
+    let targets = reversal(entry);
+    if targets.is_empty() {
+        println!("Transaction {} has no changes to undo", entry.id);
+        return Ok(());
+    }
+    check_availability(&targets);
+    let origin = Origin {
+        entry,
+        relation: "Undo of",
+        tag: "undo",
+    };
+    print_or_run(&apt_commands(&targets)?, run, &origin)
+}
@@ -0,0 +1,54 @@
+use crate::bundle;
+use std::collections::HashMap;
+use std::fs;
+
+const PASSWD_PATH: &str = "/etc/passwd";
+const ROOT_UID: &str = "0";
+const ROOT_USER: &str = "root";
+
+// User names by UID from the password database, empty if it cannot be read.
+pub(crate) fn usernames() -> HashMap<u32, String> {
+    let Ok(content) = fs::read_to_string(bundle::path(PASSWD_PATH)) else {
+        return HashMap::new();
+    };
+    content
+        .lines()
+        .filter_map(|line| {
+            // e.g. `alice:x:1000:1000:Alice,,,:/home/alice:/bin/bash`
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            let uid = fields.nth(1)?.parse().ok()?;
+            Some((uid, name.to_string()))
+        })
+        .collect()
+}
+
+// A `Requested-By` value such as `alice (1000)` with the name of the UID as it is now, the logged
+// name is kept if the UID is unknown, e.g. for a user that has been deleted since.
+pub(crate) fn resolve(requested_by: &str, usernames: &HashMap<u32, String>) -> String {
+    let (logged_name, uid) = match requested_by.rsplit_once(" (") {
+        Some((name, uid)) => (name, uid.trim_end_matches(')')),
+        None => ("", requested_by),
+    };
+    let Ok(uid) = uid.trim().parse::<u32>() else {
+        return requested_by.to_string();
+    };
+    let name = usernames.get(&uid).map_or(logged_name, |n| n.as_str());
+    if name.is_empty() {
+        uid.to_string()
+    } else {
+        format!("{name} ({uid})")
+    }
+}
+
+// Whether a transaction with the given `Requested-By` value was run by the user given by name or
+// UID. Transactions run as root without sudo have no `Requested-By` field.
+pub(crate) fn requested_by_matches(requested_by: Option<&str>, user: &str) -> bool {
+    let Some(requested_by) = requested_by else {
+        return user == ROOT_USER || user == ROOT_UID;
+    };
+    match requested_by.rsplit_once(" (") {
+        Some((name, uid)) => name == user || uid.trim_end_matches(')') == user,
+        None => requested_by == user,
+    }
+}
@@ -0,0 +1,87 @@
+use crate::bundle;
+use crate::error::Result;
+use crate::history::HistoryEntry;
+use crate::imports;
+use crate::storage;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+
+// Index of UUIDs by transaction fingerprint, one `<fingerprint> <uuid>` line per transaction.
+pub(crate) const INDEX_PATH: &str = "/var/lib/apt-history/uuids";
+const RANDOM_SOURCE: &str = "/dev/urandom";
+
+fn parse(content: &str) -> HashMap<String, String> {
+    content
+        .lines()
+        .filter_map(|l| l.split_once(' '))
+        .map(|(fingerprint, uuid)| (fingerprint.to_string(), uuid.trim().to_string()))
+        .collect()
+}
+
+// A random (version 4) UUID.
+fn generate() -> String {
+    let mut bytes = [0u8; 16];
+    File::open(RANDOM_SOURCE)
+        .and_then(|mut f| f.read_exact(&mut bytes))
+        .unwrap_or_else(|e| panic!("error reading `{RANDOM_SOURCE}`: {e}"));
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|b| format!("{b:02x}")).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+// Whether the index can be written, which needs root for the system-wide index. Without it
+// known UUIDs are still read but new transactions have none until the next run as root.
+fn is_writable(path: &Path) -> bool {
+    path.parent()
+        .is_some_and(|dir| fs::create_dir_all(dir).is_ok())
+        && File::options().append(true).create(true).open(path).is_ok()
+}
+
+// UUIDs assigned so far by transaction fingerprint.
+pub(crate) fn known() -> HashMap<String, String> {
+    parse(&fs::read_to_string(bundle::path(INDEX_PATH)).unwrap_or_default())
+}
+
+// Sets the UUIDs of entries, assigning new ones to transactions not seen before. UUIDs stay the
+// same when logs are rotated and IDs shift, so they can be used to refer to transactions from
+// other tools. Entries of imported histories keep the UUIDs of their archives, and transactions
+// of a bundle only have the UUIDs of its index as new ones would not be kept.
+pub(crate) fn assign(entries: &mut [HistoryEntry]) -> Result<()> {
+    let path = bundle::path(INDEX_PATH);
+    let mut uuids = known();
+    if !bundle::is_open()
+        && entries
+            .iter()
+            .any(|e| !imports::is_imported(e) && !uuids.contains_key(&e.fingerprint()))
+        && is_writable(&path)
+    {
+        storage::update(&path, |content| {
+            // Others may have assigned UUIDs since the index was read.
+            uuids = parse(content);
+            let mut content = content.to_string();
+            for entry in entries.iter().filter(|e| !imports::is_imported(e)) {
+                let fingerprint = entry.fingerprint();
+                if let Entry::Vacant(vacant) = uuids.entry(fingerprint.clone()) {
+                    let uuid = vacant.insert(generate());
+                    content.push_str(&format!("{fingerprint} {uuid}\n"));
+                }
+            }
+            Ok(content)
+        })?;
+    }
+    for entry in entries.iter_mut().filter(|e| !imports::is_imported(e)) {
+        entry.uuid = uuids.get(&entry.fingerprint()).cloned();
+    }
+    Ok(())
+}
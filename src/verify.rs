@@ -0,0 +1,127 @@
+use crate::dpkg::{native_architecture, status_packages};
+use crate::error::Result;
+use crate::history::{history_entries, HistoryEntry, LIST_DATE_FORMAT};
+use crate::imports;
+use std::collections::{BTreeMap, BTreeSet};
+use stybulate::{Cell, Headers, Style, Table};
+
+const HEADERS: [&str; 4] = ["Package", "History", "dpkg", "Last transaction"];
+// Architecture of packages which run on any, which apt logs with the native one.
+const INDEPENDENT_ARCH: &str = "all";
+const NOT_INSTALLED: &str = "not installed";
+const NO_RECORD: &str = "no record";
+
+// What the history says about a package: the version it left installed, none if it was removed
+// last, and the transaction that did so.
+struct Recorded {
+    id: u32,
+    version: Option<String>,
+}
+
+fn recorded_states(entries: &[HistoryEntry]) -> BTreeMap<String, Recorded> {
+    let mut states = BTreeMap::new();
+    for entry in entries {
+        for action in entry.sorted_actions() {
+            for (arch, pkgs) in entry.affected[action.as_str()].iter() {
+                for pkg in pkgs {
+                    let package = format!("{pkg}:{arch}");
+                    let version = match action.as_str() {
+                        "Install" | "Reinstall" | "Upgrade" | "Downgrade" => {
+                            entry.package_version(action, &package).map(str::to_string)
+                        }
+                        "Remove" | "Purge" => None,
+                        _ => continue,
+                    };
+                    states.insert(
+                        package,
+                        Recorded {
+                            id: entry.id,
+                            version,
+                        },
+                    );
+                }
+            }
+        }
+    }
+    states
+}
+
+// Cross-checks the state the recorded transactions leave packages in with the packages dpkg has
+// installed. Packages the history cannot explain were changed with dpkg directly, or by
+// transactions in logs which have been rotated away.
+pub fn verify() -> Result<()> {
+    let entries: Vec<HistoryEntry> = history_entries()?
+        .into_iter()
+        .filter(|e| !imports::is_imported(e))
+        .collect();
+    let recorded = recorded_states(&entries);
+    let native = native_architecture()?;
+    let installed: BTreeMap<String, String> = status_packages()
+        .into_iter()
+        .filter(|p| p.is_installed())
+        .map(|p| {
+            let arch = if p.arch == INDEPENDENT_ARCH {
+                &native
+            } else {
+                &p.arch
+            };
+            let version = p.fields.get("Version").cloned().unwrap_or_default();
+            (format!("{}:{arch}", p.name), version)
+        })
+        .collect();
+
+    let packages: BTreeSet<&String> = recorded.keys().chain(installed.keys()).collect();
+    let mut rows: Vec<Vec<Cell>> = vec![];
+    let mut unrecorded = 0;
+    for package in &packages {
+        let expected = recorded.get(*package);
+        let actual = installed.get(*package);
+        let expected_version = expected.and_then(|r| r.version.as_ref());
+        if expected_version == actual {
+            continue;
+        }
+        if expected.is_none() {
+            unrecorded += 1;
+        }
+        rows.push(vec![
+            Cell::from(package.as_str()),
+            Cell::from(match expected {
+                Some(r) => r.version.as_deref().unwrap_or(NOT_INSTALLED),
+                None => NO_RECORD,
+            }),
+            Cell::from(actual.map_or(NOT_INSTALLED, |v| v.as_str())),
+            Cell::from(&expected.map(|r| r.id.to_string()).unwrap_or_default()),
+        ]);
+    }
+
+    if rows.is_empty() {
+        println!(
+            "The history explains the state of all {} installed packages",
+            installed.len()
+        );
+        return Ok(());
+    }
+    let unexplained = rows.len();
+    let headers = Headers::from(HEADERS.to_vec());
+    println!(
+        "{}",
+        Table::new(Style::Presto, rows, Some(headers)).tabulate()
+    );
+    let oldest = entries
+        .first()
+        .map(|e| e.start_date.format(LIST_DATE_FORMAT).to_string());
+    println!(
+        "{} of {} packages are not in the state the history leaves them in",
+        unexplained,
+        packages.len()
+    );
+    if unrecorded > 0 {
+        println!(
+            "{unrecorded} installed packages were never changed by a recorded transaction{}",
+            oldest.map_or(String::new(), |d| format!(
+                ", they may predate the oldest one of {d}"
+            ))
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,94 @@
+use std::cmp::Ordering;
+
+// Debian package versions in `[epoch:]upstream_version[-debian_revision]` form, compared the
+// same way `dpkg --compare-versions` does.
+struct DebianVersion<'a> {
+    epoch: u64,
+    revision: &'a str,
+    upstream: &'a str,
+}
+
+impl DebianVersion<'_> {
+    fn parse(version: &str) -> DebianVersion<'_> {
+        let (epoch, rest) = match version.split_once(':') {
+            Some((epoch, rest)) => (epoch.parse().unwrap_or(0), rest),
+            None => (0, version),
+        };
+        let (upstream, revision) = rest.rsplit_once('-').unwrap_or((rest, ""));
+        DebianVersion {
+            epoch,
+            revision,
+            upstream,
+        }
+    }
+}
+
+// Tilde sorts before everything, even the end of a part, and letters sort before non-letters.
+fn char_order(c: Option<u8>) -> i32 {
+    match c {
+        Some(b'~') => -1,
+        Some(c) if c.is_ascii_digit() => 0,
+        Some(c) if c.is_ascii_alphabetic() => c as i32,
+        Some(c) => c as i32 + 256,
+        None => 0,
+    }
+}
+
+fn compare_part(a: &str, b: &str) -> Ordering {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    let (mut i, mut j) = (0, 0);
+
+    while i < a.len() || j < b.len() {
+        // Non-digit prefixes are compared character by character.
+        while (i < a.len() && !a[i].is_ascii_digit()) || (j < b.len() && !b[j].is_ascii_digit()) {
+            let ac = char_order(a.get(i).copied().filter(|c| !c.is_ascii_digit()));
+            let bc = char_order(b.get(j).copied().filter(|c| !c.is_ascii_digit()));
+            if ac != bc {
+                return ac.cmp(&bc);
+            }
+            if i < a.len() && !a[i].is_ascii_digit() {
+                i += 1;
+            }
+            if j < b.len() && !b[j].is_ascii_digit() {
+                j += 1;
+            }
+        }
+
+        // Followed by numeric comparison of digit runs.
+        while i < a.len() && a[i] == b'0' {
+            i += 1;
+        }
+        while j < b.len() && b[j] == b'0' {
+            j += 1;
+        }
+        let mut first_difference = Ordering::Equal;
+        while i < a.len() && a[i].is_ascii_digit() && j < b.len() && b[j].is_ascii_digit() {
+            if first_difference == Ordering::Equal {
+                first_difference = a[i].cmp(&b[j]);
+            }
+            i += 1;
+            j += 1;
+        }
+        if i < a.len() && a[i].is_ascii_digit() {
+            return Ordering::Greater;
+        }
+        if j < b.len() && b[j].is_ascii_digit() {
+            return Ordering::Less;
+        }
+        if first_difference != Ordering::Equal {
+            return first_difference;
+        }
+    }
+
+    Ordering::Equal
+}
+
+pub(crate) fn compare_versions(a: &str, b: &str) -> Ordering {
+    let a = DebianVersion::parse(a);
+    let b = DebianVersion::parse(b);
+
+    a.epoch
+        .cmp(&b.epoch)
+        .then_with(|| compare_part(a.upstream, b.upstream))
+        .then_with(|| compare_part(a.revision, b.revision))
+}
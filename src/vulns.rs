@@ -0,0 +1,211 @@
+use crate::bundle;
+use crate::config::InfoLayout;
+use crate::dpkg::status_packages;
+use crate::error::{Error, Result};
+use crate::history::HistoryEntry;
+use crate::i18n::tr;
+use crate::json::{self, Value};
+use crate::net;
+use crate::version::compare_versions;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fs;
+use stybulate::{Cell, Headers, Style, Table};
+
+pub(crate) const DEBIAN_SECURITY_TRACKER_URL: &str =
+    "https://security-tracker.debian.org/tracker/data/json";
+const HEADERS: [&str; 5] = ["Package", "Version", "Vulnerability", "Fixed in", "Urgency"];
+const INSTALLING_ACTIONS: [&str; 4] = ["Install", "Upgrade", "Downgrade", "Reinstall"];
+const NOT_AFFECTED_VERSION: &str = "0";
+const OPEN_STATUS: &str = "open";
+pub(crate) const OS_RELEASE_PATH: &str = "/etc/os-release";
+
+struct Vulnerability {
+    fixed_version: String,
+    id: String,
+    package: String,
+    urgency: String,
+    version: String,
+}
+
+// Known vulnerabilities in Debian security tracker format, keyed by source package and
+// vulnerability ID, with per-release fix status.
+pub struct VulnerabilityData {
+    binary_sources: HashMap<String, String>,
+    dataset: Value,
+    release: String,
+}
+
+// Codename of the running release, which testing and unstable may not have.
+fn release_codename() -> Result<String> {
+    let os_release = fs::read_to_string(bundle::path(OS_RELEASE_PATH)).map_err(|e| {
+        Error::Usage(format!(
+            "unable to determine the release from `{OS_RELEASE_PATH}` ({e}), use `--release`"
+        ))
+    })?;
+    os_release
+        .lines()
+        .find_map(|l| l.strip_prefix("VERSION_CODENAME="))
+        .map(|c| c.trim_matches('"').to_string())
+        .filter(|c| !c.is_empty())
+        .ok_or_else(|| Error::InvalidInput {
+            path: OS_RELEASE_PATH.to_string(),
+            message: "no `VERSION_CODENAME` to look up vulnerabilities for, use `--release`"
+                .to_string(),
+        })
+}
+
+fn read_dataset(source: &str) -> Result<String> {
+    if source.starts_with("http://") || source.starts_with("https://") {
+        net::fetch(source)
+    } else {
+        fs::read_to_string(source).map_err(|e| Error::InputFile {
+            path: source.to_string(),
+            source: e,
+        })
+    }
+}
+
+impl VulnerabilityData {
+    pub(crate) fn load(source: &str, release: Option<String>) -> Result<VulnerabilityData> {
+        let release = match release {
+            Some(release) => release,
+            None => release_codename()?,
+        };
+        let dataset = json::parse(&read_dataset(source)?).map_err(|e| Error::InvalidInput {
+            path: source.to_string(),
+            message: format!("error parsing vulnerability data: {e}"),
+        })?;
+
+        // Vulnerabilities are tracked against source packages.
+        let binary_sources = status_packages()
+            .into_iter()
+            .filter_map(|p| {
+                let source = p.source_name()?;
+                Some((p.name, source))
+            })
+            .collect();
+
+        Ok(VulnerabilityData {
+            binary_sources,
+            dataset,
+            release,
+        })
+    }
+
+    fn package_vulnerabilities(
+        &self,
+        package: &str,
+        name: &str,
+        version: &str,
+    ) -> Vec<Vulnerability> {
+        let source = self
+            .binary_sources
+            .get(name)
+            .map(|s| s.as_str())
+            .unwrap_or(name);
+        let Some(issues) = self.dataset.get(source).and_then(|s| s.as_object()) else {
+            return vec![];
+        };
+
+        let mut vulnerabilities = vec![];
+        for (id, issue) in issues {
+            let Some(release) = issue.get("releases").and_then(|r| r.get(&self.release)) else {
+                continue;
+            };
+            let status = release
+                .get("status")
+                .and_then(|s| s.as_str())
+                .unwrap_or_default();
+            let fixed_version = release.get("fixed_version").and_then(|v| v.as_str());
+
+            let affected = match fixed_version {
+                Some(NOT_AFFECTED_VERSION) => false,
+                Some(fixed) => compare_versions(version, fixed) == Ordering::Less,
+                None => status == OPEN_STATUS,
+            };
+            if !affected {
+                continue;
+            }
+
+            vulnerabilities.push(Vulnerability {
+                fixed_version: fixed_version.unwrap_or_default().to_string(),
+                id: id.clone(),
+                package: package.to_string(),
+                urgency: release
+                    .get("urgency")
+                    .and_then(|u| u.as_str())
+                    .unwrap_or_default()
+                    .to_string(),
+                version: version.to_string(),
+            });
+        }
+        vulnerabilities
+    }
+
+    fn vulnerabilities(&self, entry: &HistoryEntry) -> Vec<Vulnerability> {
+        let mut vulnerabilities = vec![];
+        for action in INSTALLING_ACTIONS {
+            let Some(pkg_map) = entry.affected.get(action) else {
+                continue;
+            };
+            for (arch, pkgs) in pkg_map.iter() {
+                for pkg in pkgs {
+                    let package = format!("{pkg}:{arch}");
+                    let Some(version) = entry.package_version(action, &package) else {
+                        continue;
+                    };
+                    vulnerabilities.extend(self.package_vulnerabilities(&package, pkg, version));
+                }
+            }
+        }
+        vulnerabilities.sort_by(|a, b| a.package.cmp(&b.package).then(a.id.cmp(&b.id)));
+        vulnerabilities
+    }
+
+    pub(crate) fn to_json(&self, entry: &HistoryEntry) -> Value {
+        let vulnerabilities = self
+            .vulnerabilities(entry)
+            .into_iter()
+            .map(|v| {
+                Value::Object(vec![
+                    ("package".to_string(), Value::String(v.package)),
+                    ("version".to_string(), Value::String(v.version)),
+                    ("vulnerability".to_string(), Value::String(v.id)),
+                    ("fixed_in".to_string(), Value::String(v.fixed_version)),
+                    ("urgency".to_string(), Value::String(v.urgency)),
+                ])
+            })
+            .collect();
+        Value::Array(vulnerabilities)
+    }
+
+    pub(crate) fn show(&self, entry: &HistoryEntry, layout: &InfoLayout) {
+        let vulnerabilities = self.vulnerabilities(entry);
+        if vulnerabilities.is_empty() {
+            println!(
+                "{} {}",
+                layout.header(tr("Known Vulnerabilities:")),
+                tr("none")
+            );
+            return;
+        }
+
+        let rows: Vec<Vec<Cell>> = vulnerabilities
+            .iter()
+            .map(|v| {
+                vec![
+                    Cell::from(&v.package),
+                    Cell::from(&v.version),
+                    Cell::from(&v.id),
+                    Cell::from(&v.fixed_version),
+                    Cell::from(&v.urgency),
+                ]
+            })
+            .collect();
+        let table =
+            Table::new(Style::Presto, rows, Some(Headers::from(HEADERS.to_vec()))).tabulate();
+        println!("{}", layout.header(tr("Known Vulnerabilities:")));
+        println!("{}", table);
+    }
+}
@@ -0,0 +1,146 @@
+// Minimal YAML writing for JSON values, strings are always double quoted so that they never need
+// to be told apart from other scalars.
+use crate::json::Value;
+
+const DOCUMENT_START: &str = "---";
+const INDENT: usize = 2;
+// Plain scalars YAML 1.1 readers take for nulls and booleans rather than strings.
+const SPECIAL_SCALARS: [&str; 11] = [
+    "null", "true", "false", "yes", "no", "on", "off", "y", "n", "nan", "inf",
+];
+
+// Keys which read back as the same string unquoted. Keys starting with a digit, dot or dash could
+// be numbers.
+fn is_plain_key(key: &str) -> bool {
+    key.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+        && !SPECIAL_SCALARS.contains(&key.to_ascii_lowercase().as_str())
+}
+
+fn key(key: &str) -> String {
+    if is_plain_key(key) {
+        key.to_string()
+    } else {
+        Value::String(key.to_string()).to_string()
+    }
+}
+
+fn is_collection(value: &Value) -> bool {
+    match value {
+        Value::Array(items) => !items.is_empty(),
+        Value::Object(fields) => !fields.is_empty(),
+        _ => false,
+    }
+}
+
+fn scalar(value: &Value) -> String {
+    match value {
+        Value::Array(_) => "[]".to_string(),
+        Value::Object(_) => "{}".to_string(),
+        _ => value.to_string(),
+    }
+}
+
+fn write_value(out: &mut String, value: &Value, indent: usize) {
+    let padding = " ".repeat(indent);
+    match value {
+        Value::Object(fields) => {
+            for (k, v) in fields {
+                if is_collection(v) {
+                    out.push_str(&format!("{padding}{}:\n", key(k)));
+                    write_value(out, v, indent + INDENT);
+                } else {
+                    out.push_str(&format!("{padding}{}: {}\n", key(k), scalar(v)));
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                if is_collection(item) {
+                    // Nested collections start on the line of their item marker.
+                    let mut nested = String::new();
+                    write_value(&mut nested, item, indent + INDENT);
+                    out.push_str(&format!("{padding}- {}", &nested[indent + INDENT..]));
+                } else {
+                    out.push_str(&format!("{padding}- {}\n", scalar(item)));
+                }
+            }
+        }
+        _ => out.push_str(&format!("{padding}{}\n", scalar(value))),
+    }
+}
+
+pub(crate) fn document(value: &Value) -> String {
+    let mut out = format!("{DOCUMENT_START}\n");
+    write_value(&mut out, value, 0);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn object(fields: Vec<(&str, Value)>) -> Value {
+        Value::Object(
+            fields
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn quotes_special_strings() {
+        let value = object(vec![
+            ("a", Value::String("null".to_string())),
+            ("b", Value::String("yes".to_string())),
+            ("c", Value::String("~".to_string())),
+            ("d", Value::String("1.0".to_string())),
+            ("e", Value::String("".to_string())),
+        ]);
+        assert_eq!(
+            document(&value),
+            "---\na: \"null\"\nb: \"yes\"\nc: \"~\"\nd: \"1.0\"\ne: \"\"\n"
+        );
+    }
+
+    #[test]
+    fn writes_special_values_unquoted() {
+        let value = object(vec![
+            ("a", Value::Null),
+            ("b", Value::Bool(false)),
+            ("c", Value::Number(2.5)),
+            ("d", Value::Array(vec![])),
+            ("e", Value::Object(vec![])),
+        ]);
+        assert_eq!(
+            document(&value),
+            "---\na: null\nb: false\nc: 2.5\nd: []\ne: {}\n"
+        );
+    }
+
+    #[test]
+    fn quotes_keys_read_as_other_scalars() {
+        let value = object(vec![
+            ("null", Value::Null),
+            ("Yes", Value::Null),
+            ("1.0", Value::Null),
+            ("-x", Value::Null),
+            ("libc6:amd64", Value::Null),
+            ("plain_key-1.x", Value::Null),
+        ]);
+        assert_eq!(
+            document(&value),
+            "---\n\"null\": null\n\"Yes\": null\n\"1.0\": null\n\"-x\": null\n\
+             \"libc6:amd64\": null\nplain_key-1.x: null\n"
+        );
+    }
+
+    #[test]
+    fn escapes_strings_spanning_lines() {
+        let value = Value::Array(vec![Value::String("a\n# b: c".to_string())]);
+        assert_eq!(document(&value), "---\n- \"a\\n# b: c\"\n");
+    }
+}